@@ -0,0 +1,63 @@
+use crate::config::CopyTarget;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Copies `text` with `wl-copy`, which keeps serving the selection after
+/// yeet exits (GTK's own clipboard would vanish with the window).
+pub fn copy_text(text: &str, target: CopyTarget) {
+    for args in wl_copy_args(target) {
+        if let Err(e) = run_wl_copy(args, text) {
+            eprintln!("Failed to copy with wl-copy: {e}");
+            return;
+        }
+    }
+}
+
+/// One `wl-copy` invocation per selection `target` covers; the text is
+/// piped on stdin so it is never mistaken for an option.
+fn wl_copy_args(target: CopyTarget) -> Vec<&'static [&'static str]> {
+    match target {
+        CopyTarget::Clipboard => vec![&[]],
+        CopyTarget::Primary => vec![&["--primary"]],
+        CopyTarget::Both => vec![&[], &["--primary"]],
+    }
+}
+
+fn run_wl_copy(args: &[&str], text: &str) -> std::io::Result<()> {
+    let mut child = Command::new("wl-copy")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_target_uses_plain_wl_copy() {
+        let empty: &[&str] = &[];
+        assert_eq!(wl_copy_args(CopyTarget::Clipboard), vec![empty]);
+    }
+
+    #[test]
+    fn primary_target_passes_primary_flag() {
+        assert_eq!(wl_copy_args(CopyTarget::Primary), vec![&["--primary"]]);
+    }
+
+    #[test]
+    fn both_targets_copy_twice() {
+        let empty: &[&str] = &[];
+        assert_eq!(
+            wl_copy_args(CopyTarget::Both),
+            vec![empty, &["--primary"][..]]
+        );
+    }
+}
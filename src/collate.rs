@@ -0,0 +1,204 @@
+//! Locale-aware alphabetical ordering of app names.
+//!
+//! Uses the C library's collation for the user's locale (`LC_ALL`,
+//! `LC_COLLATE`, then `LANG`). When that locale is `C`/`POSIX` or not
+//! installed, names are compared case-insensitively with common diacritics
+//! folded, so "Über" still sorts with the U's rather than after "Zebra".
+
+use std::ffi::CString;
+
+pub struct Collator {
+    locale: Option<libc::locale_t>,
+}
+
+impl Collator {
+    pub fn from_env() -> Self {
+        let name = ["LC_ALL", "LC_COLLATE", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::for_locale(&name)
+    }
+
+    fn for_locale(name: &str) -> Self {
+        let is_c = matches!(name.split('.').next(), Some("" | "C" | "POSIX"));
+        let locale = if is_c {
+            None
+        } else {
+            CString::new(name).ok().and_then(|name| {
+                // SAFETY: `name` is a valid C string; a null base asks for a
+                // fresh locale object, which `Drop` frees.
+                let locale = unsafe {
+                    libc::newlocale(libc::LC_COLLATE_MASK, name.as_ptr(), std::ptr::null_mut())
+                };
+                (!locale.is_null()).then_some(locale)
+            })
+        };
+        Self { locale }
+    }
+
+    /// A key whose byte order is the collation order of `text`.
+    pub fn sort_key(&self, text: &str) -> Vec<u8> {
+        self.locale
+            .and_then(|locale| transform(locale, text))
+            .unwrap_or_else(|| folded_key(text))
+    }
+}
+
+impl Drop for Collator {
+    fn drop(&mut self) {
+        if let Some(locale) = self.locale {
+            // SAFETY: `locale` came from `newlocale` and is freed only here.
+            unsafe { libc::freelocale(locale) };
+        }
+    }
+}
+
+/// `strxfrm` under `locale`, switched in for this thread only.
+fn transform(locale: libc::locale_t, text: &str) -> Option<Vec<u8>> {
+    let text = CString::new(text).ok()?;
+
+    // SAFETY: `locale` is a live locale object; the previous thread locale
+    // is restored before returning. `strxfrm` writes at most `buf.len()`
+    // bytes and returns the full length it needs.
+    unsafe {
+        let previous = libc::uselocale(locale);
+        let needed = libc::strxfrm(std::ptr::null_mut(), text.as_ptr(), 0);
+        let mut buf = vec![0u8; needed + 1];
+        let written = libc::strxfrm(buf.as_mut_ptr().cast(), text.as_ptr(), buf.len());
+        libc::uselocale(previous);
+
+        buf.truncate(written.min(needed));
+        Some(buf)
+    }
+}
+
+/// Case- and accent-insensitive key, tie-broken by the lowercase name and
+/// then the name itself so equal-looking names still sort consistently.
+fn folded_key(text: &str) -> Vec<u8> {
+    let lower = text.to_lowercase();
+    let mut folded = String::with_capacity(lower.len());
+    for c in lower.chars() {
+        match fold_char(c) {
+            Folded::One(base) => folded.push(base),
+            Folded::Two(a, b) => {
+                folded.push(a);
+                folded.push(b);
+            }
+        }
+    }
+
+    let mut key = folded.into_bytes();
+    key.push(0);
+    key.extend_from_slice(lower.as_bytes());
+    key.push(0);
+    key.extend_from_slice(text.as_bytes());
+    key
+}
+
+enum Folded {
+    One(char),
+    Two(char, char),
+}
+
+/// Base letter of a lowercase Latin-1/Latin Extended-A letter (plus `ё`).
+fn fold_char(c: char) -> Folded {
+    let base = match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' | 'ð' => 'd',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        'ё' => 'е',
+        'ß' => return Folded::Two('s', 's'),
+        'æ' => return Folded::Two('a', 'e'),
+        'œ' => return Folded::Two('o', 'e'),
+        'þ' => return Folded::Two('t', 'h'),
+        other => other,
+    };
+    Folded::One(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(collator: &Collator, names: &[&'static str]) -> Vec<&'static str> {
+        let mut names = names.to_vec();
+        names.sort_by_cached_key(|name| collator.sort_key(name));
+        names
+    }
+
+    #[test]
+    fn c_locale_folds_case_and_accents() {
+        let collator = Collator::for_locale("C.UTF-8");
+        assert!(collator.locale.is_none());
+
+        assert_eq!(
+            sorted(
+                &collator,
+                &[
+                    "Zebra",
+                    "Über",
+                    "apple",
+                    "Éclair",
+                    "Ångström",
+                    "ember",
+                    "Straße",
+                    "Strasse"
+                ]
+            ),
+            [
+                "Ångström",
+                "apple",
+                "Éclair",
+                "ember",
+                "Strasse",
+                "Straße",
+                "Über",
+                "Zebra"
+            ]
+        );
+    }
+
+    #[test]
+    fn c_locale_orders_cyrillic_alphabetically() {
+        let collator = Collator::for_locale("POSIX");
+
+        assert_eq!(
+            sorted(&collator, &["Яндекс", "ёлка", "Браузер", "Firefox", "Ель"]),
+            ["Firefox", "Браузер", "ёлка", "Ель", "Яндекс"]
+        );
+    }
+
+    #[test]
+    fn missing_locale_falls_back_to_folding() {
+        let collator = Collator::for_locale("xx_YY.UTF-8");
+        assert!(collator.locale.is_none());
+        assert_eq!(sorted(&collator, &["Zoom", "über"]), ["über", "Zoom"]);
+    }
+
+    #[test]
+    fn folded_key_breaks_ties_deterministically() {
+        let collator = Collator::for_locale("");
+        assert_eq!(
+            sorted(&collator, &["resume", "Résumé", "résumé", "Resume"]),
+            ["Resume", "resume", "Résumé", "résumé"]
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 const DEFAULT_CONFIG: &str = include_str!("../defaults/config.toml");
+/// Site-wide defaults set by an administrator, under each user's config.
+const SYSTEM_CONFIG_PATH: &str = "/etc/yeet/config.toml";
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -13,6 +17,9 @@ pub struct Config {
     pub search: SearchConfig,
     #[serde(default)]
     pub apps: AppsConfig,
+    /// `[triggers]`: query prefixes that switch the search to another mode.
+    #[serde(default)]
+    pub triggers: BTreeMap<String, TriggerMode>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,44 +30,456 @@ pub struct GeneralConfig {
     pub initial_results: usize,
     #[serde(default = "default_terminal")]
     pub terminal: String,
+    /// Runs custom commands that need a shell; see `Config::shell`.
+    #[serde(default = "default_shell")]
+    pub shell: String,
+    #[serde(default)]
+    pub stay_open: bool,
+    #[serde(default)]
+    pub wrap_navigation: bool,
+    #[serde(default = "default_true")]
+    pub enter_launches_top: bool,
+    #[serde(default)]
+    pub monitor: Option<MonitorSelector>,
+    #[serde(default)]
+    pub monitor_fallback: MonitorFallback,
+    #[serde(default = "default_cancel_keys")]
+    pub cancel_keys: Vec<String>,
+    #[serde(default)]
+    pub copy_target: CopyTarget,
+    #[serde(default)]
+    pub multiline_paste: MultilinePaste,
+    #[serde(default = "default_empty_order")]
+    pub empty_order: Vec<ListOrder>,
+    #[serde(default)]
+    pub launch_prefix: String,
+    /// Run after every successful launch; see `desktop::post_launch_hook`.
+    #[serde(default)]
+    pub post_launch_hook: String,
+    /// Where launch history is kept instead of `~/.local/share/yeet`; see
+    /// `Config::data_dir`.
+    #[serde(default)]
+    pub data_dir: Option<String>,
+    #[serde(default)]
+    pub history_format: HistoryFormat,
+    #[serde(default = "default_true")]
+    pub accessibility: bool,
+    #[serde(default = "default_launch_debounce_ms")]
+    pub launch_debounce_ms: u64,
+    /// Let launched apps take focus by passing them an activation token.
+    #[serde(default = "default_true")]
+    pub focus_on_launch: bool,
+    /// Launch apps under `nice`; see `desktop::with_priority`.
+    #[serde(default)]
+    pub launch_nice: Option<Niceness>,
+    /// Launch apps under `ionice` in this scheduling class.
+    #[serde(default)]
+    pub launch_ionice: Option<IoClass>,
+    /// Listen for commands on `control::socket_path()`.
+    #[serde(default)]
+    pub control_socket: bool,
+}
+
+/// Expands a leading `~` to `home` and `$VAR`/`${VAR}` through `var`.
+/// Variables that aren't set are left as written.
+pub(crate) fn expand_path(
+    raw: &str,
+    home: Option<&Path>,
+    var: impl Fn(&str) -> Option<String>,
+) -> PathBuf {
+    let mut expanded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    if let Some(home) = home {
+        if rest == "~" || rest.starts_with("~/") {
+            expanded.push_str(&home.to_string_lossy());
+            rest = &rest[1..];
+        }
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, written_len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 3),
+                None => ("", 1),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end + 1)
+            }
+        };
+        let written = &rest[start..start + written_len];
+        match (!name.is_empty()).then(|| var(name)).flatten() {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(written),
+        }
+        rest = &rest[start + written_len..];
+    }
+    expanded.push_str(rest);
+    PathBuf::from(expanded)
+}
+
+/// How the launch history file is stored: `history.txt` with a tab between
+/// each launch's time and app, or `history.json`, an array of records,
+/// which holds any app id as is.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryFormat {
+    #[default]
+    Tsv,
+    Json,
+}
+
+/// Where Ctrl+Shift+C puts the selected entry: the regular clipboard, the
+/// primary selection (middle-click paste), or both.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyTarget {
+    #[default]
+    Clipboard,
+    Primary,
+    Both,
+}
+
+/// What text pasted into the search entry keeps of its line breaks: the
+/// lines joined with spaces, or only the first line (dmenu mode always).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MultilinePaste {
+    #[default]
+    Join,
+    FirstLine,
+}
+
+/// A key for ordering the list shown before typing; `general.empty_order`
+/// applies them in turn, each breaking the ties of the ones before it.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListOrder {
+    /// `apps.favorites` first.
+    Favorites,
+    /// Most recently launched first; apps never launched last.
+    Recent,
+    /// Alphabetical by name.
+    Alpha,
+}
+
+/// `general.monitor`: either a position in GDK's monitor list or a connector
+/// name like `"DP-1"`, which stays stable across reconnects.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum MonitorSelector {
+    Index(u32),
+    Connector(String),
+}
+
+/// Where to open when `general.monitor` isn't connected: on the active
+/// monitor as if it were unset, or on the nearest index (the last monitor
+/// for an index past the end; connector names fall back to the active one).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitorFallback {
+    #[default]
+    Active,
+    Clamp,
+}
+
+/// A `nice` value for launched apps, from -20 (most favourable) to 19.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "i64")]
+pub struct Niceness(i8);
+
+impl Niceness {
+    pub fn get(self) -> i8 {
+        self.0
+    }
+}
+
+impl TryFrom<i64> for Niceness {
+    type Error = String;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match i8::try_from(value) {
+            Ok(n) if (-20..=19).contains(&n) => Ok(Self(n)),
+            _ => Err(format!("nice value {value} is not between -20 and 19")),
+        }
+    }
+}
+
+/// An `ionice` scheduling class for launched apps: 0 none, 1 realtime,
+/// 2 best-effort, 3 idle.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "i64")]
+pub struct IoClass(u8);
+
+impl IoClass {
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<i64> for IoClass {
+    type Error = String;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match u8::try_from(value) {
+            Ok(class) if class <= 3 => Ok(Self(class)),
+            _ => Err(format!("ionice class {value} is not between 0 and 3")),
+        }
+    }
+}
+
+impl fmt::Display for MonitorSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Connector(name) => write!(f, "\"{name}\""),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AppearanceConfig {
     #[serde(default = "default_width")]
     pub width: i32,
+    #[serde(default)]
+    pub width_mode: WidthMode,
+    #[serde(default = "default_max_width")]
+    pub max_width: i32,
     #[serde(default = "default_anchor_top")]
     pub anchor_top: i32,
     #[serde(default = "default_row_height")]
     pub row_height: i32,
+    #[serde(default)]
+    pub row_spacing: i32,
+    #[serde(default)]
+    pub fixed_list_size: bool,
+    #[serde(default)]
+    pub show_sections: bool,
+    #[serde(default)]
+    pub group_by_category: bool,
     #[serde(default = "default_true")]
     pub show_shortcuts: bool,
     #[serde(default = "default_true")]
     pub show_descriptions: bool,
+    #[serde(default)]
+    pub description_as_tooltip: bool,
+    #[serde(default = "default_true")]
+    pub show_icons: bool,
+    /// Names only, in tight rows; see `MINIMAL_APPEARANCE`.
+    #[serde(default)]
+    pub minimal: bool,
+    #[serde(default)]
+    pub icon_theme: Option<String>,
+    #[serde(default)]
+    pub icon_scaling: IconScaling,
+    #[serde(default)]
+    pub theme: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Which size `appearance.icon_theme` lookups take when the theme has no
+/// icon at the size shown: the next larger one, scaled down (crisper), or
+/// whichever is closest, even if it has to be scaled up.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IconScaling {
+    #[default]
+    Down,
+    Nearest,
+}
+
+/// `fixed` keeps the window at `width`; `content` widens it to fit the
+/// widest visible result, up to `max_width`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WidthMode {
+    #[default]
+    Fixed,
+    Content,
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct SearchConfig {
     #[serde(default = "default_min_score")]
     pub min_score: i64,
     #[serde(default = "default_score_threshold")]
     pub score_threshold: f64,
+    #[serde(default = "default_keyword_weight")]
+    pub keyword_weight: f64,
     #[serde(default = "default_true")]
     pub prefer_prefix: bool,
     #[serde(default = "default_true")]
+    pub prefer_shorter: bool,
+    #[serde(default = "default_true")]
+    pub exact_to_top: bool,
+    #[serde(default = "default_true")]
     pub use_history: bool,
+    #[serde(default)]
+    pub history_forget_days: u64,
+    #[serde(default = "default_history_keep_recent")]
+    pub history_keep_recent: usize,
+    #[serde(default = "default_history_keep_frequent")]
+    pub history_keep_frequent: usize,
+    #[serde(default)]
+    pub debounce_ms: u64,
+    #[serde(default)]
+    pub matcher: MatcherKind,
+    #[serde(default = "default_true")]
+    pub open_urls: bool,
+    #[serde(default)]
+    pub path_detection: bool,
+    #[serde(default)]
+    pub positional_favorites: bool,
+    #[serde(default)]
+    pub match_description: bool,
+    #[serde(default)]
+    pub no_match_action: NoMatchAction,
+    #[serde(default = "default_web_search_url")]
+    pub web_search_url: String,
+    #[serde(default)]
+    pub engines: Vec<SearchEngine>,
+    #[serde(default)]
+    pub include_categories: bool,
 }
 
-#[derive(Debug, Deserialize, Default)]
+/// A web search picked with a bang (`[[search.engines]]`): a query whose
+/// first word is `keyword` (e.g. `!w`) searches the rest in `url` instead
+/// of `web_search_url`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct SearchEngine {
+    pub keyword: String,
+    /// `{query}` is replaced by the search, which is appended if absent.
+    pub url: String,
+    /// Shown in the result ("Search Wikipedia for …"); defaults to `keyword`.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// What to offer when nothing matches the query.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NoMatchAction {
+    /// Show an empty list.
+    #[default]
+    Empty,
+    /// Offer to run the query as a shell command.
+    RunQuery,
+    /// Offer to open the query in `web_search_url`.
+    WebSearch,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatcherKind {
+    #[default]
+    Skim,
+    Clangd,
+    Simple,
+}
+
+#[derive(Debug, Deserialize)]
 pub struct AppsConfig {
     #[serde(default)]
     pub extra_dirs: Vec<PathBuf>,
+    #[serde(default = "default_true")]
+    pub follow_symlinks: bool,
     #[serde(default)]
     pub exclude: Vec<String>,
     #[serde(default)]
     pub favorites: Vec<String>,
+    #[serde(default = "default_true")]
+    pub favorites_in_list: bool,
+    #[serde(default)]
+    pub require_icon: bool,
+    #[serde(default)]
+    pub history_exclude: Vec<String>,
     #[serde(default)]
     pub custom: Vec<CustomApp>,
+    #[serde(default)]
+    pub custom_override: bool,
+    #[serde(default)]
+    pub overrides: Vec<AppOverride>,
+    #[serde(default)]
+    pub keyword_map: Vec<KeywordMap>,
+    #[serde(default)]
+    pub actions: Vec<CustomAction>,
+    #[serde(default)]
+    pub file_apps: FileApps,
+    #[serde(default)]
+    pub dedup_prefer: DedupPrefer,
+    #[serde(default = "default_true")]
+    pub hide_flatpak_runtimes: bool,
+    #[serde(default = "default_true")]
+    pub nix_dirs: bool,
+    /// `[apps.desktop.<name>]` overlay sections, keyed by
+    /// `XDG_CURRENT_DESKTOP` name; applied by `Config::load`.
+    #[serde(default)]
+    pub desktop: toml::Table,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppsOverlay {
+    #[serde(default)]
+    exclude: Option<Vec<String>>,
+    #[serde(default)]
+    favorites: Option<Vec<String>>,
+}
+
+/// What a query starting with a `[triggers]` prefix is routed to.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// Run the rest of the query as a shell command.
+    Run,
+    /// Open the rest of the query in `search.web_search_url`.
+    WebSearch,
+}
+
+impl TriggerMode {
+    /// The `search.no_match_action` result offering the same thing.
+    pub fn action(self) -> NoMatchAction {
+        match self {
+            Self::Run => NoMatchAction::RunQuery,
+            Self::WebSearch => NoMatchAction::WebSearch,
+        }
+    }
+
+    /// CSS class put on the search entry while the mode is active.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Self::Run => "yeet-trigger-run",
+            Self::WebSearch => "yeet-trigger-web-search",
+        }
+    }
+}
+
+/// What to do with desktop entries whose Exec takes a file or URL
+/// (`%f`, `%F`, `%u`, `%U`) when yeet has none to give them.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FileApps {
+    /// Drop the field code and launch with no argument.
+    #[default]
+    Strip,
+    /// Hide such apps from the list entirely.
+    Drop,
+    /// Pass the home directory as the argument.
+    Home,
+}
+
+/// Which copy of a desktop file id wins when it's installed both for the
+/// user (`~/.local/share/...`) and system-wide.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupPrefer {
+    /// The user's copy, in the XDG spec's directory order.
+    #[default]
+    Local,
+    /// The system copy, e.g. when system entries are patched by hand.
+    System,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -71,6 +490,57 @@ pub struct CustomApp {
     pub icon: Option<String>,
     #[serde(default)]
     pub keywords: Vec<String>,
+    /// Run in this terminal instead of launching directly.
+    #[serde(default)]
+    pub terminal: Option<String>,
+    /// Shell commands to run in the terminal before `exec` takes it over,
+    /// e.g. to `cd` into a project before opening a shell or REPL.
+    #[serde(default)]
+    pub launch_and_type: Option<String>,
+    /// Ask for a second Enter before launching.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Per-app settings for discovered apps, matched by display name.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AppOverride {
+    pub name: String,
+    /// Terminal used instead of `general.terminal` for this app.
+    #[serde(default)]
+    pub terminal: Option<String>,
+    /// Whether to run the app in a terminal, overriding both its
+    /// `Terminal` key and the check for an `Exec` that is itself a terminal.
+    #[serde(default)]
+    pub wrap_terminal: Option<bool>,
+    /// Ask for a second Enter before launching.
+    #[serde(default)]
+    pub confirm: Option<bool>,
+    /// Used instead of `general.launch_nice` for this app.
+    #[serde(default)]
+    pub nice: Option<Niceness>,
+    /// Used instead of `general.launch_ionice` for this app.
+    #[serde(default)]
+    pub ionice: Option<IoClass>,
+}
+
+/// An extra action for an app (`[[apps.actions]]`), listed with the
+/// desktop entry's own actions. `exec` runs through the shell.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CustomAction {
+    /// Display name of the app it belongs to.
+    pub name: String,
+    pub label: String,
+    pub exec: String,
+}
+
+/// Extra search keywords for discovered apps, matched by display name or
+/// executable name (e.g. `firefox`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct KeywordMap {
+    pub name: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
 }
 
 fn default_max_results() -> usize {
@@ -79,12 +549,24 @@ fn default_max_results() -> usize {
 fn default_initial_results() -> usize {
     8
 }
+fn default_launch_debounce_ms() -> u64 {
+    500
+}
 fn default_terminal() -> String {
     "alacritty".into()
 }
+fn default_shell() -> String {
+    "sh".into()
+}
+fn default_cancel_keys() -> Vec<String> {
+    vec!["Escape".into()]
+}
 fn default_width() -> i32 {
     500
 }
+fn default_max_width() -> i32 {
+    900
+}
 fn default_anchor_top() -> i32 {
     200
 }
@@ -94,6 +576,21 @@ fn default_min_score() -> i64 {
 fn default_score_threshold() -> f64 {
     0.6
 }
+fn default_keyword_weight() -> f64 {
+    0.8
+}
+fn default_history_keep_recent() -> usize {
+    200
+}
+fn default_history_keep_frequent() -> usize {
+    10
+}
+fn default_empty_order() -> Vec<ListOrder> {
+    vec![ListOrder::Favorites, ListOrder::Recent, ListOrder::Alpha]
+}
+fn default_web_search_url() -> String {
+    "https://duckduckgo.com/?q={query}".into()
+}
 fn default_row_height() -> i32 {
     56
 }
@@ -107,6 +604,26 @@ impl Default for GeneralConfig {
             max_results: default_max_results(),
             initial_results: default_initial_results(),
             terminal: default_terminal(),
+            shell: default_shell(),
+            stay_open: false,
+            wrap_navigation: false,
+            enter_launches_top: default_true(),
+            monitor: None,
+            monitor_fallback: MonitorFallback::default(),
+            cancel_keys: default_cancel_keys(),
+            copy_target: CopyTarget::default(),
+            multiline_paste: MultilinePaste::default(),
+            empty_order: default_empty_order(),
+            launch_prefix: String::new(),
+            post_launch_hook: String::new(),
+            data_dir: None,
+            history_format: HistoryFormat::default(),
+            accessibility: default_true(),
+            launch_debounce_ms: default_launch_debounce_ms(),
+            focus_on_launch: default_true(),
+            launch_nice: None,
+            launch_ionice: None,
+            control_socket: false,
         }
     }
 }
@@ -115,10 +632,22 @@ impl Default for AppearanceConfig {
     fn default() -> Self {
         Self {
             width: default_width(),
+            width_mode: WidthMode::default(),
+            max_width: default_max_width(),
             anchor_top: default_anchor_top(),
             row_height: default_row_height(),
+            row_spacing: 0,
+            fixed_list_size: false,
+            show_sections: false,
+            group_by_category: false,
             show_shortcuts: default_true(),
             show_descriptions: default_true(),
+            description_as_tooltip: false,
+            show_icons: default_true(),
+            minimal: false,
+            icon_theme: None,
+            icon_scaling: IconScaling::default(),
+            theme: None,
         }
     }
 }
@@ -128,36 +657,361 @@ impl Default for SearchConfig {
         Self {
             min_score: default_min_score(),
             score_threshold: default_score_threshold(),
+            keyword_weight: default_keyword_weight(),
             prefer_prefix: default_true(),
+            prefer_shorter: default_true(),
+            exact_to_top: default_true(),
             use_history: default_true(),
+            history_forget_days: 0,
+            history_keep_recent: default_history_keep_recent(),
+            history_keep_frequent: default_history_keep_frequent(),
+            debounce_ms: 0,
+            matcher: MatcherKind::default(),
+            open_urls: default_true(),
+            path_detection: false,
+            positional_favorites: false,
+            match_description: false,
+            no_match_action: NoMatchAction::default(),
+            web_search_url: default_web_search_url(),
+            engines: Vec::new(),
+            include_categories: false,
+        }
+    }
+}
+
+impl Default for AppsConfig {
+    fn default() -> Self {
+        Self {
+            extra_dirs: Vec::new(),
+            follow_symlinks: default_true(),
+            exclude: Vec::new(),
+            favorites: Vec::new(),
+            favorites_in_list: default_true(),
+            require_icon: false,
+            history_exclude: Vec::new(),
+            custom: Vec::new(),
+            custom_override: false,
+            overrides: Vec::new(),
+            keyword_map: Vec::new(),
+            actions: Vec::new(),
+            file_apps: FileApps::default(),
+            dedup_prefer: DedupPrefer::default(),
+            hide_flatpak_runtimes: default_true(),
+            nix_dirs: default_true(),
+            desktop: toml::Table::new(),
+        }
+    }
+}
+
+/// `appearance.theme` as set in a raw config file.
+fn theme_name(config: &toml::Table) -> Option<&str> {
+    config.get("appearance")?.get("theme")?.as_str()
+}
+
+/// The `config.toml` of theme `name`, if it has one. A theme that fails to
+/// parse is warned about and left out rather than discarding the user's
+/// config with it.
+fn load_theme_config(name: &str) -> Option<toml::Table> {
+    let Some(dir) = Config::theme_dir(name).filter(|dir| dir.is_dir()) else {
+        eprintln!("Warning: theme \"{name}\" not found");
+        if crate::theme::bundled_names().any(|bundled| bundled == name) {
+            eprintln!("  Install it with: yeet --install-theme {name}");
+        }
+        return None;
+    };
+    let path = dir.join(crate::theme::CONFIG_FILE);
+    let contents = std::fs::read_to_string(&path).ok()?;
+    let table = toml::from_str::<toml::Table>(&contents).and_then(|table| {
+        toml::Value::Table(table.clone()).try_into::<Config>()?;
+        Ok(table)
+    });
+    match table {
+        Ok(table) => Some(table),
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to parse theme config at {}",
+                path.display()
+            );
+            eprintln!("  {e}");
+            None
+        }
+    }
+}
+
+/// The `[appearance]` keys `minimal = true` stands for: no icons,
+/// descriptions or shortcut badges, in rows just tall enough for a name.
+fn minimal_appearance() -> [(&'static str, toml::Value); 4] {
+    [
+        ("show_icons", toml::Value::Boolean(false)),
+        ("show_descriptions", toml::Value::Boolean(false)),
+        ("show_shortcuts", toml::Value::Boolean(false)),
+        ("row_height", toml::Value::Integer(36)),
+    ]
+}
+
+/// The user's config over a theme's config over the system config: every
+/// key a higher layer sets wins, sections are merged key by key, arrays of
+/// tables like `[[apps.custom]]` gather every layer's entries, and
+/// anything none of them sets is left for the defaults.
+/// `appearance.minimal` fills in its keys before the defaults do.
+fn layer_configs(
+    system: Option<toml::Table>,
+    theme: Option<toml::Table>,
+    user: toml::Table,
+) -> Result<Config, toml::de::Error> {
+    let mut layered = system.unwrap_or_default();
+    if let Some(theme) = theme {
+        layer_table(&mut layered, theme);
+    }
+    layer_table(&mut layered, user);
+    if let Some(toml::Value::Table(appearance)) = layered.get_mut("appearance") {
+        if appearance.get("minimal") == Some(&toml::Value::Boolean(true)) {
+            for (key, value) in minimal_appearance() {
+                appearance.entry(key).or_insert(value);
+            }
+        }
+    }
+    toml::Value::Table(layered).try_into()
+}
+
+fn layer_table(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => layer_table(base, over),
+            // Lower layers' entries come first, so the user's
+            // `[[apps.overrides]]` for an app still wins.
+            (Some(toml::Value::Array(base)), toml::Value::Array(over))
+                if is_table_array(base) && is_table_array(&over) =>
+            {
+                base.extend(over);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
         }
     }
 }
 
+/// An array written as `[[...]]` sections; a plain array like
+/// `favorites` still replaces the one below it.
+fn is_table_array(array: &[toml::Value]) -> bool {
+    !array.is_empty() && array.iter().all(toml::Value::is_table)
+}
+
+/// The table in the config file at `path`, or `None` when there's no such
+/// file. It's checked against `Config` on its own, so an error names the
+/// file it's in.
+fn read_config_table(path: &Path) -> Result<Option<toml::Table>, ConfigError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let error = |message: String| ConfigError {
+        path: path.to_path_buf(),
+        message,
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let table = toml::from_str::<toml::Table>(&contents).map_err(|e| error(e.to_string()))?;
+    toml::Value::Table(table.clone())
+        .try_into::<Config>()
+        .map_err(|e| error(e.to_string()))?;
+    Ok(Some(table))
+}
+
+/// Keys in a config file's `table` that no setting reads, such as a
+/// misspelled `general.max_result`, as dotted paths (`apps.custom[0].exec`).
+fn unknown_keys(table: &toml::Table) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let _: Result<Config, _> =
+        serde_ignored::deserialize(toml::Value::Table(table.clone()), |path| {
+            unknown.push(dotted_path(&path))
+        });
+    // Overlays are only read when their desktop is current.
+    let overlays = table
+        .get("apps")
+        .and_then(|apps| apps.get("desktop"))
+        .and_then(toml::Value::as_table);
+    for (name, section) in overlays.into_iter().flatten() {
+        let _: Result<AppsOverlay, _> = serde_ignored::deserialize(section.clone(), |path| {
+            unknown.push(format!("apps.desktop.{name}.{}", dotted_path(&path)))
+        });
+    }
+    unknown
+}
+
+fn dotted_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{index}]", dotted_path(parent)),
+        Path::Map { parent, key } => match dotted_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{parent}.{key}"),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => dotted_path(parent),
+    }
+}
+
+/// A config file that couldn't be read or parsed.
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to load config at {}\n  {}",
+            self.path.display(),
+            self.message.trim_end()
+        )
+    }
+}
+
 impl Config {
-    pub fn load() -> Self {
+    /// Defaults, then `/etc/yeet/config.toml`, then the selected theme's
+    /// config, then the user's `config.toml`. A config file that can't be
+    /// read or parsed is an error rather than silently replaced by the
+    /// defaults.
+    pub fn load() -> Result<Self, ConfigError> {
+        let user_path = Self::user_config_path();
+        let mut config =
+            Self::load_files(Some(Path::new(SYSTEM_CONFIG_PATH)), user_path.as_deref())?;
+        if let Some(warning) = config.clamp_initial_results() {
+            eprintln!("Warning: {warning}");
+        }
+
+        if let Ok(current_desktop) = std::env::var("XDG_CURRENT_DESKTOP") {
+            config.apply_desktop_overlays(&current_desktop);
+        }
+
+        Ok(config)
+    }
+
+    /// `--validate-config`: loads the config files like `load`, or just the
+    /// file at `path` over the defaults, and lists the keys in them that no
+    /// setting reads followed by what's wrong with the result (see
+    /// `validate`). A file that can't be read or parsed is an error, as is a
+    /// `path` that doesn't exist.
+    pub fn check(path: Option<&Path>) -> Result<Vec<String>, ConfigError> {
+        let user_path = Self::user_config_path();
+        let (system_path, user_path) = match path {
+            Some(path) if !path.exists() => {
+                return Err(ConfigError {
+                    path: path.to_path_buf(),
+                    message: "no such file".to_string(),
+                })
+            }
+            Some(path) => (None, Some(path)),
+            None => (Some(Path::new(SYSTEM_CONFIG_PATH)), user_path.as_deref()),
+        };
+        let config = Self::load_files(system_path, user_path)?;
+
+        let mut problems = Vec::new();
+        for path in [system_path, user_path].into_iter().flatten() {
+            if let Some(table) = read_config_table(path)? {
+                problems.extend(
+                    unknown_keys(&table)
+                        .into_iter()
+                        .map(|key| format!("{}: unknown key {key}", path.display())),
+                );
+            }
+        }
+        problems.extend(config.validate());
+        Ok(problems)
+    }
+
+    /// The defaults with the system config, the theme and the user config
+    /// layered over them, from whichever of those files exist.
+    fn load_files(
+        system_path: Option<&Path>,
+        user_path: Option<&Path>,
+    ) -> Result<Self, ConfigError> {
         let mut config: Config =
             toml::from_str(DEFAULT_CONFIG).expect("embedded default config should be valid");
 
-        if let Some(user_config_path) = Self::user_config_path() {
-            if user_config_path.exists() {
-                if let Ok(contents) = std::fs::read_to_string(&user_config_path) {
-                    match toml::from_str::<Config>(&contents) {
-                        Ok(user_config) => config.merge(user_config),
-                        Err(e) => {
-                            eprintln!(
-                                "Warning: Failed to parse config at {}",
-                                user_config_path.display()
-                            );
-                            eprintln!("  {e}");
-                            eprintln!("  Using default configuration.");
-                        }
-                    }
+        let system = match system_path {
+            Some(path) => read_config_table(path)?,
+            None => None,
+        };
+        let user = match user_path {
+            Some(path) => read_config_table(path)?,
+            None => None,
+        };
+
+        if system.is_some() || user.is_some() {
+            let theme = user
+                .as_ref()
+                .and_then(theme_name)
+                .or_else(|| system.as_ref().and_then(theme_name))
+                .and_then(load_theme_config);
+            let error_path = match (&user, user_path) {
+                (Some(_), Some(path)) => path,
+                _ => system_path.unwrap_or(Path::new(SYSTEM_CONFIG_PATH)),
+            }
+            .to_path_buf();
+            let layered = layer_configs(system, theme, user.unwrap_or_default()).map_err(|e| {
+                ConfigError {
+                    path: error_path,
+                    message: e.to_string(),
                 }
+            })?;
+            config.merge(layered);
+        }
+
+        Ok(config)
+    }
+
+    /// Settings that parse but can't be used as written: what `load` and
+    /// the launcher quietly correct or skip. Empty for a clean config.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        problems.extend(self.initial_results_problem());
+        let search = &self.search;
+        for (key, value) in [
+            ("score_threshold", search.score_threshold),
+            ("keyword_weight", search.keyword_weight),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                problems.push(format!(
+                    "search.{key} ({value}) is not between 0.0 and 1.0; it's clamped to {}",
+                    value.clamp(0.0, 1.0)
+                ));
+            }
+        }
+        for (key, section) in &self.apps.desktop {
+            if let Err(e) = section.clone().try_into::<AppsOverlay>() {
+                problems.push(format!(
+                    "[apps.desktop.{key}] is ignored: {}",
+                    e.to_string().trim_end()
+                ));
             }
         }
+        problems
+    }
+
+    /// `initial_results` caps the list before typing and `max_results` the
+    /// search results, which is never shorter: a larger `initial_results` is
+    /// lowered to `max_results`, with the warning to print. `0` (list every
+    /// app) is left alone.
+    fn clamp_initial_results(&mut self) -> Option<String> {
+        let warning = self.initial_results_problem()?;
+        self.general.initial_results = self.general.max_results;
+        Some(warning)
+    }
 
-        config
+    fn initial_results_problem(&self) -> Option<String> {
+        let general = &self.general;
+        (general.initial_results > general.max_results).then(|| {
+            format!(
+                "general.initial_results ({}) is more than general.max_results ({}); \
+                 showing {} before typing (set initial_results = 0 to list every app)",
+                general.initial_results, general.max_results, general.max_results
+            )
+        })
     }
 
     pub fn config_dir() -> Option<PathBuf> {
@@ -168,14 +1022,40 @@ impl Config {
         Self::config_dir().map(|p| p.join("config.toml"))
     }
 
+    /// `general.data_dir` with `~` and `$VAR`/`${VAR}` expanded, or `None`
+    /// for the default location.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        let raw = self.general.data_dir.as_deref()?;
+        let home = dirs::home_dir();
+        Some(expand_path(raw, home.as_deref(), |var| {
+            std::env::var(var).ok()
+        }))
+    }
+
+    /// `general.shell` with `~` and `$VAR`/`${VAR}` expanded.
+    pub fn shell(&self) -> String {
+        let home = dirs::home_dir();
+        expand_path(&self.general.shell, home.as_deref(), |var| {
+            std::env::var(var).ok()
+        })
+        .to_string_lossy()
+        .into_owned()
+    }
+
     pub fn user_style_path() -> Option<PathBuf> {
         Self::config_dir().map(|p| p.join("style.css"))
     }
 
+    /// `themes/<name>/` in the config dir, for `appearance.theme`.
+    pub fn theme_dir(name: &str) -> Option<PathBuf> {
+        Self::config_dir().and_then(|dir| crate::theme::dir(&dir, name))
+    }
+
     fn merge(&mut self, user: Config) {
         self.general = user.general;
         self.appearance = user.appearance;
         self.search = user.search;
+        self.triggers.extend(user.triggers);
 
         if !user.apps.extra_dirs.is_empty() {
             self.apps.extra_dirs = user.apps.extra_dirs;
@@ -189,6 +1069,58 @@ impl Config {
         if !user.apps.custom.is_empty() {
             self.apps.custom.extend(user.apps.custom);
         }
+        if !user.apps.overrides.is_empty() {
+            self.apps.overrides.extend(user.apps.overrides);
+        }
+        if !user.apps.keyword_map.is_empty() {
+            self.apps.keyword_map.extend(user.apps.keyword_map);
+        }
+        if !user.apps.actions.is_empty() {
+            self.apps.actions.extend(user.apps.actions);
+        }
+        if !user.apps.history_exclude.is_empty() {
+            self.apps.history_exclude = user.apps.history_exclude;
+        }
+        self.apps.favorites_in_list = user.apps.favorites_in_list;
+        self.apps.require_icon = user.apps.require_icon;
+        self.apps.custom_override = user.apps.custom_override;
+        self.apps.file_apps = user.apps.file_apps;
+        self.apps.follow_symlinks = user.apps.follow_symlinks;
+        self.apps.dedup_prefer = user.apps.dedup_prefer;
+        self.apps.hide_flatpak_runtimes = user.apps.hide_flatpak_runtimes;
+        self.apps.nix_dirs = user.apps.nix_dirs;
+        self.apps.desktop.extend(user.apps.desktop);
+    }
+
+    /// Applies the `[apps.desktop.<name>]` sections named in
+    /// `XDG_CURRENT_DESKTOP` (e.g. `ubuntu:GNOME`) over `[apps]`. The first listed desktop is the
+    /// most specific, so it is applied last and wins.
+    fn apply_desktop_overlays(&mut self, current_desktop: &str) {
+        for desktop in current_desktop.split(':').rev() {
+            let Some((key, section)) = self
+                .apps
+                .desktop
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(desktop))
+            else {
+                continue;
+            };
+
+            match section.clone().try_into::<AppsOverlay>() {
+                Ok(overlay) => {
+                    if let Some(exclude) = overlay.exclude {
+                        self.apps.exclude = exclude;
+                    }
+                    if let Some(favorites) = overlay.favorites {
+                        self.apps.favorites = favorites;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Warning: Failed to parse [apps.desktop.{key}]");
+                    eprintln!("  {e}");
+                }
+            }
+        }
     }
 
     #[cfg(test)]
@@ -266,16 +1198,499 @@ mod tests {
     }
 
     #[test]
-    fn parses_favorites_and_excludes() {
+    fn parses_launch_and_type() {
         let user_toml = r#"
-            [apps]
-            favorites = ["Firefox", "Alacritty"]
-            exclude = ["htop.desktop", "nvtop.desktop"]
+            [[apps.custom]]
+            name = "Yeet Shell"
+            exec = "bash"
+            launch_and_type = "cd ~/projects/yeet && git status"
+
+            [[apps.custom]]
+            name = "Plain"
+            exec = "true"
         "#;
 
         let config = Config::from_toml(user_toml).unwrap();
-        assert_eq!(config.apps.favorites, vec!["Firefox", "Alacritty"]);
-        assert_eq!(config.apps.exclude, vec!["htop.desktop", "nvtop.desktop"]);
+        assert_eq!(
+            config.apps.custom[0].launch_and_type.as_deref(),
+            Some("cd ~/projects/yeet && git status")
+        );
+        assert_eq!(config.apps.custom[1].launch_and_type, None);
+    }
+
+    fn table(toml: &str) -> toml::Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn theme_config_sits_under_user_config() {
+        let theme = table(
+            r#"
+            [appearance]
+            width = 600
+            row_height = 40
+
+            [search]
+            matcher = "clangd"
+        "#,
+        );
+        let user = table(
+            r#"
+            [appearance]
+            theme = "nord"
+            width = 700
+        "#,
+        );
+
+        let config = layer_configs(None, Some(theme), user).unwrap();
+        assert_eq!(config.appearance.theme.as_deref(), Some("nord"));
+        assert_eq!(config.appearance.width, 700);
+        assert_eq!(config.appearance.row_height, 40);
+        assert_eq!(config.search.matcher, MatcherKind::Clangd);
+        assert_eq!(config.appearance.anchor_top, 200);
+    }
+
+    #[test]
+    fn user_config_sits_over_system_config_over_defaults() {
+        let system = table(
+            r#"
+            [general]
+            terminal = "foot"
+            max_results = 10
+
+            [apps]
+            favorites = ["Firefox"]
+        "#,
+        );
+        let user = table(
+            r#"
+            [general]
+            max_results = 6
+
+            [appearance]
+            width = 640
+        "#,
+        );
+
+        let mut config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+        config.merge(layer_configs(Some(system), None, user).unwrap());
+
+        // Set by the user over the system config
+        assert_eq!(config.general.max_results, 6);
+        // Set by the system config only
+        assert_eq!(config.general.terminal, "foot");
+        assert_eq!(config.apps.favorites, ["Firefox"]);
+        // Set by the user only
+        assert_eq!(config.appearance.width, 640);
+        // Set by neither
+        assert_eq!(config.general.initial_results, 8);
+        assert_eq!(config.appearance.row_height, 56);
+    }
+
+    #[test]
+    fn system_app_tables_are_kept_under_the_users() {
+        let system = table(
+            r#"
+            [apps]
+            favorites = ["Firefox"]
+
+            [[apps.custom]]
+            name = "Site VPN"
+            exec = "vpn-up"
+
+            [[apps.overrides]]
+            name = "Htop"
+            terminal = "foot"
+        "#,
+        );
+        let user = table(
+            r#"
+            [apps]
+            favorites = ["kitty"]
+
+            [[apps.custom]]
+            name = "Notes"
+            exec = "notes"
+
+            [[apps.overrides]]
+            name = "Htop"
+            terminal = "kitty"
+        "#,
+        );
+
+        let config = layer_configs(Some(system), None, user).unwrap();
+        let custom: Vec<&str> = config
+            .apps
+            .custom
+            .iter()
+            .map(|app| app.name.as_str())
+            .collect();
+        assert_eq!(custom, ["Site VPN", "Notes"]);
+        let terminals: Vec<Option<&str>> = config
+            .apps
+            .overrides
+            .iter()
+            .map(|o| o.terminal.as_deref())
+            .collect();
+        assert_eq!(terminals, [Some("foot"), Some("kitty")]);
+        assert_eq!(config.apps.favorites, ["kitty"]);
+    }
+
+    #[test]
+    fn theme_config_sits_over_system_config() {
+        let system = table("[appearance]\nwidth = 550\nanchor_top = 120");
+        let theme = table("[appearance]\nwidth = 600");
+
+        let config = layer_configs(Some(system), Some(theme), toml::Table::new()).unwrap();
+        assert_eq!(config.appearance.width, 600);
+        assert_eq!(config.appearance.anchor_top, 120);
+    }
+
+    #[test]
+    fn minimal_sets_its_keys_unless_given() {
+        let config = layer_configs(None, None, table("[appearance]\nminimal = true")).unwrap();
+        assert!(!config.appearance.show_icons);
+        assert!(!config.appearance.show_descriptions);
+        assert!(!config.appearance.show_shortcuts);
+        assert_eq!(config.appearance.row_height, 36);
+        assert_eq!(config.appearance.width, 500);
+
+        let config = layer_configs(
+            None,
+            None,
+            table("[appearance]\nminimal = true\nrow_height = 44\nshow_icons = true"),
+        )
+        .unwrap();
+        assert!(config.appearance.show_icons);
+        assert_eq!(config.appearance.row_height, 44);
+        assert!(!config.appearance.show_descriptions);
+
+        let config = layer_configs(None, None, table("[appearance]\nminimal = false")).unwrap();
+        assert!(config.appearance.show_icons);
+        assert_eq!(config.appearance.row_height, 56);
+    }
+
+    #[test]
+    fn theme_name_comes_from_user_appearance() {
+        assert_eq!(
+            theme_name(&table("[appearance]\ntheme = \"latte\"")),
+            Some("latte")
+        );
+        assert_eq!(theme_name(&table("[general]\ntheme = \"latte\"")), None);
+        assert_eq!(theme_name(&table("")), None);
+    }
+
+    #[test]
+    fn parses_confirm_flags() {
+        let user_toml = r#"
+            [[apps.custom]]
+            name = "Wipe disk"
+            exec = "wipe.sh"
+            confirm = true
+
+            [[apps.custom]]
+            name = "Notes"
+            exec = "notes"
+
+            [[apps.overrides]]
+            name = "GParted"
+            confirm = true
+        "#;
+
+        let config = Config::from_toml(user_toml).unwrap();
+        assert!(config.apps.custom[0].confirm);
+        assert!(!config.apps.custom[1].confirm);
+        assert_eq!(config.apps.overrides[0].confirm, Some(true));
+    }
+
+    #[test]
+    fn parses_terminal_overrides() {
+        let user_toml = r#"
+            [[apps.custom]]
+            name = "btop"
+            exec = "btop"
+            terminal = "foot"
+
+            [[apps.overrides]]
+            name = "Htop"
+            terminal = "kitty"
+
+            [[apps.overrides]]
+            name = "Yazi"
+            wrap_terminal = true
+        "#;
+
+        let config = Config::from_toml(user_toml).unwrap();
+        assert_eq!(config.apps.custom[0].terminal, Some("foot".into()));
+        assert_eq!(config.apps.overrides.len(), 2);
+        assert_eq!(config.apps.overrides[0].name, "Htop");
+        assert_eq!(config.apps.overrides[0].terminal, Some("kitty".into()));
+        assert_eq!(config.apps.overrides[0].wrap_terminal, None);
+        assert_eq!(config.apps.overrides[1].terminal, None);
+        assert_eq!(config.apps.overrides[1].wrap_terminal, Some(true));
+    }
+
+    #[test]
+    fn parses_favorites_and_excludes() {
+        let user_toml = r#"
+            [apps]
+            favorites = ["Firefox", "Alacritty"]
+            exclude = ["htop.desktop", "nvtop.desktop"]
+        "#;
+
+        let config = Config::from_toml(user_toml).unwrap();
+        assert_eq!(config.apps.favorites, vec!["Firefox", "Alacritty"]);
+        assert_eq!(config.apps.exclude, vec!["htop.desktop", "nvtop.desktop"]);
+    }
+
+    #[test]
+    fn parses_history_exclude() {
+        let config = Config::from_toml("[apps]\nhistory_exclude = [\"KeePassXC\"]").unwrap();
+        assert_eq!(config.apps.history_exclude, vec!["KeePassXC"]);
+    }
+
+    #[test]
+    fn favorites_in_list_defaults_to_true() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.apps.favorites_in_list);
+
+        let config = Config::from_toml("[apps]\nfavorites_in_list = false").unwrap();
+        assert!(!config.apps.favorites_in_list);
+    }
+
+    #[test]
+    fn parses_keyword_map() {
+        let user_toml = r#"
+            [[apps.keyword_map]]
+            name = "Firefox"
+            keywords = ["browser", "web"]
+
+            [[apps.keyword_map]]
+            name = "code"
+            keywords = ["editor"]
+        "#;
+
+        let config = Config::from_toml(user_toml).unwrap();
+        assert_eq!(config.apps.keyword_map.len(), 2);
+        assert_eq!(config.apps.keyword_map[0].name, "Firefox");
+        assert_eq!(config.apps.keyword_map[0].keywords, vec!["browser", "web"]);
+        assert_eq!(config.apps.keyword_map[1].name, "code");
+    }
+
+    #[test]
+    fn parses_custom_actions() {
+        let user_toml = r#"
+            [[apps.actions]]
+            name = "Firefox"
+            label = "Open in incognito"
+            exec = "firefox --private-window"
+        "#;
+
+        let config = Config::from_toml(user_toml).unwrap();
+        assert_eq!(config.apps.actions.len(), 1);
+        assert_eq!(config.apps.actions[0].name, "Firefox");
+        assert_eq!(config.apps.actions[0].label, "Open in incognito");
+        assert_eq!(config.apps.actions[0].exec, "firefox --private-window");
+    }
+
+    #[test]
+    fn require_icon_defaults_to_false() {
+        assert!(!Config::from_toml("").unwrap().apps.require_icon);
+
+        let config = Config::from_toml("[apps]\nrequire_icon = true").unwrap();
+        assert!(config.apps.require_icon);
+    }
+
+    const DESKTOP_OVERLAYS: &str = r#"
+        [apps]
+        favorites = ["Firefox"]
+        exclude = ["Htop"]
+
+        [apps.desktop.sway]
+        favorites = ["foot"]
+
+        [apps.desktop.gnome]
+        favorites = ["Console"]
+        exclude = []
+    "#;
+
+    #[test]
+    fn desktop_overlay_replaces_favorites() {
+        let mut config = Config::from_toml(DESKTOP_OVERLAYS).unwrap();
+        config.apply_desktop_overlays("sway");
+        assert_eq!(config.apps.favorites, vec!["foot"]);
+        assert_eq!(config.apps.exclude, vec!["Htop"]);
+    }
+
+    #[test]
+    fn desktop_overlay_matches_any_listed_desktop_case_insensitively() {
+        let mut config = Config::from_toml(DESKTOP_OVERLAYS).unwrap();
+        config.apply_desktop_overlays("ubuntu:GNOME");
+        assert_eq!(config.apps.favorites, vec!["Console"]);
+        assert!(config.apps.exclude.is_empty());
+    }
+
+    #[test]
+    fn desktop_overlay_prefers_most_specific_desktop() {
+        let mut config = Config::from_toml(DESKTOP_OVERLAYS).unwrap();
+        config.apply_desktop_overlays("sway:GNOME");
+        assert_eq!(config.apps.favorites, vec!["foot"]);
+    }
+
+    #[test]
+    fn desktop_overlays_only_come_from_apps_desktop() {
+        let mut config = Config::from_toml("[apps.sway]\nfavorites = [\"foot\"]").unwrap();
+        config.apply_desktop_overlays("sway");
+        assert!(config.apps.desktop.is_empty());
+        assert_ne!(config.apps.favorites, vec!["foot"]);
+    }
+
+    #[test]
+    fn unmatched_desktop_keeps_base_apps() {
+        let mut config = Config::from_toml(DESKTOP_OVERLAYS).unwrap();
+        config.apply_desktop_overlays("KDE");
+        assert_eq!(config.apps.favorites, vec!["Firefox"]);
+        assert_eq!(config.apps.exclude, vec!["Htop"]);
+    }
+
+    #[test]
+    fn parses_file_apps_policy() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.apps.file_apps, FileApps::Strip);
+
+        let config = Config::from_toml("[apps]\nfile_apps = \"drop\"").unwrap();
+        assert_eq!(config.apps.file_apps, FileApps::Drop);
+
+        let config = Config::from_toml("[apps]\nfile_apps = \"home\"").unwrap();
+        assert_eq!(config.apps.file_apps, FileApps::Home);
+    }
+
+    #[test]
+    fn parses_dedup_preference() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.apps.dedup_prefer, DedupPrefer::Local);
+
+        let config = Config::from_toml("[apps]\ndedup_prefer = \"system\"").unwrap();
+        assert_eq!(config.apps.dedup_prefer, DedupPrefer::System);
+    }
+
+    #[test]
+    fn parses_triggers() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.triggers.is_empty());
+
+        let config = Config::from_toml(
+            r#"
+            [triggers]
+            ">" = "run"
+            "??" = "web_search"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.triggers[">"], TriggerMode::Run);
+        assert_eq!(config.triggers["??"], TriggerMode::WebSearch);
+
+        assert!(Config::from_toml("[triggers]\n\"=\" = \"calc\"").is_err());
+    }
+
+    #[test]
+    fn parses_launch_debounce() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.launch_debounce_ms, 500);
+
+        let config = Config::from_toml("[general]\nlaunch_debounce_ms = 0").unwrap();
+        assert_eq!(config.general.launch_debounce_ms, 0);
+    }
+
+    #[test]
+    fn parses_focus_on_launch() {
+        assert!(Config::from_toml("").unwrap().general.focus_on_launch);
+        let config = Config::from_toml("[general]\nfocus_on_launch = false").unwrap();
+        assert!(!config.general.focus_on_launch);
+    }
+
+    #[test]
+    fn parses_launch_priorities() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.launch_nice, None);
+        assert_eq!(config.general.launch_ionice, None);
+
+        let toml = r#"
+            [general]
+            launch_nice = 10
+            launch_ionice = 3
+
+            [[apps.overrides]]
+            name = "Firefox"
+            nice = -5
+            ionice = 2
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.general.launch_nice.map(Niceness::get), Some(10));
+        assert_eq!(config.general.launch_ionice.map(IoClass::get), Some(3));
+        assert_eq!(config.apps.overrides[0].nice.map(Niceness::get), Some(-5));
+        assert_eq!(config.apps.overrides[0].ionice.map(IoClass::get), Some(2));
+    }
+
+    #[test]
+    fn rejects_out_of_range_launch_priorities() {
+        let err = Config::from_toml("[general]\nlaunch_nice = 20").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("nice value 20 is not between -20 and 19"));
+        assert!(Config::from_toml("[general]\nlaunch_nice = -21").is_err());
+        assert!(Config::from_toml("[general]\nlaunch_ionice = 4").is_err());
+        assert!(Config::from_toml("[[apps.overrides]]\nname = \"X\"\nionice = -1").is_err());
+    }
+
+    #[test]
+    fn parses_control_socket() {
+        assert!(!Config::from_toml("").unwrap().general.control_socket);
+        let config = Config::from_toml("[general]\ncontrol_socket = true").unwrap();
+        assert!(config.general.control_socket);
+    }
+
+    #[test]
+    fn accessibility_is_on_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.general.accessibility);
+
+        let config = Config::from_toml("[general]\naccessibility = false").unwrap();
+        assert!(!config.general.accessibility);
+    }
+
+    #[test]
+    fn scans_nix_dirs_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.apps.nix_dirs);
+
+        let config = Config::from_toml("[apps]\nnix_dirs = false").unwrap();
+        assert!(!config.apps.nix_dirs);
+    }
+
+    #[test]
+    fn hides_flatpak_runtimes_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.apps.hide_flatpak_runtimes);
+
+        let config = Config::from_toml("[apps]\nhide_flatpak_runtimes = false").unwrap();
+        assert!(!config.apps.hide_flatpak_runtimes);
+    }
+
+    #[test]
+    fn parses_width_mode() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.appearance.width_mode, WidthMode::Fixed);
+        assert_eq!(config.appearance.max_width, 900);
+
+        let toml = r#"
+            [appearance]
+            width_mode = "content"
+            max_width = 1200
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.appearance.width_mode, WidthMode::Content);
+        assert_eq!(config.appearance.max_width, 1200);
     }
 
     #[test]
@@ -288,6 +1703,20 @@ mod tests {
         assert_eq!(config.appearance.row_height, 42);
     }
 
+    #[test]
+    fn parses_row_spacing() {
+        assert_eq!(Config::from_toml("").unwrap().appearance.row_spacing, 0);
+
+        let toml = r#"
+            [appearance]
+            row_height = 40
+            row_spacing = 6
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.appearance.row_height, 40);
+        assert_eq!(config.appearance.row_spacing, 6);
+    }
+
     #[test]
     fn row_height_defaults_to_56() {
         let config = Config::from_toml("").unwrap();
@@ -304,12 +1733,525 @@ mod tests {
         assert!(!config.search.use_history);
     }
 
+    #[test]
+    fn history_trimming_defaults() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.search.history_keep_recent, 200);
+        assert_eq!(config.search.history_keep_frequent, 10);
+
+        let toml = r#"
+            [search]
+            history_keep_recent = 50
+            history_keep_frequent = 3
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.search.history_keep_recent, 50);
+        assert_eq!(config.search.history_keep_frequent, 3);
+    }
+
+    #[test]
+    fn parses_history_forget_days() {
+        assert_eq!(Config::from_toml("").unwrap().search.history_forget_days, 0);
+
+        let toml = r#"
+            [search]
+            history_forget_days = 30
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.search.history_forget_days, 30);
+    }
+
+    #[test]
+    fn parses_show_sections() {
+        assert!(!Config::from_toml("").unwrap().appearance.show_sections);
+
+        let config = Config::from_toml("[appearance]\nshow_sections = true").unwrap();
+        assert!(config.appearance.show_sections);
+    }
+
+    #[test]
+    fn parses_group_by_category() {
+        assert!(!Config::from_toml("").unwrap().appearance.group_by_category);
+
+        let config = Config::from_toml("[appearance]\ngroup_by_category = true").unwrap();
+        assert!(config.appearance.group_by_category);
+    }
+
+    #[test]
+    fn parses_fixed_list_size() {
+        assert!(!Config::from_toml("").unwrap().appearance.fixed_list_size);
+
+        let config = Config::from_toml("[appearance]\nfixed_list_size = true").unwrap();
+        assert!(config.appearance.fixed_list_size);
+    }
+
+    #[test]
+    fn parses_description_as_tooltip() {
+        assert!(
+            !Config::from_toml("")
+                .unwrap()
+                .appearance
+                .description_as_tooltip
+        );
+
+        let toml = r#"
+            [appearance]
+            description_as_tooltip = true
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert!(config.appearance.description_as_tooltip);
+        assert!(config.appearance.show_descriptions);
+    }
+
+    #[test]
+    fn parses_icon_scaling() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.appearance.icon_scaling, IconScaling::Down);
+
+        let config = Config::from_toml("[appearance]\nicon_scaling = \"nearest\"").unwrap();
+        assert_eq!(config.appearance.icon_scaling, IconScaling::Nearest);
+    }
+
+    #[test]
+    fn parses_icon_theme() {
+        assert_eq!(Config::from_toml("").unwrap().appearance.icon_theme, None);
+
+        let toml = r#"
+            [appearance]
+            icon_theme = "Papirus-Dark"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(
+            config.appearance.icon_theme.as_deref(),
+            Some("Papirus-Dark")
+        );
+    }
+
+    #[test]
+    fn parses_launch_prefix() {
+        assert!(Config::from_toml("")
+            .unwrap()
+            .general
+            .launch_prefix
+            .is_empty());
+
+        let toml = r#"
+            [general]
+            launch_prefix = "uwsm app -- {cmd}"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.general.launch_prefix, "uwsm app -- {cmd}");
+    }
+
+    #[test]
+    fn initial_results_is_capped_at_max_results() {
+        let clamped = |toml: &str| {
+            let mut config = Config::from_toml(toml).unwrap();
+            let warning = config.clamp_initial_results();
+            (config.general.initial_results, warning.is_some())
+        };
+        assert_eq!(clamped(""), (8, false));
+        assert_eq!(
+            clamped("[general]\nmax_results = 12\ninitial_results = 12"),
+            (12, false)
+        );
+        assert_eq!(
+            clamped("[general]\nmax_results = 20\ninitial_results = 0"),
+            (0, false)
+        );
+        assert_eq!(
+            clamped("[general]\nmax_results = 5\ninitial_results = 10"),
+            (5, true)
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_listed_by_path() {
+        let toml = r#"
+            colour = "red"
+
+            [general]
+            max_result = 5
+            max_results = 5
+
+            [apps]
+            favourites = ["Firefox"]
+
+            [[apps.custom]]
+            name = "Htop"
+            exec = "htop"
+            termnal = "kitty"
+
+            [apps.desktop.sway]
+            favorites = ["foot"]
+            exlude = ["Htop"]
+
+            [triggers]
+            "!" = "run"
+        "#;
+        assert_eq!(
+            unknown_keys(&table(toml)),
+            [
+                "apps.custom[0].termnal",
+                "apps.favourites",
+                "colour",
+                "general.max_result",
+                "apps.desktop.sway.exlude",
+            ]
+        );
+        assert!(unknown_keys(&table(DEFAULT_CONFIG)).is_empty());
+    }
+
+    #[test]
+    fn validate_lists_settings_that_are_worked_around() {
+        assert!(Config::from_toml("").unwrap().validate().is_empty());
+
+        let toml = r#"
+            [general]
+            max_results = 5
+            initial_results = 10
+
+            [search]
+            score_threshold = 1.5
+            keyword_weight = 0.5
+
+            [apps.desktop.sway]
+            favorites = "foot"
+        "#;
+        let problems = Config::from_toml(toml).unwrap().validate();
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].starts_with("general.initial_results (10) is more than"));
+        assert_eq!(
+            problems[1],
+            "search.score_threshold (1.5) is not between 0.0 and 1.0; it's clamped to 1"
+        );
+        assert!(problems[2].starts_with("[apps.desktop.sway] is ignored: "));
+    }
+
+    #[test]
+    fn parses_shell() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.shell(), "sh");
+
+        let config = Config::from_toml("[general]\nshell = \"/usr/bin/bash\"").unwrap();
+        assert_eq!(config.general.shell, "/usr/bin/bash");
+        assert_eq!(config.shell(), "/usr/bin/bash");
+    }
+
+    #[test]
+    fn parses_post_launch_hook() {
+        assert!(Config::from_toml("")
+            .unwrap()
+            .general
+            .post_launch_hook
+            .is_empty());
+
+        let toml = r#"
+            [general]
+            post_launch_hook = "paplay /usr/share/sounds/launch.oga"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(
+            config.general.post_launch_hook,
+            "paplay /usr/share/sounds/launch.oga"
+        );
+    }
+
     #[test]
     fn use_history_defaults_to_true() {
         let config = Config::from_toml("").unwrap();
         assert!(config.search.use_history);
     }
 
+    #[test]
+    fn parses_stay_open_from_toml() {
+        let config = Config::from_toml("").unwrap();
+        assert!(!config.general.stay_open);
+
+        let config = Config::from_toml("[general]\nstay_open = true").unwrap();
+        assert!(config.general.stay_open);
+    }
+
+    fn expand(raw: &str) -> PathBuf {
+        expand_path(raw, Some(Path::new("/home/me")), |var| match var {
+            "XDG_STATE_HOME" => Some("/home/me/.state".into()),
+            "USER" => Some("me".into()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn data_dir_expands_tilde_and_variables() {
+        assert_eq!(expand("~/.config/yeet"), Path::new("/home/me/.config/yeet"));
+        assert_eq!(expand("~"), Path::new("/home/me"));
+        assert_eq!(
+            expand("$XDG_STATE_HOME/yeet"),
+            Path::new("/home/me/.state/yeet")
+        );
+        assert_eq!(expand("/data/${USER}_yeet"), Path::new("/data/me_yeet"));
+        assert_eq!(expand("/srv/~me/yeet"), Path::new("/srv/~me/yeet"));
+    }
+
+    #[test]
+    fn data_dir_keeps_unset_variables() {
+        assert_eq!(expand("$NOPE/yeet"), Path::new("$NOPE/yeet"));
+        assert_eq!(expand("${NOPE}/a$"), Path::new("${NOPE}/a$"));
+        assert_eq!(expand("${USER"), Path::new("${USER"));
+    }
+
+    #[test]
+    fn data_dir_is_unset_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.data_dir(), None);
+
+        let config = Config::from_toml("[general]\ndata_dir = \"/tmp/yeet\"").unwrap();
+        assert_eq!(config.data_dir(), Some(PathBuf::from("/tmp/yeet")));
+    }
+
+    #[test]
+    fn parses_history_format() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.history_format, HistoryFormat::Tsv);
+
+        let config = Config::from_toml("[general]\nhistory_format = \"json\"").unwrap();
+        assert_eq!(config.general.history_format, HistoryFormat::Json);
+
+        assert!(Config::from_toml("[general]\nhistory_format = \"csv\"").is_err());
+    }
+
+    #[test]
+    fn enter_launches_top_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.general.enter_launches_top);
+
+        let config = Config::from_toml("[general]\nenter_launches_top = false").unwrap();
+        assert!(!config.general.enter_launches_top);
+    }
+
+    #[test]
+    fn wrap_navigation_defaults_to_off() {
+        let config = Config::from_toml("").unwrap();
+        assert!(!config.general.wrap_navigation);
+
+        let config = Config::from_toml("[general]\nwrap_navigation = true").unwrap();
+        assert!(config.general.wrap_navigation);
+    }
+
+    #[test]
+    fn cancel_keys_default_to_escape() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.cancel_keys, vec!["Escape"]);
+
+        let config =
+            Config::from_toml("[general]\ncancel_keys = [\"Escape\", \"ctrl+c\"]").unwrap();
+        assert_eq!(config.general.cancel_keys, vec!["Escape", "ctrl+c"]);
+    }
+
+    #[test]
+    fn parses_include_categories() {
+        assert!(!Config::from_toml("").unwrap().search.include_categories);
+
+        let config = Config::from_toml("[search]\ninclude_categories = true").unwrap();
+        assert!(config.search.include_categories);
+    }
+
+    #[test]
+    fn parses_empty_order() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(
+            config.general.empty_order,
+            [ListOrder::Favorites, ListOrder::Recent, ListOrder::Alpha]
+        );
+
+        let config = Config::from_toml("[general]\nempty_order = [\"recent\", \"alpha\"]").unwrap();
+        assert_eq!(
+            config.general.empty_order,
+            [ListOrder::Recent, ListOrder::Alpha]
+        );
+        assert!(Config::from_toml("[general]\nempty_order = [\"frecent\"]").is_err());
+    }
+
+    #[test]
+    fn parses_multiline_paste() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.multiline_paste, MultilinePaste::Join);
+
+        let config = Config::from_toml("[general]\nmultiline_paste = \"first_line\"").unwrap();
+        assert_eq!(config.general.multiline_paste, MultilinePaste::FirstLine);
+    }
+
+    #[test]
+    fn parses_copy_target() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.copy_target, CopyTarget::Clipboard);
+
+        let config = Config::from_toml("[general]\ncopy_target = \"both\"").unwrap();
+        assert_eq!(config.general.copy_target, CopyTarget::Both);
+    }
+
+    #[test]
+    fn monitor_defaults_to_unset() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.monitor, None);
+    }
+
+    #[test]
+    fn parses_monitor_index() {
+        let config = Config::from_toml("[general]\nmonitor = 1").unwrap();
+        assert_eq!(config.general.monitor, Some(MonitorSelector::Index(1)));
+    }
+
+    #[test]
+    fn parses_monitor_connector_name() {
+        let config = Config::from_toml("[general]\nmonitor = \"DP-1\"").unwrap();
+        assert_eq!(
+            config.general.monitor,
+            Some(MonitorSelector::Connector("DP-1".into()))
+        );
+    }
+
+    #[test]
+    fn parses_monitor_fallback() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.general.monitor_fallback, MonitorFallback::Active);
+
+        let config = Config::from_toml("[general]\nmonitor_fallback = \"clamp\"").unwrap();
+        assert_eq!(config.general.monitor_fallback, MonitorFallback::Clamp);
+    }
+
+    #[test]
+    fn rejects_negative_monitor_index() {
+        assert!(Config::from_toml("[general]\nmonitor = -1").is_err());
+    }
+
+    #[test]
+    fn debounce_defaults_to_off() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.search.debounce_ms, 0);
+
+        let config = Config::from_toml("[search]\ndebounce_ms = 40").unwrap();
+        assert_eq!(config.search.debounce_ms, 40);
+    }
+
+    #[test]
+    fn parses_keyword_weight() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.search.keyword_weight, 0.8);
+
+        let config = Config::from_toml("[search]\nkeyword_weight = 0.5").unwrap();
+        assert_eq!(config.search.keyword_weight, 0.5);
+    }
+
+    #[test]
+    fn parses_exact_to_top() {
+        assert!(Config::from_toml("").unwrap().search.exact_to_top);
+        let config = Config::from_toml("[search]\nexact_to_top = false").unwrap();
+        assert!(!config.search.exact_to_top);
+    }
+
+    #[test]
+    fn parses_prefer_shorter() {
+        assert!(Config::from_toml("").unwrap().search.prefer_shorter);
+        let config = Config::from_toml("[search]\nprefer_shorter = false").unwrap();
+        assert!(!config.search.prefer_shorter);
+    }
+
+    #[test]
+    fn parses_matcher_backend() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.search.matcher, MatcherKind::Skim);
+
+        let config = Config::from_toml("[search]\nmatcher = \"clangd\"").unwrap();
+        assert_eq!(config.search.matcher, MatcherKind::Clangd);
+
+        assert!(Config::from_toml("[search]\nmatcher = \"fzf\"").is_err());
+    }
+
+    #[test]
+    fn parses_path_detection() {
+        assert!(!Config::from_toml("").unwrap().search.path_detection);
+        let toml = r#"
+            [search]
+            path_detection = true
+        "#;
+        assert!(Config::from_toml(toml).unwrap().search.path_detection);
+    }
+
+    #[test]
+    fn parses_positional_favorites() {
+        assert!(!Config::from_toml("").unwrap().search.positional_favorites);
+        let toml = r#"
+            [search]
+            positional_favorites = true
+        "#;
+        assert!(Config::from_toml(toml).unwrap().search.positional_favorites);
+    }
+
+    #[test]
+    fn parses_match_description() {
+        assert!(!Config::from_toml("").unwrap().search.match_description);
+        let toml = r#"
+            [search]
+            match_description = true
+        "#;
+        assert!(Config::from_toml(toml).unwrap().search.match_description);
+    }
+
+    #[test]
+    fn parses_open_urls() {
+        assert!(Config::from_toml("").unwrap().search.open_urls);
+
+        let toml = r#"
+            [search]
+            open_urls = false
+        "#;
+        assert!(!Config::from_toml(toml).unwrap().search.open_urls);
+    }
+
+    #[test]
+    fn parses_no_match_action() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.search.no_match_action, NoMatchAction::Empty);
+        assert_eq!(
+            config.search.web_search_url,
+            "https://duckduckgo.com/?q={query}"
+        );
+
+        let toml = r#"
+            [search]
+            no_match_action = "web_search"
+            web_search_url = "https://www.startpage.com/do/search?q={query}"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.search.no_match_action, NoMatchAction::WebSearch);
+        assert_eq!(
+            config.search.web_search_url,
+            "https://www.startpage.com/do/search?q={query}"
+        );
+    }
+
+    #[test]
+    fn parses_search_engines() {
+        assert!(Config::from_toml("").unwrap().search.engines.is_empty());
+
+        let toml = r#"
+            [[search.engines]]
+            keyword = "!w"
+            url = "https://en.wikipedia.org/w/index.php?search={query}"
+            name = "Wikipedia"
+
+            [[search.engines]]
+            keyword = "!gh"
+            url = "https://github.com/search?q="
+        "#;
+        let engines = Config::from_toml(toml).unwrap().search.engines;
+        assert_eq!(engines.len(), 2);
+        assert_eq!(engines[0].keyword, "!w");
+        assert_eq!(engines[0].name.as_deref(), Some("Wikipedia"));
+        assert_eq!(engines[1].url, "https://github.com/search?q=");
+        assert_eq!(engines[1].name, None);
+    }
+
     #[test]
     fn rejects_invalid_toml() {
         let bad_toml = r#"
@@ -0,0 +1,275 @@
+//! `general.control_socket`: a Unix socket in the runtime dir through which
+//! scripts and status bars drive a running launcher, one command per line:
+//!
+//! ```text
+//! echo toggle | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/yeet.sock
+//! ```
+//!
+//! Each command gets an `ok` or `error: ...` line back.
+
+use gtk4::glib::{self, ControlFlow, IOCondition, SourceId};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const SOCKET_FILE: &str = "yeet.sock";
+
+/// Longest command line a client may send; past it the connection is
+/// dropped rather than buffered without end.
+const MAX_LINE: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Rescan desktop entries and rebuild the window.
+    Reload,
+    /// Hide the window, or show it again.
+    Toggle,
+    Quit,
+    /// Replace the search text, showing the window if it was hidden.
+    Query(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let command = match word {
+            "query" => return Ok(Command::Query(rest.to_string())),
+            "reload" => Command::Reload,
+            "toggle" => Command::Toggle,
+            "quit" => Command::Quit,
+            "" => return Err("empty command".to_string()),
+            _ => return Err(format!("unknown command \"{word}\"")),
+        };
+        if rest.trim().is_empty() {
+            Ok(command)
+        } else {
+            Err(format!("{word} takes no argument"))
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/yeet.sock`; `None` without a runtime dir, as a socket
+/// in a shared dir like `/tmp` would take commands from other users.
+pub fn socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|dir| dir.join(SOCKET_FILE))
+}
+
+/// Runs one line from a client through `on_command`; the reply to send back.
+pub fn respond(line: &str, on_command: &dyn Fn(Command)) -> String {
+    match Command::parse(line) {
+        Ok(command) => {
+            on_command(command);
+            "ok".to_string()
+        }
+        Err(e) => format!("error: {e}"),
+    }
+}
+
+/// The listening socket; its file is removed when this is dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+    source: Option<SourceId>,
+}
+
+impl ControlSocket {
+    /// Listens on `path` and hands each command to `on_command` on the main
+    /// loop, as its line arrives; a slow client never holds the window up.
+    /// A socket file left behind by a yeet that's gone is replaced; one that
+    /// another instance still answers on is an error.
+    pub fn bind(path: PathBuf, on_command: impl Fn(Command) + 'static) -> io::Result<Self> {
+        let listener = match UnixListener::bind(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(&path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("another yeet is listening on {}", path.display()),
+                    ));
+                }
+                std::fs::remove_file(&path)?;
+                UnixListener::bind(&path)?
+            }
+            listener => listener?,
+        };
+        listener.set_nonblocking(true)?;
+
+        let on_command: Rc<dyn Fn(Command)> = Rc::new(on_command);
+        let fd = listener.as_raw_fd();
+        let source = glib::unix_fd_add_local(fd, IOCondition::IN, move |_, _| {
+            while let Ok((stream, _)) = listener.accept() {
+                if let Err(e) = serve(stream, on_command.clone()) {
+                    eprintln!("yeet: control socket: {e}");
+                }
+            }
+            ControlFlow::Continue
+        });
+
+        Ok(Self {
+            path,
+            source: Some(source),
+        })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Answers the lines `stream` sends whenever the main loop finds some to
+/// read, until the client hangs up.
+fn serve(mut stream: UnixStream, on_command: Rc<dyn Fn(Command)>) -> io::Result<()> {
+    stream.set_nonblocking(true)?;
+    let fd = stream.as_raw_fd();
+    let mut pending = Vec::new();
+    let condition = IOCondition::IN | IOCondition::HUP | IOCondition::ERR;
+    glib::unix_fd_add_local(fd, condition, move |_, _| {
+        match answer_available(&mut stream, &mut pending, &*on_command) {
+            Ok(true) => ControlFlow::Continue,
+            Ok(false) => ControlFlow::Break,
+            Err(e) => {
+                eprintln!("yeet: control socket: {e}");
+                ControlFlow::Break
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Reads what `client` has sent so far, without waiting for more, and
+/// answers every complete line of it; the rest stays in `pending` for the
+/// next call. `false` once the client has hung up, after answering a last
+/// line it didn't end.
+fn answer_available(
+    client: &mut UnixStream,
+    pending: &mut Vec<u8>,
+    on_command: &dyn Fn(Command),
+) -> io::Result<bool> {
+    let mut buf = [0; 1024];
+    let open = loop {
+        match client.read(&mut buf) {
+            Ok(0) => break false,
+            Ok(n) => pending.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break true,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    };
+    if !open && !pending.is_empty() && !pending.ends_with(b"\n") {
+        pending.push(b'\n');
+    }
+    while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = pending.drain(..=end).collect();
+        writeln!(
+            client,
+            "{}",
+            respond(&String::from_utf8_lossy(&line), on_command)
+        )?;
+    }
+    if pending.len() > MAX_LINE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "command line too long",
+        ));
+    }
+    Ok(open)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::net::Shutdown;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse("reload"), Ok(Command::Reload));
+        assert_eq!(Command::parse("toggle\n"), Ok(Command::Toggle));
+        assert_eq!(Command::parse("quit\r\n"), Ok(Command::Quit));
+        assert_eq!(
+            Command::parse("query fire fox"),
+            Ok(Command::Query("fire fox".to_string()))
+        );
+        assert_eq!(Command::parse("query"), Ok(Command::Query(String::new())));
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_stray_arguments() {
+        assert_eq!(
+            Command::parse("restart"),
+            Err("unknown command \"restart\"".to_string())
+        );
+        assert_eq!(
+            Command::parse("quit now"),
+            Err("quit takes no argument".to_string())
+        );
+        assert_eq!(Command::parse(""), Err("empty command".to_string()));
+        assert!(Command::parse("Reload").is_err());
+    }
+
+    #[test]
+    fn respond_dispatches_only_valid_commands() {
+        let received = RefCell::new(Vec::new());
+        let on_command = |command: Command| received.borrow_mut().push(command);
+
+        assert_eq!(respond("toggle", &on_command), "ok");
+        assert_eq!(respond("query term", &on_command), "ok");
+        assert_eq!(
+            respond("explode", &on_command),
+            "error: unknown command \"explode\""
+        );
+
+        assert_eq!(
+            *received.borrow(),
+            [Command::Toggle, Command::Query("term".to_string())]
+        );
+    }
+
+    #[test]
+    fn answers_lines_as_they_arrive_without_waiting() {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        server.set_nonblocking(true).unwrap();
+        let received = RefCell::new(Vec::new());
+        let on_command = |command: Command| received.borrow_mut().push(command);
+        let mut pending = Vec::new();
+
+        client.write_all(b"toggle\nquery fi").unwrap();
+        assert!(answer_available(&mut server, &mut pending, &on_command).unwrap());
+        assert_eq!(*received.borrow(), [Command::Toggle]);
+        // Nothing new to read: back at once, still connected.
+        assert!(answer_available(&mut server, &mut pending, &on_command).unwrap());
+
+        client.write_all(b"re fox\nquit").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        assert!(!answer_available(&mut server, &mut pending, &on_command).unwrap());
+        assert_eq!(
+            *received.borrow(),
+            [
+                Command::Toggle,
+                Command::Query("fire fox".to_string()),
+                Command::Quit
+            ]
+        );
+
+        drop(server);
+        let mut replies = String::new();
+        client.read_to_string(&mut replies).unwrap();
+        assert_eq!(replies, "ok\nok\nok\n");
+    }
+
+    #[test]
+    fn drops_clients_that_never_end_a_line() {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        server.set_nonblocking(true).unwrap();
+        let mut pending = Vec::new();
+
+        client.write_all(&[b'x'; MAX_LINE + 1]).unwrap();
+        assert!(answer_available(&mut server, &mut pending, &|_| {}).is_err());
+    }
+}
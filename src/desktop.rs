@@ -1,8 +1,16 @@
-use crate::config::{Config, CustomApp};
-use freedesktop_desktop_entry::{get_languages_from_env, DesktopEntry, Iter as DesktopIter};
-use std::collections::HashSet;
-use std::ffi::OsString;
-use std::path::PathBuf;
+use crate::collate::Collator;
+use crate::config::{
+    Config, CustomAction, CustomApp, DedupPrefer, FileApps, IoClass, KeywordMap, Niceness,
+    NoMatchAction, SearchConfig, SearchEngine,
+};
+use crate::exec;
+use crate::recent::RecentFile;
+use freedesktop_desktop_entry::{get_languages_from_env, DesktopEntry};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 #[derive(Debug, Clone)]
@@ -14,24 +22,127 @@ enum LaunchCommand {
 #[derive(Debug, Clone)]
 pub struct App {
     pub name: String,
+    /// `name` with a qualifier when it collides with another app's, e.g.
+    /// "Terminal (kitty)"; only the list shows it.
+    label: Option<String>,
     pub icon: Option<String>,
     pub description: Option<String>,
     pub keywords: Vec<String>,
     pub terminal: bool,
+    /// Terminal to use instead of `general.terminal`.
+    pub preferred_terminal: Option<String>,
+    /// `wrap_terminal` from `[[apps.overrides]]`; `None` decides from
+    /// `terminal` and whether the app is a terminal itself.
+    pub wrap_terminal: Option<bool>,
     pub favorite: bool,
+    /// Where the app is in `apps.favorites`, first at 0.
+    pub favorite_position: Option<usize>,
+    /// Desktop file id (`firefox` for `firefox.desktop`); `None` for custom
+    /// entries.
+    pub desktop_id: Option<String>,
+    /// Built from the typed query rather than discovered; never recorded in
+    /// launch history.
+    pub from_query: bool,
+    /// A `--recent` file that's no longer there; listed greyed out.
+    pub missing: bool,
+    pub source: AppSource,
+    /// Needs a second Enter before launching (`confirm = true`).
+    pub confirm: bool,
+    /// `nice` and `ionice` from `[[apps.overrides]]`, over the
+    /// `general.launch_nice`/`launch_ionice` defaults.
+    pub nice: Option<Niceness>,
+    pub ionice: Option<IoClass>,
+    /// The desktop entry's `GenericName`, used to tell same-named apps apart.
+    generic_name: Option<String>,
+    /// The desktop entry's `StartupWMClass`: the app-id (or X11 class) its
+    /// windows get when that isn't the desktop id. Only `app_for_window`
+    /// reads it, and nothing outside the tests matches windows yet.
+    #[cfg(test)]
+    startup_wm_class: Option<String>,
+    /// The desktop entry's `Categories`, as written (`TextEditor`).
+    categories: Vec<String>,
+    /// Interfaces from the desktop entry's `Implements`, e.g.
+    /// `org.freedesktop.FileManager1`.
+    implements: Vec<String>,
+    /// Desktop actions followed by `[[apps.actions]]` entries.
+    actions: Vec<Action>,
+    url_handler: Option<UrlHandler>,
     launch: LaunchCommand,
 }
 
+/// Where an app came from, to tell duplicates apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppSource {
+    /// The desktop file it was read from.
+    File(PathBuf),
+    /// An `[[apps.custom]]` entry.
+    Custom,
+    /// Made up at runtime: a dmenu item or a result built from the query.
+    Generated,
+}
+
+impl fmt::Display for AppSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppSource::File(path) => write!(f, "{}", path.display()),
+            AppSource::Custom => f.write_str("custom"),
+            AppSource::Generated => Ok(()),
+        }
+    }
+}
+
+/// Something else an app can do, like a browser's "New Private Window":
+/// a desktop entry's `[Desktop Action]` or an `[[apps.actions]]` entry.
+#[derive(Debug, Clone)]
+struct Action {
+    label: String,
+    launch: LaunchCommand,
+}
+
+/// What a desktop entry needs to open a typed URL: the URL schemes it
+/// handles and its raw `Exec`, re-parsed with the URL in `%u`.
+#[derive(Debug, Clone)]
+struct UrlHandler {
+    schemes: Vec<String>,
+    exec: String,
+    entry_name: Option<String>,
+    path: String,
+}
+
 impl App {
     fn from_custom(custom: &CustomApp) -> Self {
+        let launch = match &custom.launch_and_type {
+            Some(typed) => LaunchCommand::Shell(format!("{typed}\nexec {}", custom.exec)),
+            None => LaunchCommand::Shell(custom.exec.clone()),
+        };
+
         Self {
             name: custom.name.clone(),
             icon: custom.icon.clone(),
             description: None,
             keywords: custom.keywords.clone(),
-            terminal: false,
+            // Typed commands need a terminal to be seen in.
+            terminal: custom.terminal.is_some() || custom.launch_and_type.is_some(),
+            preferred_terminal: custom.terminal.clone(),
+            wrap_terminal: None,
             favorite: false,
-            launch: LaunchCommand::Shell(custom.exec.clone()),
+            favorite_position: None,
+            desktop_id: None,
+            from_query: false,
+            missing: false,
+            label: None,
+            source: AppSource::Custom,
+            confirm: custom.confirm,
+            nice: None,
+            ionice: None,
+            generic_name: None,
+            #[cfg(test)]
+            startup_wm_class: None,
+            categories: Vec::new(),
+            implements: Vec::new(),
+            actions: Vec::new(),
+            url_handler: None,
+            launch,
         }
     }
 
@@ -44,43 +155,662 @@ impl App {
             description: None,
             keywords: Vec::new(),
             terminal: false,
+            preferred_terminal: None,
+            wrap_terminal: None,
             favorite: false,
+            favorite_position: None,
+            desktop_id: None,
+            from_query: false,
+            missing: false,
+            label: None,
+            source: AppSource::Generated,
+            confirm: false,
+            nice: None,
+            ionice: None,
+            generic_name: None,
+            #[cfg(test)]
+            startup_wm_class: None,
+            categories: Vec::new(),
+            implements: Vec::new(),
+            actions: Vec::new(),
+            url_handler: None,
+        }
+    }
+
+    /// The stand-in result offered for `search.no_match_action` when nothing
+    /// matches `query`. A web search starting with a bang from
+    /// `search.engines` goes to that engine.
+    pub fn for_unmatched_query(
+        action: NoMatchAction,
+        query: &str,
+        search: &SearchConfig,
+    ) -> Option<Self> {
+        let (name, icon, launch) = match action {
+            NoMatchAction::Empty => return None,
+            NoMatchAction::RunQuery => (
+                format!("Run: {query}"),
+                "utilities-terminal",
+                LaunchCommand::Shell(query.to_string()),
+            ),
+            NoMatchAction::WebSearch => match route_bang(&search.engines, query) {
+                Some((engine, rest)) => (
+                    format!(
+                        "Search {} for \u{201c}{rest}\u{201d}",
+                        engine.name.as_deref().unwrap_or(&engine.keyword)
+                    ),
+                    "web-browser",
+                    LaunchCommand::Direct(vec![
+                        "xdg-open".into(),
+                        web_search_link(&engine.url, rest),
+                    ]),
+                ),
+                None => (
+                    format!("Search the web for \u{201c}{query}\u{201d}"),
+                    "web-browser",
+                    LaunchCommand::Direct(vec![
+                        "xdg-open".into(),
+                        web_search_link(&search.web_search_url, query),
+                    ]),
+                ),
+            },
+        };
+
+        Some(Self {
+            icon: Some(icon.to_string()),
+            from_query: true,
+            launch,
+            ..Self::plain(name)
+        })
+    }
+
+    /// An entry that opens a `--recent` file with its default handler,
+    /// described by the directory it's in.
+    pub fn for_recent_file(file: &RecentFile) -> Self {
+        let description = match file.path() {
+            Some(path) => path.parent().map(|dir| dir.display().to_string()),
+            None => Some(file.uri.clone()),
+        };
+        Self {
+            icon: file.icon(),
+            description,
+            from_query: true,
+            missing: file.is_missing(),
+            launch: LaunchCommand::Direct(vec!["xdg-open".into(), file.uri.clone()]),
+            ..Self::plain(file.name())
+        }
+    }
+
+    /// "Open <path>", which opens `path` with its default handler; offered
+    /// for queries naming a path (`search.path_detection`).
+    pub fn open_path(path: &Path) -> Self {
+        let icon = if path.is_dir() {
+            "folder"
+        } else {
+            "document-open"
+        };
+        Self {
+            icon: Some(icon.to_string()),
+            from_query: true,
+            launch: LaunchCommand::Direct(vec![
+                "xdg-open".into(),
+                path.to_string_lossy().into_owned(),
+            ]),
+            ..Self::plain(format!("Open {}", path.display()))
+        }
+    }
+
+    /// A copy of the app that opens `url`, when it handles `scheme`.
+    pub fn open_url(&self, scheme: &str, url: &str) -> Option<App> {
+        let handler = self.url_handler.as_ref()?;
+        if !handler.schemes.iter().any(|s| s == scheme) {
+            return None;
+        }
+
+        let uris = [url.to_string()];
+        let values = exec::FieldValues {
+            uris: &uris,
+            icon: self.icon.as_deref(),
+            name: handler.entry_name.as_deref(),
+            path: Some(&handler.path),
+        };
+        let args = exec::parse(&handler.exec, &values).ok()?;
+        Some(App {
+            launch: LaunchCommand::Direct(args),
+            ..self.clone()
+        })
+    }
+
+    /// The command the app runs as a single shell-safe line, without any
+    /// terminal wrapper; what `--print-only` prints.
+    pub fn command_line(&self) -> String {
+        match &self.launch {
+            LaunchCommand::Direct(args) => args
+                .iter()
+                .map(|arg| shell_quote(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            LaunchCommand::Shell(exec) => exec.clone(),
+        }
+    }
+
+    /// What tells this app apart from the others in a list: its desktop id,
+    /// or its name for entries without one.
+    pub fn identity(&self) -> &str {
+        self.desktop_id.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The name the list shows: `name`, qualified where it collides with
+    /// another app's.
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The app's actions as launchable entries named "App: Action", so a
+    /// query naming both ("firefox private") matches the action. They keep
+    /// the app's terminal settings and keywords and have no actions of their
+    /// own.
+    pub fn action_apps(&self) -> Vec<App> {
+        self.actions
+            .iter()
+            .map(|action| App {
+                name: format!("{}: {}", self.name, action.label),
+                label: self
+                    .label
+                    .as_ref()
+                    .map(|label| format!("{label}: {}", action.label)),
+                description: None,
+                confirm: false,
+                actions: Vec::new(),
+                url_handler: None,
+                launch: action.launch.clone(),
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// File name of the program the app runs, e.g. `kitty` for
+    /// `/usr/bin/kitty --single-instance` or `env GDK_BACKEND=x11 kitty`.
+    fn program_name(&self) -> String {
+        let program = match &self.launch {
+            LaunchCommand::Direct(args) => exec::program(args.iter().map(String::as_str)),
+            LaunchCommand::Shell(exec) => exec::program(exec.split_whitespace()),
         }
+        .unwrap_or_default();
+        Path::new(program)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| program.to_string())
     }
 }
 
+/// Quotes `arg` for `sh` unless it's made of characters that never need it.
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// The engine whose keyword is the query's first word, with the rest of
+/// the query to search for.
+fn route_bang<'a>(
+    engines: &'a [SearchEngine],
+    query: &'a str,
+) -> Option<(&'a SearchEngine, &'a str)> {
+    let (bang, rest) = query.split_once(char::is_whitespace).unwrap_or((query, ""));
+    let engine = engines.iter().find(|engine| engine.keyword == bang)?;
+    Some((engine, rest.trim_start()))
+}
+
+/// Fills `{query}` in the search URL template with the percent-encoded
+/// query, or appends it when the template has no placeholder.
+fn web_search_link(template: &str, query: &str) -> String {
+    let encoded = percent_encode(query);
+    if template.contains("{query}") {
+        template.replace("{query}", &encoded)
+    } else {
+        format!("{template}{encoded}")
+    }
+}
+
+/// RFC 3986 percent-encoding of everything but unreserved characters.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
 pub fn discover_apps(config: &Config) -> Vec<App> {
     let exclude_set: HashSet<&str> = config.apps.exclude.iter().map(|s| s.as_str()).collect();
 
-    let all_dirs: Vec<PathBuf> = xdg_application_dirs()
+    let xdg_dirs = match dirs::data_local_dir() {
+        Some(data_home) => prefer_dirs(
+            xdg_application_dirs(config.apps.nix_dirs),
+            &data_home,
+            config.apps.dedup_prefer,
+        ),
+        None => xdg_application_dirs(config.apps.nix_dirs),
+    };
+    // XDG dirs are often missing and that's fine, but a configured dir that
+    // can't be scanned is most likely a typo worth pointing out.
+    for dir in &config.apps.extra_dirs {
+        if let Some(problem) = dir_problem(dir) {
+            eprintln!(
+                "Warning: skipping apps.extra_dirs entry {}: {problem}",
+                dir.display()
+            );
+        }
+    }
+    let all_dirs: Vec<PathBuf> = xdg_dirs
         .into_iter()
         .chain(config.apps.extra_dirs.iter().cloned())
         .collect();
 
     let locales = get_languages_from_env();
-    let mut apps = apps_from_dirs(all_dirs, &exclude_set, &locales);
-
-    for custom in &config.apps.custom {
-        apps.push(App::from_custom(custom));
+    let mut apps = apps_from_files(
+        desktop_files(all_dirs, config.apps.follow_symlinks),
+        &exclude_set,
+        &locales,
+        config.apps.file_apps,
+    );
+    if config.apps.hide_flatpak_runtimes {
+        apps.retain(|app| !app.desktop_id.as_deref().is_some_and(is_flatpak_runtime));
     }
 
-    let favorites_set: HashSet<&str> = config.apps.favorites.iter().map(|s| s.as_str()).collect();
+    add_custom_apps(&mut apps, &config.apps.custom, config.apps.custom_override);
+
     for app in &mut apps {
-        app.favorite = favorites_set.contains(app.name.as_str());
+        let Some(app_override) = config
+            .apps
+            .overrides
+            .iter()
+            .rev()
+            .find(|o| o.name == app.name)
+        else {
+            continue;
+        };
+        if app_override.terminal.is_some() {
+            app.preferred_terminal = app_override.terminal.clone();
+        }
+        if app_override.wrap_terminal.is_some() {
+            app.wrap_terminal = app_override.wrap_terminal;
+        }
+        if let Some(confirm) = app_override.confirm {
+            app.confirm = confirm;
+        }
+        if app_override.nice.is_some() {
+            app.nice = app_override.nice;
+        }
+        if app_override.ionice.is_some() {
+            app.ionice = app_override.ionice;
+        }
+    }
+
+    seed_keywords(&mut apps, &config.apps.keyword_map);
+    add_custom_actions(&mut apps, &config.apps.actions);
+    if config.search.include_categories {
+        add_category_keywords(&mut apps);
+    }
+    if config.search.match_description {
+        add_description_keywords(&mut apps);
     }
 
-    apps.sort_by_cached_key(|a| (!a.favorite, a.name.to_lowercase()));
+    mark_favorites(&mut apps, &config.apps.favorites);
+
+    disambiguate_names(&mut apps);
+    let collator = Collator::from_env();
+    apps.sort_by_cached_key(|a| (!a.favorite, collator.sort_key(&a.name)));
 
     apps
 }
 
-fn apps_from_dirs(dirs: Vec<PathBuf>, exclude: &HashSet<&str>, locales: &[String]) -> Vec<App> {
+/// Last id components of flatpak runtimes, SDK extensions and their
+/// companion refs, which sometimes export launchers of their own.
+const FLATPAK_RUNTIME_PARTS: &[&str] =
+    &["BaseApp", "Debug", "Locale", "Platform", "Sdk", "Sources"];
+
+/// Whether desktop id `id` belongs to a flatpak runtime rather than an app,
+/// e.g. `org.freedesktop.Sdk.Debug` or `io.qt.qtwebengine.BaseApp`. Only
+/// components after the reverse-DNS prefix count, so `org.sdk.Editor` is
+/// an app.
+fn is_flatpak_runtime(id: &str) -> bool {
+    let id = id.strip_suffix(".desktop").unwrap_or(id);
+    id.split('.')
+        .skip(2)
+        .any(|part| FLATPAK_RUNTIME_PARTS.contains(&part))
+}
+
+/// Appends `[[apps.custom]]` entries. With `replace`
+/// (`apps.custom_override`), a custom entry takes the place of discovered
+/// apps of the same name instead of being listed beside them.
+fn add_custom_apps(apps: &mut Vec<App>, custom: &[CustomApp], replace: bool) {
+    if replace {
+        let names: HashSet<&str> = custom.iter().map(|c| c.name.as_str()).collect();
+        apps.retain(|app| app.desktop_id.is_none() || !names.contains(app.name.as_str()));
+    }
+    apps.extend(custom.iter().map(App::from_custom));
+}
+
+/// Adds `apps.keyword_map` keywords to every app whose display name or
+/// executable name matches an entry, skipping ones it already has.
+fn seed_keywords(apps: &mut [App], keyword_map: &[KeywordMap]) {
+    if keyword_map.is_empty() {
+        return;
+    }
+    for app in apps {
+        let program = app.program_name();
+        for entry in keyword_map {
+            if entry.name != app.name && entry.name != program {
+                continue;
+            }
+            for keyword in &entry.keywords {
+                if !app.keywords.contains(keyword) {
+                    app.keywords.push(keyword.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Adds `[[apps.actions]]` to the apps they name. One labelled like a
+/// desktop action replaces it; the rest follow the desktop actions.
+fn add_custom_actions(apps: &mut [App], actions: &[CustomAction]) {
+    for custom in actions {
+        let action = Action {
+            label: custom.label.clone(),
+            launch: LaunchCommand::Shell(custom.exec.clone()),
+        };
+        for app in apps.iter_mut().filter(|app| app.name == custom.name) {
+            match app.actions.iter_mut().find(|a| a.label == action.label) {
+                Some(existing) => *existing = action.clone(),
+                None => app.actions.push(action.clone()),
+            }
+        }
+    }
+}
+
+/// Makes each app's `Categories` searchable (`search.include_categories`)
+/// by adding them to its keywords as lowercase words, e.g. "text editor".
+fn add_category_keywords(apps: &mut [App]) {
+    for app in apps {
+        for words in app.categories.iter().filter_map(|c| category_words(c)) {
+            if !app
+                .keywords
+                .iter()
+                .any(|kw| kw.eq_ignore_ascii_case(&words))
+            {
+                app.keywords.push(words);
+            }
+        }
+    }
+}
+
+/// Makes each app's description (the desktop entry's `Comment`) searchable
+/// (`search.match_description`) by adding it to its keywords. Whether it's
+/// shown is up to `appearance.show_descriptions`.
+fn add_description_keywords(apps: &mut [App]) {
+    for app in apps {
+        if let Some(description) = app.description.clone() {
+            app.keywords.push(description);
+        }
+    }
+}
+
+/// The groups of `appearance.group_by_category`, in list order, each with
+/// the freedesktop main categories filed under it.
+const CATEGORY_GROUPS: [(&str, &[&str]); 9] = [
+    ("Internet", &["Network"]),
+    ("Development", &["Development"]),
+    ("Office", &["Office"]),
+    ("Graphics", &["Graphics"]),
+    ("Multimedia", &["AudioVideo", "Audio", "Video"]),
+    ("Games", &["Game"]),
+    ("Education", &["Education", "Science"]),
+    ("Utilities", &["Utility"]),
+    ("System", &["System", "Settings"]),
+];
+
+/// The group of apps without a main category.
+const OTHER_GROUP: &str = "Other";
+
+/// Position in `CATEGORY_GROUPS` and title of the group `categories` go
+/// under: that of the first main category among them, so `Game;Network;`
+/// is a game. Apps without one come last, under "Other".
+fn category_group(categories: &[String]) -> (usize, &'static str) {
+    categories
+        .iter()
+        .find_map(|category| {
+            CATEGORY_GROUPS
+                .iter()
+                .position(|(_, main)| main.contains(&category.as_str()))
+        })
+        .map(|group| (group, CATEGORY_GROUPS[group].0))
+        .unwrap_or((CATEGORY_GROUPS.len(), OTHER_GROUP))
+}
+
+/// `appearance.group_by_category`: reorders `indices` (into `apps`) by
+/// category group, keeping their order within each group, and returns the
+/// `(position, title)` of each group's header. A list that falls in one
+/// group gets no headers.
+pub fn group_by_category(apps: &[App], indices: &mut [usize]) -> Vec<(usize, &'static str)> {
+    indices.sort_by_key(|&i| category_group(&apps[i].categories).0);
+    let mut headers: Vec<(usize, &'static str)> = Vec::new();
+    for (position, &i) in indices.iter().enumerate() {
+        let (_, title) = category_group(&apps[i].categories);
+        if headers.last().map(|&(_, last)| last) != Some(title) {
+            headers.push((position, title));
+        }
+    }
+    if headers.len() < 2 {
+        headers.clear();
+    }
+    headers
+}
+
+/// A category name as lowercase words: `AudioVideo` becomes "audio video",
+/// `3DGraphics` "3d graphics". Vendor extensions (`X-...`) have none.
+fn category_words(category: &str) -> Option<String> {
+    if category.is_empty() || category.starts_with("X-") {
+        return None;
+    }
+    let chars: Vec<char> = category.chars().collect();
+    let mut words = String::with_capacity(category.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        // A capital starts a word after a lowercase letter ("AudioVideo"),
+        // or after an acronym when a lowercase letter follows ("3DGraphics").
+        let starts_word = c.is_uppercase()
+            && prev.is_some_and(|prev| {
+                prev.is_lowercase()
+                    || ((prev.is_uppercase() || prev.is_ascii_digit())
+                        && next.is_some_and(|next| next.is_lowercase()))
+            });
+        if starts_word {
+            words.push(' ');
+        }
+        words.extend(c.to_lowercase());
+    }
+    Some(words)
+}
+
+/// The app a window with Wayland app-id (or X11 class) `app_id` belongs
+/// to: the one whose `StartupWMClass` matches, else the one whose desktop id
+/// does, either whole or by its last part (`org.gnome.Nautilus` for a
+/// `nautilus` window). Case is ignored, as compositors and toolkits differ.
+#[cfg(test)]
+pub fn app_for_window<'a>(apps: &'a [App], app_id: &str) -> Option<&'a App> {
+    let by_wm_class = apps.iter().find(|app| {
+        app.startup_wm_class
+            .as_deref()
+            .is_some_and(|class| class.eq_ignore_ascii_case(app_id))
+    });
+    by_wm_class.or_else(|| {
+        apps.iter().find(|app| {
+            app.desktop_id.as_deref().is_some_and(|id| {
+                let last = id.rsplit('.').next().unwrap_or(id);
+                id.eq_ignore_ascii_case(app_id) || last.eq_ignore_ascii_case(app_id)
+            })
+        })
+    })
+}
+
+/// The app with desktop id `id` for `--launch`, given with or without the
+/// `.desktop` suffix as `gtk-launch` accepts it. Custom entries have no id.
+pub fn app_by_id<'a>(apps: &'a [App], id: &str) -> Option<&'a App> {
+    let id = id.strip_suffix(".desktop").unwrap_or(id);
+    apps.iter()
+        .find(|app| app.desktop_id.as_deref() == Some(id))
+}
+
+/// The apps whose desktop entry lists `interface` in `Implements`, for
+/// `--launch-interface`.
+pub fn implementing<'a>(apps: &'a [App], interface: &str) -> Vec<&'a App> {
+    apps.iter()
+        .filter(|app| app.implements.iter().any(|i| i == interface))
+        .collect()
+}
+
+/// Drops apps without a findable icon (`apps.require_icon`). `resolves`
+/// answers for icon names; absolute icon paths are checked on disk.
+pub fn retain_with_icons(apps: &mut Vec<App>, resolves: impl Fn(&str) -> bool) {
+    apps.retain(|app| {
+        app.icon.as_deref().is_some_and(|icon| {
+            let path = Path::new(icon);
+            if path.is_absolute() {
+                path.is_file()
+            } else {
+                resolves(icon)
+            }
+        })
+    });
+}
+
+/// Marks the apps named in `favorites`, noting where each is listed; a name
+/// listed twice counts where it's first.
+fn mark_favorites(apps: &mut [App], favorites: &[String]) {
+    let positions: HashMap<&str, usize> = favorites
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+    for app in apps {
+        app.favorite_position = positions.get(app.name.as_str()).copied();
+        app.favorite = app.favorite_position.is_some();
+    }
+}
+
+/// Labels apps whose display names collide with a qualifier, e.g. two
+/// "Terminal" entries are listed as "Terminal (kitty)" and "Terminal
+/// (foot)". The `GenericName` is used when it tells the group apart,
+/// otherwise the executable's file name. `name` itself is left alone, as
+/// history, favorites, overrides and printed output go by it.
+fn disambiguate_names(apps: &mut [App]) {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, app) in apps.iter().enumerate() {
+        groups.entry(app.name.clone()).or_default().push(i);
+    }
+
+    for indices in groups.into_values().filter(|group| group.len() > 1) {
+        let generic_names: Vec<Option<&str>> = indices
+            .iter()
+            .map(|&i| apps[i].generic_name.as_deref())
+            .collect();
+        let use_generic = generic_names.iter().all(Option::is_some)
+            && generic_names.iter().collect::<HashSet<_>>().len() == indices.len();
+
+        let qualifiers: Vec<String> = indices
+            .iter()
+            .map(|&i| match &apps[i].generic_name {
+                Some(generic) if use_generic => generic.clone(),
+                _ => apps[i].program_name(),
+            })
+            .collect();
+        for (&i, qualifier) in indices.iter().zip(qualifiers) {
+            apps[i].label = Some(format!("{} ({})", apps[i].name, qualifier));
+        }
+    }
+}
+
+/// Why `dir` can't be scanned for desktop entries, if it can't.
+fn dir_problem(dir: &Path) -> Option<String> {
+    match fs::read_dir(dir) {
+        Ok(_) => None,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some("it doesn't exist".into()),
+        Err(_) if !dir.is_dir() => Some("it isn't a directory".into()),
+        Err(e) => Some(format!("it can't be read ({e})")),
+    }
+}
+
+/// `.desktop` files under `dirs`, in order, including subdirectories.
+/// Every directory is read once by its canonical path, so a symlink loop
+/// can't keep discovery going; with `follow_symlinks` off, symlinked
+/// subdirectories aren't entered at all (symlinked files still count).
+fn desktop_files(dirs: Vec<PathBuf>, follow_symlinks: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    for dir in &dirs {
+        walk_desktop_dir(dir, follow_symlinks, &mut visited, &mut files);
+    }
+    files
+}
+
+fn walk_desktop_dir(
+    dir: &Path,
+    follow_symlinks: bool,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) {
+    let Ok(canonical) = fs::canonicalize(dir) else {
+        return;
+    };
+    if !visited.insert(canonical) {
+        return;
+    }
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut entries: Vec<fs::DirEntry> = read_dir.filter_map(Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+        if path.is_dir() {
+            if follow_symlinks || !symlink {
+                walk_desktop_dir(&path, follow_symlinks, visited, files);
+            }
+        } else if path.extension() == Some(OsStr::new("desktop")) {
+            files.push(path);
+        }
+    }
+}
+
+fn apps_from_files(
+    files: Vec<PathBuf>,
+    exclude: &HashSet<&str>,
+    locales: &[String],
+    file_apps: FileApps,
+) -> Vec<App> {
     let mut apps = Vec::new();
     // XDG precedence: a desktop file id seen in an earlier dir shadows later
     // ones entirely, even if the earlier entry is hidden.
     let mut seen: HashSet<OsString> = HashSet::new();
 
-    for path in DesktopIter::new(dirs.into_iter()) {
+    for path in files {
         let Some(file_name) = path.file_name() else {
             continue;
         };
@@ -96,9 +826,12 @@ fn apps_from_dirs(dirs: Vec<PathBuf>, exclude: &HashSet<&str>, locales: &[String
             let Some(name) = entry.name(locales) else {
                 continue;
             };
-            let exec_args = match entry.parse_exec() {
-                Ok(args) if !args.is_empty() => args,
-                _ => continue,
+            // Entries with neither `Exec` nor `DBusActivatable=true` can't
+            // be started.
+            let Some(exec_args) = exec_args(&entry, file_apps, locales)
+                .or_else(|| dbus_activatable(&entry).then(|| dbus_activate_args(&entry.appid)))
+            else {
+                continue;
             };
 
             if exclude.contains(name.as_ref()) {
@@ -109,12 +842,32 @@ fn apps_from_dirs(dirs: Vec<PathBuf>, exclude: &HashSet<&str>, locales: &[String
                 name: name.to_string(),
                 icon: entry.icon().map(|s| s.to_string()),
                 description: entry.comment(locales).map(|s| s.to_string()),
-                keywords: entry
-                    .keywords(locales)
-                    .map(|kws| kws.into_iter().map(|s| s.to_string()).collect())
-                    .unwrap_or_default(),
+                keywords: keywords_in_locales(&entry, locales),
                 terminal: entry.terminal(),
+                preferred_terminal: None,
+                wrap_terminal: None,
                 favorite: false,
+                favorite_position: None,
+                desktop_id: Some(entry.appid.clone()),
+                from_query: false,
+                missing: false,
+                label: None,
+                source: AppSource::File(path.clone()),
+                confirm: false,
+                nice: None,
+                ionice: None,
+                generic_name: entry.generic_name(locales).map(|s| s.to_string()),
+                #[cfg(test)]
+                startup_wm_class: entry.startup_wm_class().map(str::to_string),
+                actions: desktop_actions(&entry, locales),
+                categories: entry
+                    .categories()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                implements: list_key(&entry, "Implements"),
+                url_handler: url_handler(&entry, locales),
                 launch: LaunchCommand::Direct(exec_args),
             });
         }
@@ -123,7 +876,206 @@ fn apps_from_dirs(dirs: Vec<PathBuf>, exclude: &HashSet<&str>, locales: &[String
     apps
 }
 
-fn xdg_application_dirs() -> Vec<PathBuf> {
+/// `Keywords` in every configured locale plus the untranslated ones,
+/// without repeats (ignoring case), so a bilingual user finds an app in either language.
+fn keywords_in_locales(entry: &DesktopEntry, locales: &[String]) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    let per_locale = locales.iter().map(std::slice::from_ref);
+    for locales in per_locale.chain(std::iter::once(&[][..])) {
+        for keyword in entry.keywords(locales).unwrap_or_default() {
+            let lower = keyword.to_lowercase();
+            if !keywords.iter().any(|k| k.to_lowercase() == lower) {
+                keywords.push(keyword.into_owned());
+            }
+        }
+    }
+    keywords
+}
+
+fn exec_args(entry: &DesktopEntry, file_apps: FileApps, locales: &[String]) -> Option<Vec<String>> {
+    let exec_line = entry.exec()?;
+    let uris = match file_apps {
+        FileApps::Drop if exec::takes_files(exec_line) => return None,
+        FileApps::Home if exec::takes_files(exec_line) => {
+            vec![dirs::home_dir()?.to_string_lossy().into_owned()]
+        }
+        _ => Vec::new(),
+    };
+    let name = entry.name(locales);
+    let path = entry.path.to_string_lossy();
+    let values = exec::FieldValues {
+        uris: &uris,
+        icon: entry.icon(),
+        name: name.as_deref(),
+        path: Some(&path),
+    };
+
+    match exec::parse(exec_line, &values) {
+        Ok(args) => Some(args),
+        Err(e) => {
+            eprintln!("Warning: skipping {}: Exec {}", entry.path.display(), e);
+            None
+        }
+    }
+}
+
+/// The entries of a `;`-separated desktop entry key, without empty ones.
+fn list_key(entry: &DesktopEntry, key: &str) -> Vec<String> {
+    entry
+        .desktop_entry(key)
+        .map(|value| {
+            value
+                .split(';')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether the entry has no `Exec` and is started over D-Bus instead.
+fn dbus_activatable(entry: &DesktopEntry) -> bool {
+    entry.exec().is_none()
+        && entry
+            .desktop_entry("DBusActivatable")
+            .is_some_and(|value| value.trim() == "true")
+}
+
+/// Activates app `id` through `org.freedesktop.Application`, which starts
+/// it if it isn't running; see the Desktop Entry spec's "D-Bus Activation".
+fn dbus_activate_args(id: &str) -> Vec<String> {
+    let object_path = format!("/{}", id.replace('.', "/").replace('-', "_"));
+    [
+        "gdbus",
+        "call",
+        "--session",
+        "--dest",
+        id,
+        "--object-path",
+        &object_path,
+        "--method",
+        "org.freedesktop.Application.Activate",
+        "{}",
+    ]
+    .map(str::to_string)
+    .to_vec()
+}
+
+/// The entry's `[Desktop Action ...]` groups listed in `Actions`, in order.
+/// Actions with no name or a malformed `Exec` are skipped.
+fn desktop_actions(entry: &DesktopEntry, locales: &[String]) -> Vec<Action> {
+    let path = entry.path.to_string_lossy();
+    entry
+        .actions()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|id| {
+            let label = entry.action_name(id, locales)?;
+            let values = exec::FieldValues {
+                icon: entry.icon(),
+                name: Some(&label),
+                path: Some(&path),
+                ..Default::default()
+            };
+            let args = exec::parse(entry.action_exec(id)?, &values).ok()?;
+            Some(Action {
+                label: label.to_string(),
+                launch: LaunchCommand::Direct(args),
+            })
+        })
+        .collect()
+}
+
+/// Schemes come from `x-scheme-handler/*` MIME types; anything taking
+/// `%u`/`%U` can also open `file://` URLs.
+fn url_handler(entry: &DesktopEntry, locales: &[String]) -> Option<UrlHandler> {
+    let exec_line = entry.exec()?;
+    if !exec::takes_urls(exec_line) {
+        return None;
+    }
+
+    let mut schemes: Vec<String> = entry
+        .mime_type()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|mime| mime.strip_prefix("x-scheme-handler/"))
+        .map(str::to_ascii_lowercase)
+        .collect();
+    schemes.push("file".into());
+
+    Some(UrlHandler {
+        schemes,
+        exec: exec_line.to_string(),
+        entry_name: entry.name(locales).map(|s| s.to_string()),
+        path: entry.path.to_string_lossy().into_owned(),
+    })
+}
+
+/// The scheme of a query that looks like a URL: `scheme://...`, or one of
+/// the common schemes without an authority such as `mailto:`.
+pub fn url_scheme(query: &str) -> Option<String> {
+    const OPAQUE_SCHEMES: [&str; 5] = ["mailto", "tel", "sms", "magnet", "xmpp"];
+
+    let query = query.trim();
+    if query.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let (scheme, rest) = query.split_once(':')?;
+    let valid = scheme
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid {
+        return None;
+    }
+
+    let scheme = scheme.to_ascii_lowercase();
+    let looks_like_url = match rest.strip_prefix("//") {
+        Some(after) => !after.is_empty() || scheme == "file",
+        None => OPAQUE_SCHEMES.contains(&scheme.as_str()) && !rest.is_empty(),
+    };
+    looks_like_url.then_some(scheme)
+}
+
+/// The path a query names when it looks like one: absolute (`/etc/hosts`),
+/// in the home dir (`~`, `~/notes`) or relative to `cwd` (`./build`,
+/// `../x`). Other queries with slashes, like `AC/DC`, aren't paths.
+pub fn query_path(query: &str, home: Option<&Path>, cwd: &Path) -> Option<PathBuf> {
+    let query = query.trim();
+    let path = if query.starts_with('/') {
+        PathBuf::from(query)
+    } else if let Some(rest) = query
+        .strip_prefix('~')
+        .filter(|r| r.is_empty() || r.starts_with('/'))
+    {
+        home?.join(rest.trim_start_matches('/'))
+    } else if matches!(query, "." | "..") || query.starts_with("./") || query.starts_with("../") {
+        cwd.join(query)
+    } else {
+        return None;
+    };
+    // Drops `.` parts and trailing slashes: `/home/me/./x/` is `/home/me/x`.
+    Some(path.components().collect())
+}
+
+/// Orders `dirs` so the preferred copy of a desktop file id comes first,
+/// as the earliest one wins: the user's dirs under `data_home` first for
+/// `Local` (XDG order), system dirs first for `System`. Order within each
+/// group is kept.
+fn prefer_dirs(mut dirs: Vec<PathBuf>, data_home: &Path, prefer: DedupPrefer) -> Vec<PathBuf> {
+    if prefer == DedupPrefer::System {
+        dirs.sort_by_key(|dir| dir.starts_with(data_home));
+    }
+    dirs
+}
+
+/// With `nix`, Nix profile directories that exist are added last, in case
+/// the session's environment doesn't point at them.
+fn xdg_application_dirs(nix: bool) -> Vec<PathBuf> {
     let mut dirs = Vec::new();
 
     if let Some(data_home) = dirs::data_local_dir() {
@@ -138,74 +1090,355 @@ fn xdg_application_dirs() -> Vec<PathBuf> {
     }
     dirs.push(PathBuf::from("/var/lib/flatpak/exports/share/applications"));
 
+    if nix {
+        let user = std::env::var("USER").ok();
+        let home = dirs::home_dir();
+        dirs.extend(nix_application_dirs(
+            Path::new("/"),
+            home.as_deref(),
+            user.as_deref(),
+        ));
+    }
+
     dirs
 }
 
-pub fn launch_app(app: &App, terminal: &str) {
-    let command = match &app.launch {
-        LaunchCommand::Direct(args) => spawn_direct(args, app.terminal.then_some(terminal)),
-        LaunchCommand::Shell(exec) => spawn_shell(exec, app.terminal.then_some(terminal)),
-    };
+/// The applications dirs of the user's Nix profile, their Home Manager
+/// profile and the NixOS system profile, under `root`, that exist.
+fn nix_application_dirs(root: &Path, home: Option<&Path>, user: Option<&str>) -> Vec<PathBuf> {
+    let mut profiles = Vec::new();
+    if let Some(home) = home {
+        profiles.push(home.join(".nix-profile"));
+        profiles.push(home.join(".local/state/nix/profile"));
+    }
+    if let Some(user) = user.filter(|user| !user.is_empty() && !user.contains('/')) {
+        profiles.push(root.join("etc/profiles/per-user").join(user));
+    }
+    profiles.push(root.join("run/current-system/sw"));
+
+    profiles
+        .into_iter()
+        .map(|profile| profile.join("share/applications"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Launches `app`, recording it in history unless `record_history` is off
+/// (`--no-history`); `false` when it couldn't be started (the error has
+/// been reported). `activation_token` lets its window take focus; see
+/// `with_activation`.
+pub fn launch_app(
+    app: &App,
+    config: &Config,
+    activation_token: Option<&str>,
+    record_history: bool,
+) -> bool {
+    let command = launch_command(app, config, activation_token).and_then(spawn);
 
     match command {
-        Ok(_) => crate::history::record_launch(&app.name),
-        Err(e) => eprintln!("Failed to launch {}: {}", app.name, e),
+        Ok(_) => {
+            if record_history && !app.from_query {
+                crate::history::record_launch(
+                    &app.name,
+                    app.identity(),
+                    &config.apps.history_exclude,
+                    config.data_dir().as_deref(),
+                    config.general.history_format,
+                    crate::history::TrimPolicy {
+                        recent: config.search.history_keep_recent,
+                        frequent: config.search.history_keep_frequent,
+                    },
+                );
+            }
+            if let Some(hook) = post_launch_hook(app, &config.general.post_launch_hook) {
+                if let Err(e) = spawn(hook) {
+                    eprintln!("Failed to run post_launch_hook: {}", e);
+                }
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to launch {}: {}", app.name, e);
+            false
+        }
     }
 }
 
-fn spawn_direct(args: &[String], terminal: Option<&str>) -> std::io::Result<std::process::Child> {
-    if args.is_empty() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "empty command",
-        ));
+/// `--dry-run`: what `launch_app` would run for `app`, as one shell-quoted
+/// line. Environment it sets or clears comes first, through `env`.
+pub fn launch_line(app: &App, config: &Config) -> std::io::Result<String> {
+    let cmd = launch_command(app, config, None)?;
+    let mut words: Vec<String> = Vec::new();
+    let mut assignments = Vec::new();
+    for (key, value) in cmd.get_envs() {
+        let key = key.to_string_lossy();
+        match value {
+            Some(value) => assignments.push(format!("{key}={}", value.to_string_lossy())),
+            None => words.extend(["-u".to_string(), key.into_owned()]),
+        }
     }
+    words.extend(assignments);
+    if !words.is_empty() {
+        words.insert(0, "env".to_string());
+    }
+    words.extend(
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned()),
+    );
+    Ok(words
+        .iter()
+        .map(|word| shell_quote(word))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
 
-    let mut cmd = if let Some(terminal) = terminal {
-        let mut command = Command::new(terminal);
-        command.arg("-e").arg(&args[0]).args(&args[1..]);
-        command
-    } else {
-        let mut command = Command::new(&args[0]);
-        command.args(&args[1..]);
-        command
-    };
+/// The whole process `launch_app` starts for `app`: its command (in a
+/// terminal if it needs one) under `nice`/`ionice`, inside
+/// `general.launch_prefix`, with the activation token in its environment.
+fn launch_command(
+    app: &App,
+    config: &Config,
+    activation_token: Option<&str>,
+) -> std::io::Result<Command> {
+    let nice = app.nice.or(config.general.launch_nice);
+    let ionice = app.ionice.or(config.general.launch_ionice);
+    build_command(app, &config.general.terminal, &config.shell())
+        .map(|cmd| with_priority(cmd, nice, ionice, on_path))
+        .map(|cmd| with_launch_prefix(cmd, &config.general.launch_prefix))
+        .map(|cmd| with_activation(cmd, activation_token, config.general.focus_on_launch))
+}
 
-    cmd.stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
+/// `general.post_launch_hook` for a launched `app`, or `None` when unset.
+/// `{name}` and `{exec}` are replaced within each word, so each stays a
+/// single argument however many spaces the app's name or command has.
+fn post_launch_hook(app: &App, template: &str) -> Option<Command> {
+    let exec = app.command_line();
+    let mut words = template
+        .split_whitespace()
+        .map(|word| word.replace("{name}", &app.name).replace("{exec}", &exec));
+    let mut hook = Command::new(words.next()?);
+    hook.args(words);
+    Some(hook)
 }
 
-fn spawn_shell(exec: &str, terminal: Option<&str>) -> std::io::Result<std::process::Child> {
-    let mut cmd = if let Some(terminal) = terminal {
-        let mut command = Command::new(terminal);
-        command.arg("-e").arg("sh").arg("-c").arg(exec);
-        command
-    } else {
-        let mut command = Command::new("sh");
-        command.arg("-c").arg(exec);
-        command
-    };
+/// The process `launch_app` would start for `app`, not yet spawned; `shell`
+/// runs commands that need one.
+fn build_command(app: &App, default_terminal: &str, shell: &str) -> std::io::Result<Command> {
+    let terminal = app
+        .preferred_terminal
+        .as_deref()
+        .unwrap_or(default_terminal);
+    // `Terminal=true` entries whose Exec already opens a terminal (e.g.
+    // `kitty -e htop`) would otherwise end up in two.
+    let wrap = app
+        .wrap_terminal
+        .unwrap_or_else(|| app.terminal && !is_terminal(&app.program_name(), terminal));
+    let terminal = wrap.then_some(terminal);
 
-    cmd.stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
+    match &app.launch {
+        LaunchCommand::Direct(args) => direct_command(args, terminal),
+        LaunchCommand::Shell(exec) => Ok(shell_command(exec, terminal, shell)),
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
+/// Terminal emulators recognised as an app's program, besides the
+/// configured terminal itself.
+const KNOWN_TERMINALS: &[&str] = &[
+    "alacritty",
+    "foot",
+    "footclient",
+    "ghostty",
+    "gnome-terminal",
+    "kgx",
+    "kitty",
+    "konsole",
+    "ptyxis",
+    "rio",
+    "st",
+    "terminator",
+    "tilix",
+    "urxvt",
+    "wezterm",
+    "xfce4-terminal",
+    "xterm",
+];
 
-    #[test]
+/// Whether `program` (a file name) is a terminal emulator: a known one, or
+/// the one yeet would wrap it in.
+fn is_terminal(program: &str, terminal: &str) -> bool {
+    let terminal = Path::new(terminal)
+        .file_name()
+        .map(|name| name.to_string_lossy());
+    KNOWN_TERMINALS.contains(&program) || terminal.is_some_and(|name| name == program)
+}
+
+/// Runs `cmd` under `nice -n N` and/or `ionice -c N`, inside any
+/// `general.launch_prefix` so the priority applies to the app itself. A
+/// tool that `available` can't find is skipped with a warning rather than
+/// failing the launch.
+fn with_priority(
+    cmd: Command,
+    nice: Option<Niceness>,
+    ionice: Option<IoClass>,
+    available: impl Fn(&str) -> bool,
+) -> Command {
+    let mut prefix: Vec<String> = Vec::new();
+    let mut add = |tool: &str, args: [String; 2]| {
+        if available(tool) {
+            prefix.push(tool.to_string());
+            prefix.extend(args);
+        } else {
+            eprintln!("Warning: {tool} not found; launching without it");
+        }
+    };
+    if let Some(nice) = nice {
+        add("nice", ["-n".into(), nice.get().to_string()]);
+    }
+    if let Some(ionice) = ionice {
+        add("ionice", ["-c".into(), ionice.get().to_string()]);
+    }
+    let Some((program, args)) = prefix.split_first() else {
+        return cmd;
+    };
+
+    let mut wrapped = Command::new(program);
+    wrapped
+        .args(args)
+        .arg(cmd.get_program())
+        .args(cmd.get_args());
+    wrapped
+}
+
+/// Whether `program` is an executable file in a `$PATH` directory.
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// Wraps `cmd`, terminal included, in `general.launch_prefix`: the `{cmd}`
+/// word is replaced by the full command line, which is appended when the
+/// prefix has no placeholder. An empty prefix leaves `cmd` untouched.
+fn with_launch_prefix(cmd: Command, prefix: &str) -> Command {
+    let template: Vec<&str> = prefix.split_whitespace().collect();
+    let Some((program, rest)) = template.split_first() else {
+        return cmd;
+    };
+
+    let argv: Vec<&OsStr> = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .collect();
+    let mut wrapped = Command::new(program);
+    let mut placed = false;
+    for part in rest {
+        if *part == "{cmd}" {
+            wrapped.args(&argv);
+            placed = true;
+        } else {
+            wrapped.arg(part);
+        }
+    }
+    if !placed {
+        wrapped.args(&argv);
+    }
+    wrapped
+}
+
+/// Environment variables through which a launched app receives its
+/// activation token: xdg-activation on Wayland, startup notification on X11.
+const ACTIVATION_VARS: [&str; 2] = ["XDG_ACTIVATION_TOKEN", "DESKTOP_STARTUP_ID"];
+
+/// Hands `token` to the launched app so the compositor lets its window take
+/// focus. Without `focus` (`general.focus_on_launch = false`) no token is
+/// passed on, including one yeet itself was started with, so the app opens
+/// in the background.
+fn with_activation(mut cmd: Command, token: Option<&str>, focus: bool) -> Command {
+    for var in ACTIVATION_VARS {
+        match token.filter(|_| focus) {
+            Some(token) => cmd.env(var, token),
+            None if !focus => cmd.env_remove(var),
+            None => &mut cmd,
+        };
+    }
+    cmd
+}
+
+fn spawn(mut cmd: Command) -> std::io::Result<std::process::Child> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+fn direct_command(args: &[String], terminal: Option<&str>) -> std::io::Result<Command> {
+    if args.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "empty command",
+        ));
+    }
+
+    let cmd = if let Some(terminal) = terminal {
+        let mut command = Command::new(terminal);
+        command.arg("-e").arg(&args[0]).args(&args[1..]);
+        command
+    } else {
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..]);
+        command
+    };
+
+    Ok(cmd)
+}
+
+/// Characters that make a command line need a shell: operators, quoting,
+/// expansions and globs.
+const SHELL_METACHARACTERS: &str = "|&;<>()$`\\\"'*?[]{}~#=%!\n";
+
+/// Whether `exec` is more than plain words, so it has to run through
+/// `<shell> -c`.
+fn needs_shell(exec: &str) -> bool {
+    exec.contains(|c| SHELL_METACHARACTERS.contains(c))
+}
+
+/// `exec` on its own or in `terminal`. Plain words start directly as the
+/// program and its arguments, so a terminal runs (and titles itself after)
+/// the program, not the shell; anything needing the shell, like a pipe,
+/// becomes `<shell> -c '<exec>'`.
+fn shell_command(exec: &str, terminal: Option<&str>, shell: &str) -> Command {
+    let argv: Vec<&str> = if needs_shell(exec) || exec.trim().is_empty() {
+        vec![shell, "-c", exec]
+    } else {
+        exec.split_whitespace().collect()
+    };
+    let mut command = match terminal {
+        Some(terminal) => {
+            let mut command = Command::new(terminal);
+            command.arg("-e").arg(argv[0]);
+            command
+        }
+        None => Command::new(argv[0]),
+    };
+    command.args(&argv[1..]);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchText;
+    use std::fs;
+
+    #[test]
     fn custom_app_uses_shell_launch_mode() {
         let custom = CustomApp {
             name: "My Script".to_string(),
             exec: "echo hello".to_string(),
             icon: None,
             keywords: Vec::new(),
+            terminal: None,
+            launch_and_type: None,
+            confirm: false,
         };
 
         let app = App::from_custom(&custom);
@@ -215,12 +1448,1190 @@ mod tests {
         }
     }
 
+    fn command_line(cmd: &Command) -> Vec<String> {
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|s| s.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn terminal_app(preferred_terminal: Option<&str>) -> App {
+        App {
+            terminal: true,
+            preferred_terminal: preferred_terminal.map(str::to_string),
+            launch: LaunchCommand::Direct(vec!["htop".into(), "-d".into(), "10".into()]),
+            ..App::plain("Htop".into())
+        }
+    }
+
+    #[test]
+    fn terminal_app_uses_general_terminal() {
+        let cmd = build_command(&terminal_app(None), "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["alacritty", "-e", "htop", "-d", "10"]);
+    }
+
+    #[test]
+    fn terminal_override_replaces_general_terminal() {
+        let cmd = build_command(&terminal_app(Some("kitty")), "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["kitty", "-e", "htop", "-d", "10"]);
+    }
+
+    #[test]
+    fn terminal_exec_is_not_wrapped_again() {
+        let app = App {
+            launch: LaunchCommand::Direct(vec![
+                "/usr/bin/kitty".into(),
+                "-e".into(),
+                "htop".into(),
+            ]),
+            ..terminal_app(None)
+        };
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["/usr/bin/kitty", "-e", "htop"]);
+
+        // The configured terminal counts even when it isn't a known one.
+        let app = App {
+            launch: LaunchCommand::Direct(vec!["myterm".into(), "-e".into(), "htop".into()]),
+            ..terminal_app(None)
+        };
+        let cmd = build_command(&app, "/opt/bin/myterm", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["myterm", "-e", "htop"]);
+    }
+
+    #[test]
+    fn terminal_entry_running_its_own_terminal_is_launched_as_is() {
+        let (_fixture, apps) = fixture_apps(
+            "yeet_test_terminal_exec",
+            &[(
+                "vim.desktop",
+                "[Desktop Entry]\nType=Application\nName=Vim\n\
+                 Exec=xterm -e vim %F\nTerminal=true\n",
+            )],
+        );
+        assert!(apps[0].terminal);
+        let cmd = build_command(&apps[0], "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["xterm", "-e", "vim"]);
+    }
+
+    #[test]
+    fn env_prefixed_exec_launches_through_env() {
+        let args = exec::parse("env A=1 B=2 prog %U", &exec::FieldValues::default()).unwrap();
+        let app = App {
+            launch: LaunchCommand::Direct(args),
+            ..App::plain("Prog".into())
+        };
+        assert_eq!(app.program_name(), "prog");
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["env", "A=1", "B=2", "prog"]);
+
+        let app = App {
+            terminal: true,
+            ..app
+        };
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(
+            command_line(&cmd),
+            ["alacritty", "-e", "env", "A=1", "B=2", "prog"]
+        );
+
+        // An env-prefixed terminal is still recognised as one.
+        let app = App {
+            launch: LaunchCommand::Direct(vec![
+                "env".into(),
+                "TERM=xterm-256color".into(),
+                "kitty".into(),
+                "-e".into(),
+                "htop".into(),
+            ]),
+            ..terminal_app(None)
+        };
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(
+            command_line(&cmd),
+            ["env", "TERM=xterm-256color", "kitty", "-e", "htop"]
+        );
+    }
+
+    #[test]
+    fn wrap_terminal_override_wins_over_detection() {
+        let app = App {
+            wrap_terminal: Some(true),
+            launch: LaunchCommand::Direct(vec!["foot".into(), "htop".into()]),
+            ..terminal_app(None)
+        };
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["alacritty", "-e", "foot", "htop"]);
+
+        let app = App {
+            wrap_terminal: Some(false),
+            ..terminal_app(None)
+        };
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["htop", "-d", "10"]);
+    }
+
+    #[test]
+    fn custom_app_with_terminal_runs_in_it() {
+        let custom = CustomApp {
+            terminal: Some("foot".to_string()),
+            ..custom("btop", "btop --utf-force")
+        };
+
+        let cmd = build_command(&App::from_custom(&custom), "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["foot", "-e", "btop", "--utf-force"]);
+    }
+
+    #[test]
+    fn terminal_command_needing_a_shell_runs_through_sh() {
+        for exec in [
+            "journalctl -f | grep yeet",
+            "make && ./run",
+            "echo $HOME",
+            "nvim ~/notes.md",
+            "printf 'a b'",
+        ] {
+            assert_eq!(
+                command_line(&shell_command(exec, Some("foot"), "sh")),
+                ["foot", "-e", "sh", "-c", exec],
+                "{exec}"
+            );
+        }
+        assert_eq!(
+            command_line(&shell_command("htop  -d 10", Some("foot"), "sh")),
+            ["foot", "-e", "htop", "-d", "10"]
+        );
+        // Without a terminal, the same split between argv and shell.
+        assert_eq!(
+            command_line(&shell_command("htop -d 10", None, "sh")),
+            ["htop", "-d", "10"]
+        );
+        assert_eq!(
+            command_line(&shell_command("htop | tee log", None, "sh")),
+            ["sh", "-c", "htop | tee log"]
+        );
+    }
+
+    #[test]
+    fn shell_commands_use_the_configured_shell() {
+        let app = App::from_custom(&custom("Build", "make -j8 |& tee build.log"));
+        let cmd = build_command(&app, "alacritty", "/usr/bin/bash").unwrap();
+        assert_eq!(
+            command_line(&cmd),
+            ["/usr/bin/bash", "-c", "make -j8 |& tee build.log"]
+        );
+
+        assert_eq!(
+            command_line(&shell_command("ls **/*.rs", Some("foot"), "zsh")),
+            ["foot", "-e", "zsh", "-c", "ls **/*.rs"]
+        );
+        // Plain words in a terminal skip the shell, whichever it is.
+        assert_eq!(
+            command_line(&shell_command("htop", Some("foot"), "zsh")),
+            ["foot", "-e", "htop"]
+        );
+    }
+
     #[test]
-    fn spawn_direct_rejects_empty_command() {
-        let err = spawn_direct(&[], None).expect_err("empty command must fail");
+    fn launch_and_type_runs_before_exec_in_terminal() {
+        let custom = CustomApp {
+            launch_and_type: Some("cd ~/projects/yeet && git status".to_string()),
+            ..custom("Yeet Shell", "bash")
+        };
+
+        let cmd = build_command(&App::from_custom(&custom), "alacritty", "sh").unwrap();
+        assert_eq!(
+            command_line(&cmd),
+            [
+                "alacritty",
+                "-e",
+                "sh",
+                "-c",
+                "cd ~/projects/yeet && git status\nexec bash"
+            ]
+        );
+    }
+
+    #[test]
+    fn run_query_fallback_runs_query_through_shell() {
+        let app = App::for_unmatched_query(
+            NoMatchAction::RunQuery,
+            "notify-send 'hi there'",
+            &SearchConfig::default(),
+        )
+        .unwrap();
+        assert!(app.from_query);
+
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["sh", "-c", "notify-send 'hi there'"]);
+    }
+
+    #[test]
+    fn recent_files_open_with_xdg_open() {
+        let file = RecentFile {
+            uri: "file:///nonexistent/yeet/Q3%20report.pdf".to_string(),
+            used: "2024-05-03T17:45:10Z".to_string(),
+            mime_type: Some("application/pdf".to_string()),
+        };
+        let app = App::for_recent_file(&file);
+        assert_eq!(app.name, "Q3 report.pdf");
+        assert_eq!(app.description.as_deref(), Some("/nonexistent/yeet"));
+        assert_eq!(app.icon.as_deref(), Some("application-pdf"));
+        assert!(app.missing);
+        assert!(app.from_query);
+
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(
+            command_line(&cmd),
+            ["xdg-open", "file:///nonexistent/yeet/Q3%20report.pdf"]
+        );
+    }
+
+    #[test]
+    fn web_search_fallback_encodes_query() {
+        let app = App::for_unmatched_query(
+            NoMatchAction::WebSearch,
+            "c++ & rust?",
+            &SearchConfig::default(),
+        )
+        .unwrap();
+
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(
+            command_line(&cmd),
+            [
+                "xdg-open",
+                "https://duckduckgo.com/?q=c%2B%2B%20%26%20rust%3F"
+            ]
+        );
+    }
+
+    fn with_engines() -> SearchConfig {
+        let engine = |keyword: &str, url: &str, name: Option<&str>| SearchEngine {
+            keyword: keyword.into(),
+            url: url.into(),
+            name: name.map(Into::into),
+        };
+        SearchConfig {
+            engines: vec![
+                engine(
+                    "!w",
+                    "https://en.wikipedia.org/w/index.php?search={query}",
+                    Some("Wikipedia"),
+                ),
+                engine("!gh", "https://github.com/search?q=", None),
+            ],
+            ..SearchConfig::default()
+        }
+    }
+
+    #[test]
+    fn bangs_are_the_first_word_only() {
+        let search = with_engines();
+        let routed =
+            |query| route_bang(&search.engines, query).map(|(e, rest)| (&*e.keyword, rest));
+
+        assert_eq!(routed("!w rust  lang"), Some(("!w", "rust  lang")));
+        assert_eq!(routed("!gh"), Some(("!gh", "")));
+        assert_eq!(routed("!wiki rust"), None);
+        assert_eq!(routed("rust !w"), None);
+        assert_eq!(routed("!W rust"), None);
+    }
+
+    #[test]
+    fn bang_searches_the_rest_on_its_engine() {
+        let app =
+            App::for_unmatched_query(NoMatchAction::WebSearch, "!w c++ & rust?", &with_engines())
+                .unwrap();
+        assert_eq!(app.name, "Search Wikipedia for \u{201c}c++ & rust?\u{201d}");
+        assert_eq!(
+            command_line(&build_command(&app, "alacritty", "sh").unwrap()),
+            [
+                "xdg-open",
+                "https://en.wikipedia.org/w/index.php?search=c%2B%2B%20%26%20rust%3F"
+            ]
+        );
+
+        let app = App::for_unmatched_query(NoMatchAction::WebSearch, "!gh yeet", &with_engines())
+            .unwrap();
+        assert_eq!(app.name, "Search !gh for \u{201c}yeet\u{201d}");
+    }
+
+    #[test]
+    fn queries_without_a_bang_use_the_default_engine() {
+        let app = App::for_unmatched_query(NoMatchAction::WebSearch, "!nope rust", &with_engines())
+            .unwrap();
+        assert_eq!(
+            command_line(&build_command(&app, "alacritty", "sh").unwrap()),
+            ["xdg-open", "https://duckduckgo.com/?q=%21nope%20rust"]
+        );
+    }
+
+    #[test]
+    fn web_search_link_appends_without_placeholder() {
+        assert_eq!(
+            web_search_link("https://example.com/search?q=", "a/b"),
+            "https://example.com/search?q=a%2Fb"
+        );
+    }
+
+    #[test]
+    fn percent_encode_handles_unicode() {
+        assert_eq!(percent_encode("café-1_2.3~"), "caf%C3%A9-1_2.3~");
+    }
+
+    #[test]
+    fn triggers_route_to_their_backend() {
+        use crate::config::TriggerMode;
+        use crate::search::detect_trigger;
+
+        let triggers: std::collections::BTreeMap<_, _> =
+            [(">", TriggerMode::Run), ("?", TriggerMode::WebSearch)]
+                .map(|(prefix, mode)| (prefix.to_string(), mode))
+                .into();
+        let routed = |query: &str| {
+            let (mode, rest) = detect_trigger(&triggers, query).unwrap();
+            let search = SearchConfig {
+                web_search_url: "https://example.com/?q=".into(),
+                ..SearchConfig::default()
+            };
+            let app = App::for_unmatched_query(mode.action(), rest, &search).unwrap();
+            command_line(&build_command(&app, "alacritty", "sh").unwrap())
+        };
+
+        assert_eq!(routed("> make -j8"), ["make", "-j8"]);
+        assert_eq!(routed("?yeet"), ["xdg-open", "https://example.com/?q=yeet"]);
+    }
+
+    #[test]
+    fn empty_action_offers_no_fallback() {
+        assert!(
+            App::for_unmatched_query(NoMatchAction::Empty, "x", &SearchConfig::default()).is_none()
+        );
+    }
+
+    #[test]
+    fn launch_prefix_wraps_terminal_command() {
+        let cmd = build_command(&terminal_app(None), "alacritty", "sh").unwrap();
+        let cmd = with_launch_prefix(cmd, "uwsm app -- {cmd}");
+
+        assert_eq!(
+            command_line(&cmd),
+            ["uwsm", "app", "--", "alacritty", "-e", "htop", "-d", "10"]
+        );
+    }
+
+    #[test]
+    fn launch_line_shows_terminal_and_launch_prefix() {
+        let config: Config = toml::from_str(
+            "[general]\nterminal = \"alacritty\"\nlaunch_prefix = \"uwsm app -- {cmd}\"",
+        )
+        .unwrap();
+        let custom = CustomApp {
+            terminal: Some("foot".to_string()),
+            ..custom("Logs", "journalctl -f | grep 'kernel: '")
+        };
+
+        assert_eq!(
+            launch_line(&App::from_custom(&custom), &config).unwrap(),
+            "uwsm app -- foot -e sh -c 'journalctl -f | grep '\\''kernel: '\\'''"
+        );
+
+        // Launching in the background clears the activation variables.
+        let config: Config = toml::from_str("[general]\nfocus_on_launch = false").unwrap();
+        assert_eq!(
+            launch_line(&App::plain("htop".into()), &config).unwrap(),
+            "env -u DESKTOP_STARTUP_ID -u XDG_ACTIVATION_TOKEN htop"
+        );
+    }
+
+    #[test]
+    fn priorities_prefix_the_command() {
+        let nice = Niceness::try_from(10).ok();
+        let ionice = IoClass::try_from(3).ok();
+        let cmd = || build_command(&terminal_app(None), "alacritty", "sh").unwrap();
+
+        assert_eq!(
+            command_line(&with_priority(cmd(), nice, ionice, |_| true)),
+            [
+                "nice",
+                "-n",
+                "10",
+                "ionice",
+                "-c",
+                "3",
+                "alacritty",
+                "-e",
+                "htop",
+                "-d",
+                "10"
+            ]
+        );
+        assert_eq!(
+            command_line(&with_priority(cmd(), None, ionice, |_| true)),
+            ["ionice", "-c", "3", "alacritty", "-e", "htop", "-d", "10"]
+        );
+        assert_eq!(
+            command_line(&with_priority(cmd(), nice, ionice, |tool| tool == "nice")),
+            ["nice", "-n", "10", "alacritty", "-e", "htop", "-d", "10"]
+        );
+        assert_eq!(
+            command_line(&with_priority(cmd(), None, None, |_| true)),
+            ["alacritty", "-e", "htop", "-d", "10"]
+        );
+
+        let prefixed = with_launch_prefix(
+            with_priority(cmd(), nice, None, |_| true),
+            "uwsm app -- {cmd}",
+        );
+        assert_eq!(
+            command_line(&prefixed)[..6],
+            ["uwsm", "app", "--", "nice", "-n", "10"]
+        );
+    }
+
+    #[test]
+    fn launch_prefix_without_placeholder_appends_command() {
+        let app = App::from_custom(&custom("Notes", "nvim --clean"));
+        let cmd = with_launch_prefix(build_command(&app, "alacritty", "sh").unwrap(), "app2unit");
+
+        assert_eq!(command_line(&cmd), ["app2unit", "nvim", "--clean"]);
+    }
+
+    #[test]
+    fn launch_prefix_placeholder_can_sit_mid_template() {
+        let cmd = direct_command(&["firefox".to_string()], None).unwrap();
+        let cmd = with_launch_prefix(cmd, "systemd-run --user {cmd} ");
+
+        assert_eq!(command_line(&cmd), ["systemd-run", "--user", "firefox"]);
+    }
+
+    #[test]
+    fn empty_launch_prefix_launches_directly() {
+        let cmd = direct_command(&["firefox".to_string()], Some("kitty")).unwrap();
+        let cmd = with_launch_prefix(cmd, "  ");
+
+        assert_eq!(command_line(&cmd), ["kitty", "-e", "firefox"]);
+    }
+
+    fn activation_env(cmd: &Command) -> Vec<(String, Option<String>)> {
+        cmd.get_envs()
+            .map(|(var, value)| {
+                let text = |s: &OsStr| s.to_string_lossy().into_owned();
+                (text(var), value.map(text))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn activation_token_is_passed_only_with_focus_on_launch() {
+        let launch = || direct_command(&["firefox".to_string()], None).unwrap();
+
+        let mut env = activation_env(&with_activation(launch(), Some("tok-1"), true));
+        env.sort();
+        assert_eq!(
+            env,
+            [
+                ("DESKTOP_STARTUP_ID".to_string(), Some("tok-1".to_string())),
+                (
+                    "XDG_ACTIVATION_TOKEN".to_string(),
+                    Some("tok-1".to_string())
+                ),
+            ]
+        );
+
+        // No token to hand on: an inherited one is left alone.
+        assert!(activation_env(&with_activation(launch(), None, true)).is_empty());
+
+        // Background launch: the token is withheld and inherited ones cleared.
+        for token in [Some("tok-1"), None] {
+            let mut env = activation_env(&with_activation(launch(), token, false));
+            env.sort();
+            assert_eq!(
+                env,
+                [
+                    ("DESKTOP_STARTUP_ID".to_string(), None),
+                    ("XDG_ACTIVATION_TOKEN".to_string(), None),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn no_history_launches_leave_the_history_file_alone() {
+        let dir = std::env::temp_dir().join("yeet_test_no_history");
+        let _ = fs::remove_dir_all(&dir);
+        let config: Config =
+            toml::from_str(&format!("[general]\ndata_dir = \"{}\"", dir.display())).unwrap();
+        let app = App {
+            launch: LaunchCommand::Direct(vec!["true".into()]),
+            ..App::plain("True".into())
+        };
+        let history = crate::history::history_path(Some(&dir), crate::config::HistoryFormat::Tsv);
+
+        assert!(launch_app(&app, &config, None, false));
+        assert!(!history.exists());
+
+        assert!(launch_app(&app, &config, None, true));
+        assert!(fs::read_to_string(&history).unwrap().ends_with("\tTrue\n"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn post_launch_hook_substitutes_placeholders() {
+        let app = App {
+            launch: LaunchCommand::Direct(vec!["files".into(), "--new-window".into()]),
+            ..App::plain("Files Reader".into())
+        };
+        let hook = post_launch_hook(&app, "logger -t yeet {name} exec={exec}").unwrap();
+        assert_eq!(
+            command_line(&hook),
+            [
+                "logger",
+                "-t",
+                "yeet",
+                "Files Reader",
+                "exec=files --new-window"
+            ]
+        );
+
+        assert!(post_launch_hook(&app, " ").is_none());
+    }
+
+    #[test]
+    fn detects_url_shaped_queries() {
+        assert_eq!(
+            url_scheme("https://example.com/a?b=c"),
+            Some("https".into())
+        );
+        assert_eq!(url_scheme("  HTTP://Example.com "), Some("http".into()));
+        assert_eq!(url_scheme("mailto:me@example.com"), Some("mailto".into()));
+        assert_eq!(url_scheme("file:///home/me/notes.md"), Some("file".into()));
+        assert_eq!(url_scheme("steam://run/440"), Some("steam".into()));
+
+        assert_eq!(url_scheme("firefox"), None);
+        assert_eq!(url_scheme("https://"), None);
+        assert_eq!(url_scheme("mailto:"), None);
+        assert_eq!(url_scheme("note: buy milk"), None);
+        assert_eq!(url_scheme("c:foo"), None);
+        assert_eq!(url_scheme("1http://x"), None);
+    }
+
+    #[test]
+    fn detects_paths_but_not_names_with_slashes() {
+        let home = Path::new("/home/me");
+        let cwd = Path::new("/work/yeet");
+        let path = |query: &str| query_path(query, Some(home), cwd);
+
+        assert_eq!(path("/etc/hosts"), Some(PathBuf::from("/etc/hosts")));
+        assert_eq!(path("  /tmp/ "), Some(PathBuf::from("/tmp")));
+        assert_eq!(path("~"), Some(PathBuf::from("/home/me")));
+        assert_eq!(
+            path("~/notes/todo.md"),
+            Some(PathBuf::from("/home/me/notes/todo.md"))
+        );
+        assert_eq!(path("./target/"), Some(PathBuf::from("/work/yeet/target")));
+        assert_eq!(path("../other"), Some(PathBuf::from("/work/yeet/../other")));
+        assert_eq!(path("."), Some(PathBuf::from("/work/yeet")));
+
+        // App names and other queries that happen to contain slashes.
+        assert_eq!(path("AC/DC"), None);
+        assert_eq!(path("lutris/steam"), None);
+        assert_eq!(path("~user/x"), None);
+        assert_eq!(path(".hidden"), None);
+        assert_eq!(path("file:///etc/hosts"), None);
+        assert_eq!(path("firefox"), None);
+
+        assert_eq!(query_path("~/x", None, cwd), None);
+    }
+
+    #[test]
+    fn open_path_opens_with_xdg_open() {
+        let app = App::open_path(Path::new("/etc/hosts"));
+        assert_eq!(app.name, "Open /etc/hosts");
+        assert!(app.from_query);
+        let cmd = build_command(&app, "alacritty", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["xdg-open", "/etc/hosts"]);
+    }
+
+    fn url_apps(name: &str) -> (Fixture, Vec<App>) {
+        fixture_apps(
+            name,
+            &[
+                (
+                    "browser.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Browser\n\
+                     Exec=browser --new-window %u\n\
+                     MimeType=text/html;x-scheme-handler/http;x-scheme-handler/https;\n",
+                ),
+                (
+                    "mail.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Mail\n\
+                     Exec=mail -compose %U\nMimeType=x-scheme-handler/mailto;\n",
+                ),
+                ("plain.desktop", PLAIN_ENTRY),
+            ],
+        )
+    }
+
+    fn open_url_args(apps: &[App], scheme: &str, url: &str) -> Vec<(String, Vec<String>)> {
+        apps.iter()
+            .filter_map(|app| app.open_url(scheme, url))
+            .map(|app| {
+                let args = match app.launch {
+                    LaunchCommand::Direct(args) => args,
+                    LaunchCommand::Shell(_) => panic!("URL handlers launch directly"),
+                };
+                (app.name, args)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn url_is_substituted_into_handler_exec() {
+        let (_fixture, mut apps) = url_apps("yeet_test_url_handlers");
+        apps.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(direct_args(&apps, "Browser"), ["browser", "--new-window"]);
+        assert_eq!(
+            open_url_args(&apps, "https", "https://example.com/?q=a b"),
+            [(
+                "Browser".to_string(),
+                vec![
+                    "browser".to_string(),
+                    "--new-window".to_string(),
+                    "https://example.com/?q=a b".to_string()
+                ]
+            )]
+        );
+        assert_eq!(
+            open_url_args(&apps, "mailto", "mailto:me@example.com"),
+            [(
+                "Mail".to_string(),
+                vec![
+                    "mail".to_string(),
+                    "-compose".to_string(),
+                    "mailto:me@example.com".to_string()
+                ]
+            )]
+        );
+
+        let file_handlers: Vec<String> = open_url_args(&apps, "file", "file:///tmp/x")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(file_handlers, ["Browser", "Mail"]);
+    }
+
+    #[test]
+    fn implemented_interfaces_are_read_and_filtered_on() {
+        let (_fixture, apps) = fixture_apps(
+            "yeet_test_implements",
+            &[
+                (
+                    "org.gnome.Nautilus.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Files\nExec=nautilus\n\
+                     Implements=org.freedesktop.FileManager1; org.gnome.Shell.SearchProvider2;\n",
+                ),
+                (
+                    "thunar.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Thunar\nExec=thunar\n\
+                     Implements=org.freedesktop.FileManager1\n",
+                ),
+                (
+                    "kitty.desktop",
+                    "[Desktop Entry]\nType=Application\nName=kitty\nExec=kitty\n",
+                ),
+            ],
+        );
+        let files = apps.iter().find(|app| app.name == "Files").unwrap();
+        assert_eq!(
+            files.implements,
+            [
+                "org.freedesktop.FileManager1",
+                "org.gnome.Shell.SearchProvider2"
+            ]
+        );
+        assert!(apps
+            .iter()
+            .find(|app| app.name == "kitty")
+            .unwrap()
+            .implements
+            .is_empty());
+
+        let mut file_managers: Vec<&str> = implementing(&apps, "org.freedesktop.FileManager1")
+            .iter()
+            .map(|app| app.name.as_str())
+            .collect();
+        file_managers.sort();
+        assert_eq!(file_managers, ["Files", "Thunar"]);
+        let providers = implementing(&apps, "org.gnome.Shell.SearchProvider2");
+        assert_eq!(providers.len(), 1);
+        assert!(implementing(&apps, "org.freedesktop.FileManager").is_empty());
+    }
+
+    #[test]
+    fn dbus_activatable_entries_without_exec_are_kept() {
+        let (_fixture, apps) = fixture_apps(
+            "yeet_test_dbus_activatable",
+            &[
+                (
+                    "org.example.Maps-Beta.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Maps\nDBusActivatable=true\n",
+                ),
+                (
+                    "broken.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Broken\n",
+                ),
+            ],
+        );
+        assert_eq!(names(&apps), ["Maps"]);
+        assert_eq!(
+            apps[0].command_line(),
+            "gdbus call --session --dest org.example.Maps-Beta \
+             --object-path /org/example/Maps_Beta \
+             --method org.freedesktop.Application.Activate '{}'"
+        );
+    }
+
+    #[test]
+    fn command_line_is_cleaned_and_quoted() {
+        let (_fixture, apps) = fixture_apps(
+            "yeet_test_command_line",
+            &[(
+                "org.example.Viewer.desktop",
+                "[Desktop Entry]\nType=Application\nName=Viewer\n\
+                 Exec=\"/opt/My App/viewer\" --title \"it's\" --new %U\n",
+            )],
+        );
+        assert_eq!(
+            apps[0].command_line(),
+            r"'/opt/My App/viewer' --title 'it'\''s' --new"
+        );
+        assert_eq!(apps[0].desktop_id.as_deref(), Some("org.example.Viewer"));
+    }
+
+    #[test]
+    fn custom_command_line_is_printed_verbatim() {
+        let app = App::from_custom(&CustomApp {
+            terminal: Some("kitty".to_string()),
+            ..custom("Logs", "journalctl -f | grep -i error")
+        });
+        assert_eq!(app.command_line(), "journalctl -f | grep -i error");
+        assert_eq!(app.desktop_id, None);
+    }
+
+    #[test]
+    fn direct_command_rejects_empty_command() {
+        let err = direct_command(&[], None).expect_err("empty command must fail");
         assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     }
 
+    fn custom(name: &str, exec: &str) -> CustomApp {
+        CustomApp {
+            name: name.to_string(),
+            exec: exec.to_string(),
+            icon: None,
+            keywords: Vec::new(),
+            terminal: None,
+            launch_and_type: None,
+            confirm: false,
+        }
+    }
+
+    fn discovered(name: &str, id: &str) -> App {
+        App {
+            desktop_id: Some(id.to_string()),
+            ..App::plain(name.to_string())
+        }
+    }
+
+    #[test]
+    fn custom_app_is_listed_beside_same_named_app() {
+        let mut apps = vec![
+            discovered("Firefox", "firefox"),
+            discovered("Files", "nautilus"),
+        ];
+        add_custom_apps(&mut apps, &[custom("Firefox", "firefox -P work")], false);
+
+        let names: Vec<&str> = apps.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["Firefox", "Files", "Firefox"]);
+    }
+
+    #[test]
+    fn custom_override_replaces_same_named_app() {
+        let mut apps = vec![
+            discovered("Firefox", "firefox"),
+            discovered("Files", "nautilus"),
+        ];
+        add_custom_apps(
+            &mut apps,
+            &[
+                custom("Firefox", "firefox -P work"),
+                custom("Notes", "notes"),
+            ],
+            true,
+        );
+
+        let names: Vec<&str> = apps.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["Files", "Firefox", "Notes"]);
+        assert_eq!(apps[1].command_line(), "firefox -P work");
+        assert_eq!(apps[1].desktop_id, None);
+    }
+
+    #[test]
+    fn gathers_keywords_from_every_locale() {
+        let fixture = Fixture::new(
+            "yeet_test_locale_keywords",
+            &[(
+                "browser.desktop",
+                "[Desktop Entry]\nType=Application\nName=Browser\nExec=browser\n\
+                 Keywords=web;internet;\nKeywords[de]=Netz;Internet;\n\
+                 Keywords[en]=web;surf;\n",
+            )],
+        );
+
+        let locales = ["de".to_string(), "en".to_string()];
+        let apps = fixture.apps(&locales, FileApps::Strip);
+        assert_eq!(apps[0].keywords, ["Netz", "Internet", "web", "surf"]);
+
+        let text = SearchText::new(&apps[0].name, &apps[0].keywords);
+        assert!(text.contains("netz"));
+        assert!(text.contains("surf"));
+
+        let apps = fixture.apps(&[], FileApps::Strip);
+        assert_eq!(apps[0].keywords, ["web", "internet"]);
+    }
+
+    #[test]
+    fn reads_startup_wm_class() {
+        let (_fixture, mut apps) = fixture_apps(
+            "yeet_test_wm_class",
+            &[
+                (
+                    "code.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Visual Studio Code\n\
+                     Exec=code\nStartupWMClass=Code\n",
+                ),
+                ("plain.desktop", PLAIN_ENTRY),
+            ],
+        );
+        apps.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(apps[0].name, "Plain");
+        assert_eq!(apps[0].startup_wm_class, None);
+        assert_eq!(apps[1].startup_wm_class.as_deref(), Some("Code"));
+    }
+
+    #[test]
+    fn window_app_id_maps_to_app() {
+        let app = |name: &str, id: &str, class: Option<&str>| App {
+            desktop_id: Some(id.to_string()),
+            startup_wm_class: class.map(str::to_string),
+            ..App::plain(name.to_string())
+        };
+        let apps = [
+            app("Files", "org.gnome.Nautilus", None),
+            app("Code", "code", Some("Code")),
+            app("Code Helper", "code-url-handler", Some("code-url-handler")),
+            App::plain("Script".to_string()),
+        ];
+        let name = |app_id| app_for_window(&apps, app_id).map(|app| app.name.as_str());
+
+        assert_eq!(name("code"), Some("Code"));
+        assert_eq!(name("org.gnome.Nautilus"), Some("Files"));
+        assert_eq!(name("nautilus"), Some("Files"));
+        assert_eq!(name("firefox"), None);
+    }
+
+    #[test]
+    fn category_names_become_words() {
+        assert_eq!(
+            category_words("Development").as_deref(),
+            Some("development")
+        );
+        assert_eq!(category_words("AudioVideo").as_deref(), Some("audio video"));
+        assert_eq!(category_words("TextEditor").as_deref(), Some("text editor"));
+        assert_eq!(category_words("3DGraphics").as_deref(), Some("3d graphics"));
+        assert_eq!(category_words("IDE").as_deref(), Some("ide"));
+        assert_eq!(category_words("X-GNOME-Utilities"), None);
+    }
+
+    #[test]
+    fn categories_are_searchable_when_included() {
+        let (_fixture, mut apps) = fixture_apps(
+            "yeet_test_categories",
+            &[
+                (
+                    "code.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Code\nExec=code\n\
+                     Categories=Development;IDE;X-Vendor;\n",
+                ),
+                (
+                    "gimp.desktop",
+                    "[Desktop Entry]\nType=Application\nName=GIMP\nExec=true\n",
+                ),
+            ],
+        );
+        apps.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(apps[0].categories, ["Development", "IDE", "X-Vendor"]);
+
+        let matches = |apps: &[App]| -> Vec<String> {
+            apps.iter()
+                .filter(|app| SearchText::new(&app.name, &app.keywords).contains("development"))
+                .map(|app| app.name.clone())
+                .collect()
+        };
+        assert!(matches(&apps).is_empty());
+
+        add_category_keywords(&mut apps);
+        assert_eq!(apps[0].keywords, ["development", "ide"]);
+        assert_eq!(matches(&apps), ["Code"]);
+    }
+
+    #[test]
+    fn descriptions_are_searchable_when_matched() {
+        let (_fixture, mut apps) = fixture_apps(
+            "yeet_test_match_description",
+            &[(
+                "foot.desktop",
+                "[Desktop Entry]\nType=Application\nName=Foot\nExec=foot\n\
+                 Comment=Wayland terminal emulator\n",
+            )],
+        );
+        let matcher = crate::search::Matcher::new(crate::config::MatcherKind::Skim);
+        let matches = |app: &App, query: &str| {
+            SearchText::new(&app.name, &app.keywords)
+                .score(&matcher, query, 1.0)
+                .is_some()
+        };
+        assert!(!matches(&apps[0], "emulator"));
+
+        add_description_keywords(&mut apps);
+        assert!(matches(&apps[0], "emulator"));
+        assert!(matches(&apps[0], "foot"));
+        // Still shown as the description, as before.
+        assert_eq!(
+            apps[0].description.as_deref(),
+            Some("Wayland terminal emulator")
+        );
+    }
+
+    #[test]
+    fn apps_group_under_their_first_main_category() {
+        let group = |categories: &[&str]| {
+            let categories: Vec<String> = categories.iter().map(|c| c.to_string()).collect();
+            category_group(&categories).1
+        };
+        assert_eq!(group(&["GTK", "Development", "Network"]), "Development");
+        assert_eq!(group(&["Network", "WebBrowser"]), "Internet");
+        assert_eq!(group(&["X-Vendor", "Player", "AudioVideo"]), "Multimedia");
+        assert_eq!(group(&["Settings", "DesktopSettings"]), "System");
+        assert_eq!(group(&["X-Vendor"]), "Other");
+        assert_eq!(group(&[]), "Other");
+    }
+
+    #[test]
+    fn grouping_by_category_keeps_the_order_within_groups() {
+        let app = |name: &str, categories: &[&str]| App {
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            ..App::plain(name.to_string())
+        };
+        let apps = [
+            app("Calculator", &["Utility"]),
+            app("Code", &["Development", "IDE"]),
+            app("Firefox", &["Network", "WebBrowser"]),
+            app("Notes", &[]),
+            app("Zed", &["Development"]),
+        ];
+
+        let mut indices: Vec<usize> = (0..apps.len()).collect();
+        let headers = group_by_category(&apps, &mut indices);
+        assert_eq!(indices, [2, 1, 4, 0, 3]);
+        assert_eq!(
+            headers,
+            [
+                (0, "Internet"),
+                (1, "Development"),
+                (3, "Utilities"),
+                (4, "Other")
+            ]
+        );
+
+        let mut indices = vec![4, 1];
+        assert!(group_by_category(&apps, &mut indices).is_empty());
+        assert_eq!(indices, [4, 1]);
+    }
+
+    fn browser_with_desktop_action(name: &str) -> (Fixture, Vec<App>) {
+        fixture_apps(
+            name,
+            &[(
+                "browser.desktop",
+                "[Desktop Entry]\nType=Application\nName=Browser\nExec=browser %u\n\
+                 Actions=new-window;private;\n\n\
+                 [Desktop Action new-window]\nName=New Window\nExec=browser --new-window %u\n\n\
+                 [Desktop Action private]\nName=Private Window\nExec=browser --private %u\n",
+            )],
+        )
+    }
+
+    fn action_labels(app: &App) -> Vec<&str> {
+        app.actions.iter().map(|a| a.label.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_desktop_actions() {
+        let (_fixture, apps) = browser_with_desktop_action("yeet_test_actions");
+        assert_eq!(action_labels(&apps[0]), ["New Window", "Private Window"]);
+
+        let actions = apps[0].action_apps();
+        assert_eq!(actions[0].name, "Browser: New Window");
+        assert_eq!(actions[0].command_line(), "browser --new-window");
+        assert!(actions[0].actions.is_empty());
+    }
+
+    #[test]
+    fn action_entries_match_queries_spanning_app_and_action() {
+        let (_fixture, apps) = browser_with_desktop_action("yeet_test_action_queries");
+        let entries: Vec<App> = std::iter::once(apps[0].clone())
+            .chain(apps[0].action_apps())
+            .collect();
+        let texts: Vec<SearchText> = entries
+            .iter()
+            .map(|app| SearchText::new(&app.name, &app.keywords))
+            .collect();
+        let matcher = crate::search::Matcher::new(crate::config::MatcherKind::Skim);
+        let ranked = crate::search::rank(
+            &texts,
+            &matcher,
+            "browser private",
+            &SearchConfig::default(),
+            &HashMap::new(),
+            0,
+        );
+
+        let names: Vec<&str> = ranked
+            .iter()
+            .map(|&(i, _)| entries[i].name.as_str())
+            .collect();
+        assert_eq!(names, ["Browser: Private Window"]);
+    }
+
+    #[test]
+    fn custom_actions_merge_with_desktop_actions() {
+        let (_fixture, mut apps) = browser_with_desktop_action("yeet_test_custom_actions");
+        let custom = |label: &str, exec: &str| CustomAction {
+            name: "Browser".into(),
+            label: label.into(),
+            exec: exec.into(),
+        };
+        add_custom_actions(
+            &mut apps,
+            &[
+                custom("Open in incognito", "browser --incognito"),
+                custom("Private Window", "browser --private --no-remote"),
+                CustomAction {
+                    name: "Other".into(),
+                    ..custom("Elsewhere", "true")
+                },
+            ],
+        );
+
+        assert_eq!(
+            action_labels(&apps[0]),
+            ["New Window", "Private Window", "Open in incognito"]
+        );
+        assert_eq!(
+            apps[0].action_apps()[1].command_line(),
+            "browser --private --no-remote"
+        );
+    }
+
+    #[test]
+    fn custom_action_launches_argv_in_terminal() {
+        let mut apps = [App {
+            terminal: true,
+            ..App::plain("Htop".into())
+        }];
+        add_custom_actions(
+            &mut apps,
+            &[CustomAction {
+                name: "Htop".into(),
+                label: "Run as root".into(),
+                exec: "sudo htop".into(),
+            }],
+        );
+
+        let action = &apps[0].action_apps()[0];
+        assert_eq!(action.name, "Htop: Run as root");
+        let cmd = build_command(action, "foot", "sh").unwrap();
+        assert_eq!(command_line(&cmd), ["foot", "-e", "sudo", "htop"]);
+    }
+
+    #[test]
+    fn launch_id_selects_app() {
+        let apps = [
+            App {
+                desktop_id: Some("org.mozilla.firefox".into()),
+                ..App::plain("Firefox".into())
+            },
+            App {
+                desktop_id: Some("kitty".into()),
+                ..App::plain("kitty".into())
+            },
+            App::plain("Script".into()),
+        ];
+        let name = |id| app_by_id(&apps, id).map(|app| app.name.as_str());
+
+        assert_eq!(name("org.mozilla.firefox"), Some("Firefox"));
+        assert_eq!(name("kitty.desktop"), Some("kitty"));
+        assert_eq!(name("firefox"), None);
+        assert_eq!(name("Script"), None);
+    }
+
+    const PLAIN_ENTRY: &str = "[Desktop Entry]\nType=Application\nName=Plain\nExec=true\n";
+
+    /// A temp dir of desktop entries, removed again when dropped so a
+    /// failing assertion doesn't leave it behind.
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(name);
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            for (file, contents) in files {
+                fs::write(dir.join(file), contents).unwrap();
+            }
+            Self { dir }
+        }
+
+        fn apps(&self, locales: &[String], file_apps: FileApps) -> Vec<App> {
+            apps_from_files(
+                desktop_files(vec![self.dir.clone()], true),
+                &HashSet::new(),
+                locales,
+                file_apps,
+            )
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// The apps read from a fixture dir of `files`, with the default locale
+    /// and file-app policy; keep the fixture alive while using them.
+    fn fixture_apps(name: &str, files: &[(&str, &str)]) -> (Fixture, Vec<App>) {
+        let fixture = Fixture::new(name, files);
+        let apps = fixture.apps(&[], FileApps::Strip);
+        (fixture, apps)
+    }
+
     fn write_desktop_file(dir: &std::path::Path, file: &str, name: &str) {
         fs::write(
             dir.join(file),
@@ -229,6 +2640,313 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn apps_remember_where_they_came_from() {
+        let (fixture, mut apps) = fixture_apps(
+            "yeet_test_app_source",
+            &[(
+                "found.desktop",
+                "[Desktop Entry]\nType=Application\nName=Found\nExec=true\n",
+            )],
+        );
+        add_custom_apps(&mut apps, &[custom("Mine", "mine")], false);
+
+        assert_eq!(
+            apps[0].source,
+            AppSource::File(fixture.dir.join("found.desktop"))
+        );
+        assert_eq!(apps[1].name, "Mine");
+        assert_eq!(apps[1].source, AppSource::Custom);
+        assert_eq!(apps[1].source.to_string(), "custom");
+        assert_eq!(App::plain("item".into()).source, AppSource::Generated);
+    }
+
+    #[test]
+    fn missing_extra_dir_is_reported_and_skipped() {
+        let root = std::env::temp_dir().join("yeet_test_extra_dirs");
+        let _ = fs::remove_dir_all(&root);
+        let apps_dir = root.join("apps");
+        fs::create_dir_all(&apps_dir).unwrap();
+        write_desktop_file(&apps_dir, "found.desktop", "Found");
+        let missing = root.join("typo");
+        let not_a_dir = apps_dir.join("found.desktop");
+
+        assert_eq!(dir_problem(&apps_dir), None);
+        assert_eq!(dir_problem(&missing).as_deref(), Some("it doesn't exist"));
+        assert_eq!(
+            dir_problem(&not_a_dir).as_deref(),
+            Some("it isn't a directory")
+        );
+
+        let files = desktop_files(vec![missing, apps_dir.clone()], true);
+        assert_eq!(files, [apps_dir.join("found.desktop")]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    fn file_app_fixture(name: &str) -> Fixture {
+        Fixture::new(
+            name,
+            &[
+                (
+                    "viewer.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Viewer\nExec=viewer --new %U\n",
+                ),
+                ("plain.desktop", PLAIN_ENTRY),
+            ],
+        )
+    }
+
+    fn direct_args<'a>(apps: &'a [App], name: &str) -> &'a [String] {
+        let app = apps.iter().find(|a| a.name == name).unwrap();
+        match &app.launch {
+            LaunchCommand::Direct(args) => args,
+            LaunchCommand::Shell(_) => panic!("desktop entries must launch directly"),
+        }
+    }
+
+    #[test]
+    fn exec_takes_files_detects_file_and_url_codes() {
+        assert!(exec::takes_files("eog %U"));
+        assert!(exec::takes_files("gimp %f --new"));
+        assert!(!exec::takes_files("alacritty"));
+        assert!(!exec::takes_files("app --icon %i"));
+    }
+
+    #[test]
+    fn strip_policy_launches_file_apps_without_argument() {
+        let apps = file_app_fixture("yeet_test_file_apps_strip").apps(&[], FileApps::Strip);
+
+        assert_eq!(apps.len(), 2);
+        assert_eq!(direct_args(&apps, "Viewer"), ["viewer", "--new"]);
+    }
+
+    #[test]
+    fn drop_policy_hides_file_apps() {
+        let apps = file_app_fixture("yeet_test_file_apps_drop").apps(&[], FileApps::Drop);
+
+        let names: Vec<&str> = apps.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["Plain"]);
+    }
+
+    #[test]
+    fn home_policy_passes_home_dir() {
+        let apps = file_app_fixture("yeet_test_file_apps_home").apps(&[], FileApps::Home);
+        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+
+        assert_eq!(direct_args(&apps, "Viewer"), ["viewer", "--new", &home]);
+        assert_eq!(direct_args(&apps, "Plain"), ["true"]);
+    }
+
+    #[test]
+    fn quoted_exec_arguments_survive_parsing() {
+        // The file's own `\\` escape unwraps to `\` before Exec quoting.
+        let (_fixture, apps) = fixture_apps(
+            "yeet_test_exec_quoting",
+            &[(
+                "spaced.desktop",
+                "[Desktop Entry]\nType=Application\nName=Spaced\n\
+                 Exec=\"/opt/My App/bin/app\" --title \"Say \\\\\"hi\\\\\" for \\\\$5\"\n",
+            )],
+        );
+        assert_eq!(
+            direct_args(&apps, "Spaced"),
+            ["/opt/My App/bin/app", "--title", "Say \"hi\" for $5"]
+        );
+    }
+
+    fn named_app(name: &str, generic_name: Option<&str>, exec: &[&str]) -> App {
+        App {
+            generic_name: generic_name.map(str::to_string),
+            launch: LaunchCommand::Direct(exec.iter().map(|s| s.to_string()).collect()),
+            ..App::plain(name.into())
+        }
+    }
+
+    fn names(apps: &[App]) -> Vec<&str> {
+        apps.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    fn labels(apps: &[App]) -> Vec<&str> {
+        apps.iter().map(App::label).collect()
+    }
+
+    #[test]
+    fn keyword_map_seeds_discovered_apps() {
+        let mut apps = vec![
+            App {
+                keywords: vec!["Internet".into()],
+                ..named_app("Firefox", None, &["/usr/lib/firefox/firefox", "%u"])
+            },
+            named_app("Visual Studio Code", None, &["code"]),
+            named_app("Files", None, &["nautilus"]),
+        ];
+        let keyword_map = vec![
+            KeywordMap {
+                name: "Firefox".into(),
+                keywords: vec!["browser".into(), "Internet".into()],
+            },
+            KeywordMap {
+                name: "code".into(),
+                keywords: vec!["editor".into()],
+            },
+        ];
+        seed_keywords(&mut apps, &keyword_map);
+
+        assert_eq!(apps[0].keywords, ["Internet", "browser"]);
+        assert_eq!(apps[1].keywords, ["editor"]);
+        assert!(apps[2].keywords.is_empty());
+
+        let text = crate::search::SearchText::new(&apps[0].name, &apps[0].keywords);
+        assert!(text.contains("browser"));
+        let text = crate::search::SearchText::new(&apps[1].name, &apps[1].keywords);
+        assert!(text.contains("editor"));
+    }
+
+    fn icon_app(name: &str, icon: Option<&str>) -> App {
+        App {
+            icon: icon.map(str::to_string),
+            ..App::plain(name.into())
+        }
+    }
+
+    #[test]
+    fn require_icon_drops_apps_without_resolvable_icon() {
+        let dir = std::env::temp_dir().join("yeet_test_require_icon");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let icon_file = dir.join("tool.png");
+        fs::write(&icon_file, "").unwrap();
+        let icon_path = icon_file.to_string_lossy().to_string();
+        let missing_path = dir.join("missing.png").to_string_lossy().to_string();
+
+        let mut apps = vec![
+            icon_app("Firefox", Some("firefox")),
+            icon_app("Themed", Some("papirus-only")),
+            icon_app("Broken", Some("no-such-icon")),
+            icon_app("Bare", None),
+            icon_app("Tool", Some(&icon_path)),
+            icon_app("Gone", Some(&missing_path)),
+        ];
+        retain_with_icons(&mut apps, |icon| matches!(icon, "firefox" | "papirus-only"));
+
+        assert_eq!(names(&apps), ["Firefox", "Themed", "Tool"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn favorites_note_their_position() {
+        let mut apps = vec![
+            named_app("Firefox", None, &["firefox"]),
+            named_app("Files", None, &["nautilus"]),
+            named_app("Zed", None, &["zeditor"]),
+        ];
+        let favorites = ["Zed", "Gimp", "Firefox", "Zed"].map(String::from);
+        mark_favorites(&mut apps, &favorites);
+
+        let positions: Vec<(bool, Option<usize>)> = apps
+            .iter()
+            .map(|app| (app.favorite, app.favorite_position))
+            .collect();
+        assert_eq!(positions, [(true, Some(2)), (false, None), (true, Some(0))]);
+    }
+
+    #[test]
+    fn colliding_names_get_exec_basename() {
+        let mut apps = vec![
+            named_app("Terminal", None, &["/usr/bin/kitty", "--single-instance"]),
+            named_app("Firefox", None, &["firefox"]),
+            named_app("Terminal", Some("Terminal Emulator"), &["foot"]),
+        ];
+        disambiguate_names(&mut apps);
+
+        assert_eq!(
+            labels(&apps),
+            ["Terminal (kitty)", "Firefox", "Terminal (foot)"]
+        );
+        assert_eq!(names(&apps), ["Terminal", "Firefox", "Terminal"]);
+    }
+
+    #[test]
+    fn colliding_names_prefer_distinct_generic_names() {
+        let mut apps = vec![
+            named_app("Files", Some("File Manager"), &["nautilus"]),
+            named_app("Files", Some("File Search"), &["catfish"]),
+            named_app("Settings", Some("Settings"), &["gnome-control-center"]),
+        ];
+        disambiguate_names(&mut apps);
+
+        assert_eq!(
+            labels(&apps),
+            ["Files (File Manager)", "Files (File Search)", "Settings"]
+        );
+    }
+
+    #[test]
+    fn custom_apps_collide_with_desktop_entries() {
+        let mut apps = vec![
+            named_app("Htop", None, &["htop"]),
+            App::from_custom(&custom("Htop", "btop --utf-force")),
+        ];
+        disambiguate_names(&mut apps);
+
+        assert_eq!(labels(&apps), ["Htop (htop)", "Htop (btop)"]);
+    }
+
+    #[test]
+    fn flatpak_runtime_ids_are_recognized() {
+        for id in [
+            "org.freedesktop.Platform",
+            "org.freedesktop.Sdk.Debug",
+            "org.gnome.Sdk.Locale",
+            "io.qt.qtwebengine.BaseApp",
+            "org.electronjs.Electron2.BaseApp.desktop",
+            "org.kde.Platform.Sources",
+        ] {
+            assert!(is_flatpak_runtime(id), "{id}");
+        }
+        for id in [
+            "org.mozilla.firefox",
+            "com.valvesoftware.Steam",
+            "org.sdk.Editor",
+            "org.example.Debugger",
+            "firefox",
+        ] {
+            assert!(!is_flatpak_runtime(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn nix_profile_dirs_are_included_when_present() {
+        let root = std::env::temp_dir().join("yeet_test_nix_dirs");
+        let _ = fs::remove_dir_all(&root);
+        let home = root.join("home/me");
+        let user_profile = home.join(".nix-profile/share/applications");
+        let system = root.join("run/current-system/sw/share/applications");
+        fs::create_dir_all(&user_profile).unwrap();
+        fs::create_dir_all(&system).unwrap();
+
+        assert_eq!(
+            nix_application_dirs(&root, Some(&home), Some("me")),
+            [user_profile.clone(), system]
+        );
+
+        let per_user = root.join("etc/profiles/per-user/me/share/applications");
+        fs::create_dir_all(&per_user).unwrap();
+        let dirs = nix_application_dirs(&root, Some(&home), Some("me"));
+        assert_eq!(dirs.len(), 3);
+        assert!(dirs.contains(&per_user));
+
+        fs::remove_dir_all(root.join("run")).unwrap();
+        assert_eq!(
+            nix_application_dirs(&root, Some(&home), None),
+            [user_profile]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
     #[test]
     fn duplicate_desktop_ids_prefer_earlier_dirs() {
         let base = std::env::temp_dir().join("yeet_test_dedup");
@@ -242,7 +2960,12 @@ mod tests {
         write_desktop_file(&system, "firefox.desktop", "Firefox System");
         write_desktop_file(&system, "kitty.desktop", "Kitty");
 
-        let apps = apps_from_dirs(vec![local, system], &HashSet::new(), &[]);
+        let apps = apps_from_files(
+            desktop_files(vec![local, system], true),
+            &HashSet::new(),
+            &[],
+            FileApps::Strip,
+        );
 
         let names: Vec<&str> = apps.iter().map(|a| a.name.as_str()).collect();
         assert!(names.contains(&"Firefox Local"));
@@ -253,6 +2976,53 @@ mod tests {
         let _ = fs::remove_dir_all(&base);
     }
 
+    #[test]
+    fn dedup_preference_picks_local_or_system_copy() {
+        let base = std::env::temp_dir().join("yeet_test_dedup_prefer");
+        let _ = fs::remove_dir_all(&base);
+        let data_home = base.join("home");
+        let local = data_home.join("applications");
+        let system = base.join("usr/share/applications");
+        fs::create_dir_all(&local).unwrap();
+        fs::create_dir_all(&system).unwrap();
+        write_desktop_file(&local, "firefox.desktop", "Firefox Local");
+        write_desktop_file(&system, "firefox.desktop", "Firefox System");
+
+        let xdg = vec![local.clone(), system.clone()];
+        let name = |prefer| {
+            let dirs = prefer_dirs(xdg.clone(), &data_home, prefer);
+            let apps = apps_from_files(
+                desktop_files(dirs, true),
+                &HashSet::new(),
+                &[],
+                FileApps::Strip,
+            );
+            assert_eq!(apps.len(), 1);
+            apps[0].name.clone()
+        };
+        assert_eq!(name(DedupPrefer::Local), "Firefox Local");
+        assert_eq!(name(DedupPrefer::System), "Firefox System");
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn system_preference_keeps_order_within_groups() {
+        let home = Path::new("/home/me/.local/share");
+        let dirs = vec![
+            home.join("applications"),
+            PathBuf::from("/usr/share/applications"),
+            PathBuf::from("/usr/local/share/applications"),
+            home.join("flatpak/exports/share/applications"),
+            PathBuf::from("/var/lib/flatpak/exports/share/applications"),
+        ];
+        assert_eq!(prefer_dirs(dirs.clone(), home, DedupPrefer::Local), dirs);
+        assert_eq!(
+            prefer_dirs(dirs.clone(), home, DedupPrefer::System),
+            [1, 2, 4, 0, 3].map(|i| dirs[i].clone())
+        );
+    }
+
     #[test]
     fn hidden_local_entry_shadows_system_entry() {
         let base = std::env::temp_dir().join("yeet_test_shadow");
@@ -269,9 +3039,37 @@ mod tests {
         .unwrap();
         write_desktop_file(&system, "htop.desktop", "Htop");
 
-        let apps = apps_from_dirs(vec![local, system], &HashSet::new(), &[]);
+        let apps = apps_from_files(
+            desktop_files(vec![local, system], true),
+            &HashSet::new(),
+            &[],
+            FileApps::Strip,
+        );
         assert!(apps.is_empty());
 
         let _ = fs::remove_dir_all(&base);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle_does_not_stall_discovery() {
+        let base = std::env::temp_dir().join("yeet_test_symlink_cycle");
+        let _ = fs::remove_dir_all(&base);
+        let nested = base.join("apps").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        write_desktop_file(&nested, "kitty.desktop", "Kitty");
+        // nested/loop -> apps, so walking it would go around forever
+        std::os::unix::fs::symlink(base.join("apps"), nested.join("loop")).unwrap();
+
+        let files = desktop_files(vec![base.join("apps")], true);
+        assert_eq!(files, [nested.join("kitty.desktop")]);
+
+        let linked = base.join("linked");
+        fs::create_dir_all(&linked).unwrap();
+        std::os::unix::fs::symlink(&nested, linked.join("nested")).unwrap();
+        assert_eq!(desktop_files(vec![linked.clone()], true).len(), 1);
+        assert!(desktop_files(vec![linked], false).is_empty());
+
+        let _ = fs::remove_dir_all(&base);
+    }
 }
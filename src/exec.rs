@@ -0,0 +1,319 @@
+//! `Exec` key parsing per the Desktop Entry spec.
+//!
+//! The desktop-entry crate has already undone the string-level escapes
+//! (`\s`, `\\`, ...); what's left is the Exec-specific quoting layer:
+//! arguments may be wrapped in double quotes, inside which `"`, `` ` ``, `$`
+//! and `\` are escaped with a backslash. Field codes are only recognised as
+//! whole unquoted arguments.
+//!
+//! <https://specifications.freedesktop.org/desktop-entry-spec/latest/exec-variables.html>
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::Path;
+
+/// Values substituted for field codes.
+#[derive(Debug, Default)]
+pub struct FieldValues<'a> {
+    pub uris: &'a [String],
+    pub icon: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub path: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExecError {
+    UnterminatedQuote,
+    TrailingBackslash,
+    Empty,
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::UnterminatedQuote => write!(f, "unterminated quoted argument"),
+            ExecError::TrailingBackslash => write!(f, "trailing backslash in quoted argument"),
+            ExecError::Empty => write!(f, "empty command"),
+        }
+    }
+}
+
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+/// Splits an Exec value into argv, expanding field codes from `values`.
+pub fn parse(exec: &str, values: &FieldValues) -> Result<Vec<String>, ExecError> {
+    let mut args = Vec::new();
+
+    for token in tokenize(exec)? {
+        if !token.quoted {
+            if let Some(code) = field_code(&token.text) {
+                expand(code, values, &mut args);
+                continue;
+            }
+        }
+        args.push(strip_percents(&token.text));
+    }
+
+    if args.first().map_or(true, String::is_empty) {
+        return Err(ExecError::Empty);
+    }
+    Ok(args)
+}
+
+/// Whether the Exec value accepts files or URLs (`%f`, `%F`, `%u`, `%U`).
+pub fn takes_files(exec: &str) -> bool {
+    has_field_code(exec, &['f', 'F', 'u', 'U'])
+}
+
+/// Whether the Exec value accepts URLs (`%u`, `%U`), not just local paths.
+pub fn takes_urls(exec: &str) -> bool {
+    has_field_code(exec, &['u', 'U'])
+}
+
+/// The program an argv runs. A leading `env VAR=value ...` stays in argv, so
+/// the variables reach the app however it's launched (in a terminal, behind
+/// `launch_prefix`); this looks past it, and past `env`'s own flags, to the
+/// program `env` starts.
+pub fn program<'a>(mut argv: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let first = argv.next()?;
+    if Path::new(first).file_name() != Some(OsStr::new("env")) {
+        return Some(first);
+    }
+    argv.find(|arg| !arg.starts_with('-') && !is_env_assignment(arg))
+}
+
+fn is_env_assignment(arg: &str) -> bool {
+    arg.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+fn has_field_code(exec: &str, codes: &[char]) -> bool {
+    tokenize(exec).is_ok_and(|tokens| {
+        tokens
+            .iter()
+            .filter(|t| !t.quoted)
+            .filter_map(|t| field_code(&t.text))
+            .any(|code| codes.contains(&code))
+    })
+}
+
+fn tokenize(exec: &str) -> Result<Vec<Token>, ExecError> {
+    let mut tokens = Vec::new();
+    let mut chars = exec.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+
+        let mut text = String::new();
+        let quoted = first == '"';
+        if quoted {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c @ ('"' | '`' | '$' | '\\')) => text.push(c),
+                        // Not one of the spec's escapes; keep it literally.
+                        Some(c) => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                        None => return Err(ExecError::TrailingBackslash),
+                    },
+                    Some(c) => text.push(c),
+                    None => return Err(ExecError::UnterminatedQuote),
+                }
+            }
+        }
+        while let Some(c) = chars.next_if(|c| *c != ' ' && *c != '\t') {
+            text.push(c);
+        }
+
+        tokens.push(Token { text, quoted });
+    }
+
+    Ok(tokens)
+}
+
+fn field_code(arg: &str) -> Option<char> {
+    let mut chars = arg.strip_prefix('%')?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != '%' => Some(c),
+        _ => None,
+    }
+}
+
+fn expand(code: char, values: &FieldValues, args: &mut Vec<String>) {
+    match code {
+        'f' | 'u' => args.extend(values.uris.first().cloned()),
+        'F' | 'U' => args.extend(values.uris.iter().cloned()),
+        'i' => {
+            if let Some(icon) = values.icon {
+                args.push("--icon".into());
+                args.push(icon.into());
+            }
+        }
+        'c' => args.extend(values.name.map(String::from)),
+        'k' => args.extend(values.path.map(String::from)),
+        // Deprecated (%d, %D, %n, %N, %v, %m) and unknown codes expand to
+        // nothing.
+        _ => {}
+    }
+}
+
+/// Turns `%%` into `%` and drops any other field code embedded in an
+/// argument, which the spec doesn't allow.
+fn strip_percents(arg: &str) -> String {
+    let mut out = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+        } else if let Some('%') = chars.next() {
+            out.push('%');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(exec: &str) -> Vec<String> {
+        parse(exec, &FieldValues::default()).unwrap()
+    }
+
+    #[test]
+    fn splits_plain_arguments() {
+        assert_eq!(argv("firefox --new-window"), ["firefox", "--new-window"]);
+        assert_eq!(argv("  htop\t-d  10 "), ["htop", "-d", "10"]);
+    }
+
+    #[test]
+    fn quoted_arguments_keep_spaces() {
+        assert_eq!(
+            argv(r#""/opt/My App/bin/app" --profile "Profile 1""#),
+            ["/opt/My App/bin/app", "--profile", "Profile 1"]
+        );
+    }
+
+    #[test]
+    fn unescapes_reserved_characters_in_quotes() {
+        assert_eq!(
+            argv(r#"sh -c "echo \"\$HOME\" \`date\` \\""#),
+            ["sh", "-c", r#"echo "$HOME" `date` \"#]
+        );
+    }
+
+    #[test]
+    fn keeps_other_backslashes_literally() {
+        assert_eq!(argv(r#"app "C:\temp""#), ["app", r"C:\temp"]);
+    }
+
+    #[test]
+    fn quotes_may_touch_unquoted_text() {
+        assert_eq!(argv(r#"app "a b"c"#), ["app", "a bc"]);
+    }
+
+    #[test]
+    fn quoted_empty_argument_is_kept() {
+        assert_eq!(argv(r#"app "" x"#), ["app", "", "x"]);
+    }
+
+    #[test]
+    fn rejects_malformed_quoting() {
+        let values = FieldValues::default();
+        assert_eq!(
+            parse(r#"app "unterminated"#, &values),
+            Err(ExecError::UnterminatedQuote)
+        );
+        assert_eq!(
+            parse("app \"trailing\\", &values),
+            Err(ExecError::TrailingBackslash)
+        );
+        assert_eq!(parse("   ", &values), Err(ExecError::Empty));
+        assert_eq!(parse("%U", &values), Err(ExecError::Empty));
+    }
+
+    #[test]
+    fn expands_field_codes() {
+        let uris = vec!["/home/me".to_string(), "/tmp".to_string()];
+        let values = FieldValues {
+            uris: &uris,
+            icon: Some("viewer"),
+            name: Some("Viewer"),
+            path: Some("/usr/share/applications/viewer.desktop"),
+        };
+
+        assert_eq!(
+            parse("viewer %f %i %c %k", &values).unwrap(),
+            [
+                "viewer",
+                "/home/me",
+                "--icon",
+                "viewer",
+                "Viewer",
+                "/usr/share/applications/viewer.desktop"
+            ]
+        );
+        assert_eq!(
+            parse("viewer %U", &values).unwrap(),
+            ["viewer", "/home/me", "/tmp"]
+        );
+        assert_eq!(
+            parse("viewer %d %m %U", &FieldValues::default()).unwrap(),
+            ["viewer"]
+        );
+    }
+
+    #[test]
+    fn percent_escapes_and_stray_codes() {
+        assert_eq!(argv(r#"date +%%Y "%%U" "%U""#), ["date", "+%Y", "%U", ""]);
+    }
+
+    #[test]
+    fn takes_files_ignores_quoted_codes() {
+        assert!(takes_files("viewer --new %U"));
+        assert!(takes_files("viewer %f"));
+        assert!(!takes_files("viewer %i"));
+        assert!(!takes_files(r#"viewer "%U""#));
+        assert!(!takes_files("viewer %%u"));
+    }
+
+    #[test]
+    fn takes_urls_only_for_url_codes() {
+        assert!(takes_urls("firefox %u"));
+        assert!(takes_urls("thunderbird -compose %U"));
+        assert!(!takes_urls("gimp %F"));
+        assert!(!takes_urls("alacritty"));
+    }
+
+    #[test]
+    fn env_prefix_stays_in_argv() {
+        assert_eq!(
+            argv("env A=1 B=2 prog --flag %U"),
+            ["env", "A=1", "B=2", "prog", "--flag"]
+        );
+    }
+
+    #[test]
+    fn program_looks_past_env_prefix() {
+        let program_of =
+            |exec: &str| program(argv(exec).iter().map(String::as_str)).map(str::to_string);
+        assert_eq!(program_of("env A=1 B=2 prog %U").as_deref(), Some("prog"));
+        assert_eq!(
+            program_of("/usr/bin/env -i PATH=/bin prog").as_deref(),
+            Some("prog")
+        );
+        assert_eq!(program_of("prog A=1").as_deref(), Some("prog"));
+        assert_eq!(program_of("env A=1").as_deref(), None);
+    }
+}
@@ -1,109 +1,506 @@
-use std::collections::HashMap;
+use crate::config::HistoryFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{BufRead, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-const MAX_HISTORY_LINES: usize = 200;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// What trimming keeps once the history file grows past `recent` lines
+/// (`search.history_keep_recent` and `search.history_keep_frequent`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrimPolicy {
+    /// Newest launches kept regardless of app.
+    pub recent: usize,
+    /// Most launched apps whose own newest `recent` launches are kept too,
+    /// however old.
+    pub frequent: usize,
+}
+
+/// `data_dir` (`general.data_dir`), or `~/.local/share/yeet` when that's
+/// unset.
+pub fn data_dir(data_dir: Option<&Path>) -> PathBuf {
+    data_dir.map(Path::to_path_buf).unwrap_or_else(|| {
+        dirs::data_local_dir()
+            .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("yeet")
+    })
+}
 
-pub fn history_path() -> PathBuf {
-    let base_dir = dirs::data_local_dir()
-        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")))
-        .unwrap_or_else(std::env::temp_dir);
-    base_dir.join("yeet").join("history.txt")
+/// `history.txt` in the data dir, or `history.json` in the JSON format.
+pub fn history_path(data_dir: Option<&Path>, format: HistoryFormat) -> PathBuf {
+    let file = match format {
+        HistoryFormat::Tsv => "history.txt",
+        HistoryFormat::Json => "history.json",
+    };
+    self::data_dir(data_dir).join(file)
 }
 
-pub fn record_launch(app_name: &str) {
+/// `history_path`, after moving a `history.txt` over to `history.json` the
+/// first time the JSON format is used. The text file is left in place.
+fn history_file(data_dir: Option<&Path>, format: HistoryFormat) -> PathBuf {
+    let path = history_path(data_dir, format);
+    if format == HistoryFormat::Json && !path.exists() {
+        let tsv = history_path(data_dir, HistoryFormat::Tsv);
+        if tsv.exists() {
+            if let Err(e) = convert_history(&tsv, HistoryFormat::Tsv, &path, format) {
+                eprintln!("Warning: could not move launch history to JSON: {e}");
+            }
+        }
+    }
+    path
+}
+
+fn convert_history(
+    from: &Path,
+    from_format: HistoryFormat,
+    to: &Path,
+    to_format: HistoryFormat,
+) -> std::io::Result<()> {
+    let stored = read_history(from, from_format)?;
+    write_history(to, &stored.entries(), &stored.names(), to_format)
+}
+
+/// Records a launch of the app named `app_name` under `key`, its desktop
+/// id where it has one, so the history follows the app through renames.
+/// `exclude` holds display names.
+pub fn record_launch(
+    app_name: &str,
+    key: &str,
+    exclude: &[String],
+    data_dir: Option<&Path>,
+    format: HistoryFormat,
+    trim: TrimPolicy,
+) {
+    if !should_record(app_name, exclude) {
+        return;
+    }
+
     let timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .map(|d| d.as_secs())
         .unwrap_or(0);
 
-    let path = history_path();
+    let path = history_file(data_dir, format);
 
     let result = (|| -> std::io::Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
         ensure_not_symlink(&path)?;
-        let mut file = open_history_for_append(&path)?;
-        writeln!(file, "{}\t{}", timestamp, app_name)?;
+        match format {
+            HistoryFormat::Tsv => {
+                let mut file = open_history_for_append(&path)?;
+                writeln!(file, "{}\t{}", timestamp, key)?;
+            }
+            // An array can't be appended to; the whole file is rewritten.
+            HistoryFormat::Json => {
+                let stored = read_history(&path, format)?;
+                let mut entries = stored.entries();
+                entries.push((timestamp, key));
+                let mut names = stored.names();
+                names.insert(key, app_name);
+                write_history(&path, &entries, &names, format)?;
+            }
+        }
         Ok(())
     })();
 
     if result.is_ok() {
         if let Ok(meta) = fs::metadata(&path) {
-            if meta.len() > (MAX_HISTORY_LINES as u64) * 100 {
-                trim_history(MAX_HISTORY_LINES);
+            if meta.len() > (trim.recent as u64) * 100 {
+                trim_history(trim, data_dir, format);
             }
         }
     }
 }
 
-pub fn load_history() -> HashMap<String, u64> {
-    let path = history_path();
-    let file = match fs::File::open(&path) {
-        Ok(f) => f,
-        Err(_) => return HashMap::new(),
+pub fn load_history(data_dir: Option<&Path>, format: HistoryFormat) -> HashMap<String, u64> {
+    let path = history_file(data_dir, format);
+    let Ok(stored) = read_stored(&path, format) else {
+        return HashMap::new();
     };
 
-    let reader = std::io::BufReader::new(file);
     let mut history = HashMap::new();
+    for (ts, name) in stored.entries() {
+        let entry = history.entry(name.to_string()).or_insert(0u64);
+        if ts > *entry {
+            *entry = ts;
+        }
+    }
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
-        if let Some((ts_str, name)) = line.split_once('\t') {
-            if let Ok(ts) = ts_str.parse::<u64>() {
-                let entry = history.entry(name.to_string()).or_insert(0u64);
-                if ts > *entry {
-                    *entry = ts;
+    history
+}
+
+/// Display names that stand for exactly one app key in `apps`, given as
+/// `(name, key)` pairs, mapped to that key. Names that are a key themselves
+/// (entries without a desktop id) or are shared by several apps are left
+/// out, as history recorded under them can't be told apart.
+pub fn name_renames<'a>(apps: &[(&'a str, &'a str)]) -> HashMap<&'a str, &'a str> {
+    let keys: HashSet<&str> = apps.iter().map(|&(_, key)| key).collect();
+    let mut by_name: HashMap<&str, Option<&str>> = HashMap::new();
+    for &(name, key) in apps {
+        by_name
+            .entry(name)
+            .and_modify(|found| {
+                if *found != Some(key) {
+                    *found = None;
                 }
+            })
+            .or_insert(Some(key));
+    }
+    by_name
+        .into_iter()
+        .filter_map(|(name, key)| Some((name, key?)))
+        .filter(|&(name, key)| name != key && !keys.contains(name))
+        .collect()
+}
+
+/// Moves launches recorded under a display name, as yeet did before it
+/// keyed history by desktop id, to the key `renames` gives for it (see
+/// `name_renames`). The file is rewritten only when something moved.
+pub fn migrate_names(
+    renames: &HashMap<&str, &str>,
+    data_dir: Option<&Path>,
+    format: HistoryFormat,
+) {
+    if renames.is_empty() {
+        return;
+    }
+    let path = history_file(data_dir, format);
+    let result = (|| -> std::io::Result<()> {
+        let stored = read_history(&path, format)?;
+        let mut entries = stored.entries();
+        if rekey(&mut entries, renames) > 0 {
+            let mut names = stored.names();
+            for (&name, &key) in renames {
+                names.entry(key).or_insert(name);
             }
+            write_history(&path, &entries, &names, format)?;
         }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        eprintln!("Warning: could not migrate launch history: {e}");
     }
+}
 
-    history
+/// Loaded history keyed by the display names of `apps`, `(name, key)`
+/// pairs, which is how search and the initial list look launches up. Keys
+/// of apps that are gone stay as they are.
+pub fn by_name(history: HashMap<String, u64>, apps: &[(&str, &str)]) -> HashMap<String, u64> {
+    let names: HashMap<&str, &str> = apps.iter().map(|&(name, key)| (key, name)).collect();
+    let mut by_name = HashMap::with_capacity(history.len());
+    for (key, ts) in history {
+        let name = names.get(key.as_str()).map_or(key, |name| name.to_string());
+        let latest = by_name.entry(name).or_insert(0);
+        *latest = ts.max(*latest);
+    }
+    by_name
 }
 
-pub fn trim_history(max_lines: usize) {
-    let path = history_path();
+pub fn trim_history(policy: TrimPolicy, data_dir: Option<&Path>, format: HistoryFormat) {
+    let path = history_file(data_dir, format);
 
     let _ = (|| -> std::io::Result<()> {
-        ensure_not_symlink(&path)?;
-        let content = fs::read_to_string(&path)?;
-        let mut entries: Vec<(u64, &str)> = content
-            .lines()
-            .filter_map(|line| {
-                let (ts_str, name) = line.split_once('\t')?;
-                let ts = ts_str.parse::<u64>().ok()?;
-                Some((ts, name))
-            })
-            .collect();
+        let stored = read_history(&path, format)?;
+        let mut entries = stored.entries();
 
-        if entries.len() <= max_lines {
+        if entries.len() <= policy.recent {
             return Ok(());
         }
 
-        entries.sort_by_key(|&(ts, _)| std::cmp::Reverse(ts));
-        entries.truncate(max_lines);
-        entries.sort_by_key(|&(ts, _)| ts);
-
-        let (temp_path, mut file) = create_temp_history_file(&path)?;
-        for (ts, name) in entries {
-            writeln!(file, "{}\t{}", ts, name)?;
+        if keep_recent_and_frequent(&mut entries, policy) > 0 {
+            write_history(&path, &entries, &stored.names(), format)?;
         }
-        drop(file);
-        fs::rename(&temp_path, &path).map_err(|rename_err| {
-            let _ = fs::remove_file(&temp_path);
-            rename_err
-        })?;
         Ok(())
     })();
 }
 
+/// Drops launches older than `max_age_days`, rewriting the file only when
+/// something was removed. Zero keeps everything.
+pub fn forget_older_than(max_age_days: u64, data_dir: Option<&Path>, format: HistoryFormat) {
+    if max_age_days == 0 {
+        return;
+    }
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let cutoff = now.saturating_sub(max_age_days.saturating_mul(SECS_PER_DAY));
+
+    if let Err(e) = prune_history(&history_file(data_dir, format), format, cutoff) {
+        eprintln!("Warning: could not prune launch history: {e}");
+    }
+}
+
+/// Forgets the most recent launch, e.g. after launching the wrong app.
+/// Returns the name of the app it belonged to.
+pub fn remove_last(
+    data_dir: Option<&Path>,
+    format: HistoryFormat,
+) -> std::io::Result<Option<String>> {
+    let path = history_file(data_dir, format);
+    let stored = read_history(&path, format)?;
+    let mut entries = stored.entries();
+
+    let removed = remove_latest(&mut entries).map(str::to_string);
+    if removed.is_some() {
+        write_history(&path, &entries, &stored.names(), format)?;
+    }
+    Ok(removed)
+}
+
+/// Forgets every launch recorded under any of `keys`, an app's name and
+/// desktop ids; returns how many were removed.
+pub fn remove_app(
+    keys: &[&str],
+    data_dir: Option<&Path>,
+    format: HistoryFormat,
+) -> std::io::Result<usize> {
+    let path = history_file(data_dir, format);
+    let stored = read_history(&path, format)?;
+    let mut entries = stored.entries();
+
+    let removed = remove_named(&mut entries, keys);
+    if removed > 0 {
+        write_history(&path, &entries, &stored.names(), format)?;
+    }
+    Ok(removed)
+}
+
+fn prune_history(path: &Path, format: HistoryFormat, cutoff: u64) -> std::io::Result<usize> {
+    let stored = read_history(path, format)?;
+    let mut entries = stored.entries();
+
+    let removed = remove_before(&mut entries, cutoff);
+    if removed > 0 {
+        write_history(path, &entries, &stored.names(), format)?;
+    }
+    Ok(removed)
+}
+
+fn should_record(app_name: &str, exclude: &[String]) -> bool {
+    !exclude.iter().any(|excluded| excluded == app_name)
+}
+
+/// A history file's contents, holding the keys `entries` borrows.
+enum Stored {
+    Tsv(String),
+    Json(Vec<Record>),
+}
+
+impl Stored {
+    fn entries(&self) -> Vec<(u64, &str)> {
+        match self {
+            Stored::Tsv(content) => parse_entries(content),
+            Stored::Json(records) => records
+                .iter()
+                .map(|record| (record.ts, record.id.as_str()))
+                .collect(),
+        }
+    }
+
+    /// The newest display name recorded for each key; only `history.json`
+    /// keeps them.
+    fn names(&self) -> HashMap<&str, &str> {
+        match self {
+            Stored::Tsv(_) => HashMap::new(),
+            Stored::Json(records) => records
+                .iter()
+                .filter_map(|record| Some((record.id.as_str(), record.name.as_deref()?)))
+                .collect(),
+        }
+    }
+}
+
+/// One launch in `history.json`.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Record {
+    ts: u64,
+    id: String,
+    /// The app's display name, where it's known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Launches of `id` up to and including this one, counted again on
+    /// every write.
+    #[serde(default)]
+    count: u64,
+}
+
+/// The history at `path`, refusing a symlink as the rewrites that follow
+/// do; a missing file is an empty history.
+fn read_history(path: &Path, format: HistoryFormat) -> std::io::Result<Stored> {
+    ensure_not_symlink(path)?;
+    read_stored(path, format)
+}
+
+fn read_stored(path: &Path, format: HistoryFormat) -> std::io::Result<Stored> {
+    let content = match fs::read_to_string(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        result => result?,
+    };
+    match format {
+        HistoryFormat::Tsv => Ok(Stored::Tsv(content)),
+        HistoryFormat::Json => decode_json(&content).map(Stored::Json).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            )
+        }),
+    }
+}
+
+fn parse_entries(content: &str) -> Vec<(u64, &str)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (ts_str, name) = line.split_once('\t')?;
+            let ts = ts_str.parse::<u64>().ok()?;
+            Some((ts, name))
+        })
+        .collect()
+}
+
+/// Removes the newest entry; on equal timestamps the later line wins, as
+/// launches are appended in order.
+fn remove_latest<'a>(entries: &mut Vec<(u64, &'a str)>) -> Option<&'a str> {
+    let (latest, _) = entries
+        .iter()
+        .enumerate()
+        .max_by_key(|&(i, &(ts, _))| (ts, i))?;
+    Some(entries.remove(latest).1)
+}
+
+fn remove_named(entries: &mut Vec<(u64, &str)>, keys: &[&str]) -> usize {
+    let before = entries.len();
+    entries.retain(|&(_, key)| !keys.contains(&key));
+    before - entries.len()
+}
+
+/// Replaces keys found in `renames`; returns how many entries changed.
+fn rekey<'a>(entries: &mut [(u64, &'a str)], renames: &HashMap<&str, &'a str>) -> usize {
+    let mut changed = 0;
+    for (_, key) in entries.iter_mut() {
+        if let Some(&renamed) = renames.get(key) {
+            *key = renamed;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Drops every launch `policy` doesn't keep, leaving the rest in order;
+/// returns how many were removed. Pure recency would lose an app launched
+/// daily for months the moment a burst of other launches pushed it out.
+fn keep_recent_and_frequent(entries: &mut Vec<(u64, &str)>, policy: TrimPolicy) -> usize {
+    let mut counts: HashMap<&str, (usize, u64)> = HashMap::new();
+    for &(ts, name) in entries.iter() {
+        let (count, latest) = counts.entry(name).or_default();
+        *count += 1;
+        *latest = (*latest).max(ts);
+    }
+    let mut by_frequency: Vec<(&str, (usize, u64))> = counts.into_iter().collect();
+    by_frequency.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let mut frequent: HashMap<&str, usize> = by_frequency
+        .into_iter()
+        .take(policy.frequent)
+        .map(|(name, _)| (name, 0))
+        .collect();
+
+    // Newest first; on equal timestamps the later line is the newer launch.
+    let mut newest_first: Vec<usize> = (0..entries.len()).collect();
+    newest_first.sort_by_key(|&i| std::cmp::Reverse((entries[i].0, i)));
+
+    let mut keep = vec![false; entries.len()];
+    for (rank, &i) in newest_first.iter().enumerate() {
+        let kept_for_app = frequent
+            .get_mut(entries[i].1)
+            .filter(|kept| **kept < policy.recent);
+        if let Some(kept) = kept_for_app {
+            *kept += 1;
+            keep[i] = true;
+        }
+        if rank < policy.recent {
+            keep[i] = true;
+        }
+    }
+
+    let before = entries.len();
+    let mut keep = keep.into_iter();
+    entries.retain(|_| keep.next().unwrap_or(false));
+    before - entries.len()
+}
+
+fn remove_before(entries: &mut Vec<(u64, &str)>, cutoff: u64) -> usize {
+    let before = entries.len();
+    entries.retain(|&(ts, _)| ts >= cutoff);
+    before - entries.len()
+}
+
+/// Atomically replaces the history file with `entries`; `names` gives the
+/// display names `history.json` stores alongside the keys.
+fn write_history(
+    path: &Path,
+    entries: &[(u64, &str)],
+    names: &HashMap<&str, &str>,
+    format: HistoryFormat,
+) -> std::io::Result<()> {
+    let (temp_path, mut file) = create_temp_history_file(path)?;
+    match format {
+        HistoryFormat::Tsv => {
+            for (ts, name) in entries {
+                writeln!(file, "{}\t{}", ts, name)?;
+            }
+        }
+        HistoryFormat::Json => {
+            serde_json::to_writer_pretty(&mut file, &json_records(entries, names))?;
+            writeln!(file)?;
+        }
+    }
+    drop(file);
+    fs::rename(&temp_path, path).map_err(|rename_err| {
+        let _ = fs::remove_file(&temp_path);
+        rename_err
+    })
+}
+
+/// `entries` as `history.json` records, oldest first, named from `names`.
+fn json_records(entries: &[(u64, &str)], names: &HashMap<&str, &str>) -> Vec<Record> {
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    entries
+        .iter()
+        .map(|&(ts, id)| {
+            let count = counts.entry(id).or_insert(0);
+            *count += 1;
+            Record {
+                ts,
+                id: id.to_string(),
+                name: names.get(id).map(|name| name.to_string()),
+                count: *count,
+            }
+        })
+        .collect()
+}
+
+/// The launches in `history.json`. Records without a numeric `ts` and a
+/// string `id`, or with a `name` or `count` of the wrong type, are skipped,
+/// like malformed lines of `history.txt`; other keys are ignored. Empty
+/// content is an empty history.
+fn decode_json(content: &str) -> serde_json::Result<Vec<Record>> {
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let values: Vec<serde_json::Value> = serde_json::from_str(content)?;
+    Ok(values
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect())
+}
+
 fn ensure_not_symlink(path: &Path) -> std::io::Result<()> {
     let metadata = match fs::symlink_metadata(path) {
         Ok(meta) => meta,
@@ -186,6 +583,32 @@ mod tests {
         assert_eq!(history["terminal"], 2000);
     }
 
+    #[test]
+    fn launch_counts_do_not_add_up_in_ranking() {
+        // A script launching one app in a loop leaves it no further ahead
+        // than a single launch: ranking only sees the latest time.
+        let dir = std::env::temp_dir().join("yeet_test_history_loop");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut content = String::from("500\tfirefox\n");
+        for ts in 1000..2000 {
+            content.push_str(&format!("{ts}\tlooped\n"));
+        }
+        fs::write(history_path(Some(&dir), HistoryFormat::Tsv), &content).unwrap();
+
+        let history = load_history(Some(&dir), HistoryFormat::Tsv);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history["looped"], 1999);
+        assert_eq!(history["firefox"], 500);
+        // The file itself keeps every launch.
+        assert_eq!(
+            fs::read_to_string(history_path(Some(&dir), HistoryFormat::Tsv)).unwrap(),
+            content
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn load_skips_malformed_lines() {
         let input = "not_a_number\tfirefox\n\nbadline\n1500\tvalid_app\n";
@@ -195,6 +618,332 @@ mod tests {
         assert_eq!(history["valid_app"], 1500);
     }
 
+    #[test]
+    fn remove_latest_drops_newest_launch() {
+        let input = "1000\tfirefox\n3000\tkitty\n2000\tfirefox\n";
+        let mut entries = parse_entries(input);
+
+        assert_eq!(remove_latest(&mut entries), Some("kitty"));
+        assert_eq!(entries, vec![(1000, "firefox"), (2000, "firefox")]);
+    }
+
+    #[test]
+    fn remove_latest_prefers_last_line_on_equal_timestamps() {
+        let mut entries = parse_entries("1000\tfirefox\n1000\tkitty\n");
+        assert_eq!(remove_latest(&mut entries), Some("kitty"));
+    }
+
+    #[test]
+    fn remove_latest_on_empty_history() {
+        let mut entries = parse_entries("");
+        assert_eq!(remove_latest(&mut entries), None);
+    }
+
+    #[test]
+    fn remove_named_drops_every_launch_of_app() {
+        let mut entries = parse_entries("1000\tfirefox\n2000\tkitty\n3000\tfirefox\n");
+
+        assert_eq!(remove_named(&mut entries, &["firefox"]), 2);
+        assert_eq!(entries, vec![(2000, "kitty")]);
+        assert_eq!(remove_named(&mut entries, &["firefox"]), 0);
+
+        let mut entries = parse_entries("1000\tFirefox\n2000\torg.mozilla.firefox\n");
+        assert_eq!(
+            remove_named(&mut entries, &["Firefox", "org.mozilla.firefox"]),
+            2
+        );
+    }
+
+    /// `(name, key)` pairs as the launcher has them: flatpak and packaged
+    /// apps keyed by desktop id, a custom entry by its name, and two apps
+    /// sharing a name.
+    const APPS: [(&str, &str); 5] = [
+        ("Firefox", "org.mozilla.firefox"),
+        ("Kitty", "kitty"),
+        ("My Script", "My Script"),
+        ("Terminal", "org.gnome.Console"),
+        ("Terminal", "org.gnome.Terminal"),
+    ];
+
+    #[test]
+    fn name_keyed_history_moves_to_desktop_ids() {
+        let renames = name_renames(&APPS);
+        let mut expected = HashMap::new();
+        expected.insert("Firefox", "org.mozilla.firefox");
+        expected.insert("Kitty", "kitty");
+        assert_eq!(renames, expected);
+
+        let mut entries = parse_entries(
+            "100\tFirefox\n200\tMy Script\n300\tTerminal\n400\tGone App\n500\tFirefox\n600\torg.mozilla.firefox\n",
+        );
+        assert_eq!(rekey(&mut entries, &renames), 2);
+        assert_eq!(
+            entries,
+            vec![
+                (100, "org.mozilla.firefox"),
+                (200, "My Script"),
+                (300, "Terminal"),
+                (400, "Gone App"),
+                (500, "org.mozilla.firefox"),
+                (600, "org.mozilla.firefox"),
+            ]
+        );
+        assert_eq!(rekey(&mut entries, &renames), 0);
+    }
+
+    #[test]
+    fn history_is_looked_up_by_current_names() {
+        // The flatpak was renamed since these launches were recorded.
+        let history = parse_history_from_str(
+            "100\torg.mozilla.firefox\n200\tFirefox\n300\tMy Script\n400\tgone.app\n",
+        );
+        let apps = [("Firefox Web Browser", "org.mozilla.firefox"), APPS[2]];
+        let by_name = by_name(history, &apps);
+
+        assert_eq!(by_name.get("Firefox Web Browser"), Some(&100));
+        assert_eq!(by_name.get("Firefox"), Some(&200));
+        assert_eq!(by_name.get("My Script"), Some(&300));
+        assert_eq!(by_name.get("gone.app"), Some(&400));
+        assert_eq!(by_name.len(), 4);
+    }
+
+    #[test]
+    fn remove_before_keeps_recent_launches() {
+        let mut entries = parse_entries("100\tfirefox\n500\tkitty\n900\tfirefox\n1000\tgimp\n");
+
+        assert_eq!(remove_before(&mut entries, 500), 1);
+        assert_eq!(
+            entries,
+            vec![(500, "kitty"), (900, "firefox"), (1000, "gimp")]
+        );
+        assert_eq!(remove_before(&mut entries, 0), 0);
+    }
+
+    #[test]
+    fn prune_rewrites_file_without_stale_entries() {
+        let dir = std::env::temp_dir().join("yeet_test_prune");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.txt");
+
+        let day = SECS_PER_DAY;
+        let now = 100 * day;
+        let mut file = fs::File::create(&path).unwrap();
+        for (age_days, name) in [(90, "old"), (31, "stale"), (29, "recent"), (0, "today")] {
+            writeln!(file, "{}\t{}", now - age_days * day, name).unwrap();
+        }
+        drop(file);
+
+        assert_eq!(
+            prune_history(&path, HistoryFormat::Tsv, now - 30 * day).unwrap(),
+            2
+        );
+
+        let remaining = fs::read_to_string(&path).unwrap();
+        let names: Vec<&str> = parse_entries(&remaining).iter().map(|&(_, n)| n).collect();
+        assert_eq!(names, ["recent", "today"]);
+
+        assert_eq!(
+            prune_history(&path, HistoryFormat::Tsv, now - 30 * day).unwrap(),
+            0
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn prune_refuses_symlinked_history() {
+        let dir = std::env::temp_dir().join("yeet_test_prune_symlink");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.txt");
+        fs::write(&target, "1\tfirefox\n").unwrap();
+        let path = dir.join("history.txt");
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        assert!(prune_history(&path, HistoryFormat::Tsv, 1000).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "1\tfirefox\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn data_dir_redirects_history_path() {
+        let dir = Path::new("/home/me/.config/yeet");
+        assert_eq!(
+            history_path(Some(dir), HistoryFormat::Tsv),
+            PathBuf::from("/home/me/.config/yeet/history.txt")
+        );
+        assert!(history_path(None, HistoryFormat::Tsv).ends_with("yeet/history.txt"));
+        assert_eq!(
+            history_path(Some(dir), HistoryFormat::Json),
+            PathBuf::from("/home/me/.config/yeet/history.json")
+        );
+    }
+
+    #[test]
+    fn json_history_round_trips_any_key() {
+        let keys = [
+            "org.mozilla.firefox",
+            "My\tScript",
+            "say \"hi\" \\ bye",
+            "two\nlines",
+            "Café ☕",
+            "org.mozilla.firefox",
+        ];
+        let entries: Vec<(u64, &str)> = (100..).zip(keys).collect();
+        let names = HashMap::from([("org.mozilla.firefox", "Firefox")]);
+        let records = json_records(&entries, &names);
+        let json = serde_json::to_string(&records).unwrap();
+        assert!(json.starts_with(
+            r#"[{"ts":100,"id":"org.mozilla.firefox","name":"Firefox","count":1},{"ts":101,"id":"My\tScript","count":1}"#
+        ));
+        assert_eq!(records[5].count, 2);
+
+        let decoded = Stored::Json(decode_json(&json).unwrap());
+        assert_eq!(decoded.entries(), entries);
+        assert_eq!(decoded.names(), names);
+
+        assert_eq!(decode_json("").unwrap(), []);
+        assert_eq!(decode_json("[]").unwrap(), []);
+    }
+
+    #[test]
+    fn json_history_skips_invalid_records_and_rejects_broken_files() {
+        let json = r#"[
+            {"id": "\u00e9t\u00e9 \ud83c\udf89", "name": "Summer", "ts": 5, "pinned": true},
+            {"ts": 6, "id": "foot", "name": null},
+            {"ts": "soon", "id": "kitty"},
+            {"ts": 7},
+            {"ts": 8, "id": "gimp", "count": 1.5},
+            {"ts": 9, "id": "zed", "name": false},
+            "kitty",
+            null
+        ]"#;
+        let decoded = Stored::Json(decode_json(json).unwrap());
+        assert_eq!(decoded.entries(), [(5, "été 🎉"), (6, "foot")]);
+        assert_eq!(decoded.names(), HashMap::from([("été 🎉", "Summer")]));
+
+        assert!(decode_json(r#"[{"ts": 1, "id": "kitty"}"#).is_err());
+        assert!(decode_json(r#"{"ts": 1, "id": "kitty"}"#).is_err());
+        assert!(decode_json("1000\tkitty\n").is_err());
+    }
+
+    #[test]
+    fn switching_to_json_moves_text_history_over() {
+        let dir = std::env::temp_dir().join("yeet_test_history_json");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let tsv = history_path(Some(&dir), HistoryFormat::Tsv);
+        fs::write(&tsv, "100\tfirefox\n200\tkitty\n300\tfirefox\n").unwrap();
+
+        let history = load_history(Some(&dir), HistoryFormat::Json);
+        assert_eq!(
+            history,
+            HashMap::from([("firefox".into(), 300), ("kitty".into(), 200)])
+        );
+        let json = history_path(Some(&dir), HistoryFormat::Json);
+        let records = decode_json(&fs::read_to_string(&json).unwrap()).unwrap();
+        let counted: Vec<(&str, u64)> = records
+            .iter()
+            .map(|record| (record.id.as_str(), record.count))
+            .collect();
+        assert_eq!(counted, [("firefox", 1), ("kitty", 1), ("firefox", 2)]);
+        // The text file stays as it was, and is no longer read.
+        assert!(tsv.exists());
+        fs::write(&tsv, "900\tgimp\n").unwrap();
+
+        let trim = TrimPolicy {
+            recent: 200,
+            frequent: 10,
+        };
+        record_launch(
+            "Zed",
+            "dev.zed.Zed",
+            &[],
+            Some(&dir),
+            HistoryFormat::Json,
+            trim,
+        );
+        let stored = read_stored(&json, HistoryFormat::Json).unwrap();
+        assert_eq!(stored.names(), HashMap::from([("dev.zed.Zed", "Zed")]));
+        assert_eq!(
+            remove_last(Some(&dir), HistoryFormat::Json).unwrap(),
+            Some("dev.zed.Zed".into())
+        );
+        assert_eq!(
+            remove_app(&["firefox"], Some(&dir), HistoryFormat::Json).unwrap(),
+            2
+        );
+        assert_eq!(
+            load_history(Some(&dir), HistoryFormat::Json),
+            HashMap::from([("kitty".into(), 200)])
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn excluded_apps_are_not_recorded() {
+        let exclude = vec!["KeePassXC".to_string()];
+        assert!(!should_record("KeePassXC", &exclude));
+        assert!(should_record("Firefox", &exclude));
+        assert!(should_record("KeePassXC", &[]));
+    }
+
+    #[test]
+    fn trim_keeps_frequent_apps_recency_would_drop() {
+        // "terminal" was launched all month but not in the last 5 launches.
+        let mut content = String::new();
+        for day in 0..20 {
+            content.push_str(&format!("{}\tterminal\n", day * 100));
+        }
+        for (i, name) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            content.push_str(&format!("{}\t{}\n", 5000 + i, name));
+        }
+
+        let mut recency_only = parse_entries(&content);
+        keep_recent_and_frequent(
+            &mut recency_only,
+            TrimPolicy {
+                recent: 5,
+                frequent: 0,
+            },
+        );
+        assert!(!recency_only.iter().any(|&(_, name)| name == "terminal"));
+
+        let mut entries = parse_entries(&content);
+        let removed = keep_recent_and_frequent(
+            &mut entries,
+            TrimPolicy {
+                recent: 5,
+                frequent: 1,
+            },
+        );
+        assert_eq!(removed, 15);
+        let names: Vec<&str> = entries.iter().map(|&(_, n)| n).collect();
+        assert_eq!(
+            names,
+            ["terminal", "terminal", "terminal", "terminal", "terminal", "a", "b", "c", "d", "e"]
+        );
+        assert_eq!(entries[0].0, 1500);
+    }
+
+    #[test]
+    fn trim_keeps_everything_within_limits() {
+        let mut entries = parse_entries("1\ta\n2\tb\n3\ta\n");
+        let removed = keep_recent_and_frequent(
+            &mut entries,
+            TrimPolicy {
+                recent: 3,
+                frequent: 1,
+            },
+        );
+        assert_eq!(removed, 0);
+        assert_eq!(entries, [(1, "a"), (2, "b"), (3, "a")]);
+    }
+
     #[test]
     fn trim_keeps_only_max_lines() {
         let dir = std::env::temp_dir().join("yeet_test_trim");
@@ -0,0 +1,810 @@
+//! Icon lookup for `appearance.icon_theme`, following the freedesktop Icon
+//! Theme spec: the configured theme first, then the themes it inherits from
+//! (depth first), then hicolor. Without a configured theme everything is
+//! left to GTK's own lookup. An icon given as a path, such as a custom
+//! app's `icon = "~/pictures/tool.png"`, is the file itself.
+//!
+//! Results are kept in a cache file across runs, keyed by theme, icon size
+//! and the modification times of the themes' `index.theme` files, so
+//! updating a theme starts the cache over.
+//!
+//! <https://specifications.freedesktop.org/icon-theme-spec/latest/>
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{expand_path, IconScaling};
+
+const FALLBACK_THEME: &str = "hicolor";
+const EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+
+const CACHE_VERSION: &str = "yeet-icon-cache 1";
+
+pub struct IconLookup {
+    themes: Vec<Theme>,
+    size: i32,
+    scaling: IconScaling,
+    /// Identifies the theme chain the cache is valid for; see `cache_key`.
+    key: String,
+    cache: RefCell<HashMap<String, Option<PathBuf>>>,
+    /// Cache file, and whether `cache` has lookups it doesn't.
+    cache_file: Option<PathBuf>,
+    dirty: Cell<bool>,
+}
+
+struct Theme {
+    /// `<base>/<theme>` for every base dir the theme appears in.
+    roots: Vec<PathBuf>,
+    /// The `index.theme` that was read.
+    index: PathBuf,
+    dirs: Vec<ThemeDir>,
+}
+
+struct ThemeDir {
+    path: String,
+    size: i32,
+    min_size: i32,
+    max_size: i32,
+    threshold: i32,
+    kind: DirKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DirKind {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+impl IconLookup {
+    /// `theme` of `None` disables the lookup; `size` is the pixel size icons
+    /// are shown at, used to pick a directory as `scaling` says. Lookups are
+    /// remembered in `cache_file` when given.
+    pub fn new(
+        theme: Option<&str>,
+        size: i32,
+        scaling: IconScaling,
+        cache_file: Option<PathBuf>,
+    ) -> Self {
+        let mut lookup = Self::with_base_dirs(theme, &icon_base_dirs(), size, scaling);
+        if let Some(path) = cache_file.filter(|_| !lookup.themes.is_empty()) {
+            if let Ok(content) = fs::read_to_string(&path) {
+                *lookup.cache.get_mut() = read_cache(&content, &lookup.key);
+            }
+            lookup.cache_file = Some(path);
+        }
+        lookup
+    }
+
+    fn with_base_dirs(
+        theme: Option<&str>,
+        base_dirs: &[PathBuf],
+        size: i32,
+        scaling: IconScaling,
+    ) -> Self {
+        let themes = match theme {
+            Some(name) => {
+                let installed = base_dirs
+                    .iter()
+                    .any(|base| base.join(name).join("index.theme").is_file());
+                if !installed {
+                    eprintln!("Warning: icon theme \"{name}\" not found");
+                }
+                load_chain(name, base_dirs)
+            }
+            None => Vec::new(),
+        };
+        let index_mtimes: Vec<Option<SystemTime>> = themes
+            .iter()
+            .map(|theme| fs::metadata(&theme.index).and_then(|m| m.modified()).ok())
+            .collect();
+
+        Self {
+            key: cache_key(theme.unwrap_or_default(), size, scaling, &index_mtimes),
+            themes,
+            size,
+            scaling,
+            cache: RefCell::new(HashMap::new()),
+            cache_file: None,
+            dirty: Cell::new(false),
+        }
+    }
+
+    /// The file for `icon`: the file itself when it's a path (see
+    /// `icon_file`), otherwise from the configured theme chain. `None` falls
+    /// back to GTK's lookup.
+    pub fn resolve(&self, icon: &str) -> Option<PathBuf> {
+        if is_path(icon) {
+            return icon_file(icon, dirs::home_dir().as_deref(), |var| {
+                std::env::var(var).ok()
+            });
+        }
+        if self.themes.is_empty() {
+            return None;
+        }
+        if let Some(cached) = self.cache.borrow().get(icon) {
+            // A file removed since it was cached is looked up again.
+            if cached.as_ref().map_or(true, |path| path.is_file()) {
+                return cached.clone();
+            }
+        }
+
+        let found = self
+            .themes
+            .iter()
+            .find_map(|theme| theme.find(icon, self.size, self.scaling));
+        self.cache
+            .borrow_mut()
+            .insert(icon.to_string(), found.clone());
+        self.dirty.set(true);
+        found
+    }
+
+    /// Writes new lookups to the cache file. Failing to is harmless: the
+    /// next run just looks them up again.
+    pub fn save_cache(&self) {
+        let Some(path) = &self.cache_file else {
+            return;
+        };
+        if !self.dirty.replace(false) {
+            return;
+        }
+        let content = write_cache(&self.key, &self.cache.borrow());
+        let tmp = path.with_extension("tmp");
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, content))
+            .and_then(|_| fs::rename(&tmp, path));
+        if let Err(e) = written {
+            eprintln!("Warning: failed to write icon cache: {e}");
+        }
+    }
+}
+
+/// Whether `icon` is a file path rather than an icon theme name, which
+/// never contains a `/`.
+fn is_path(icon: &str) -> bool {
+    icon.contains('/') || icon.starts_with('~')
+}
+
+/// The image `icon` points at, with `~` and `$VAR`/`${VAR}` expanded
+/// (relative paths are taken from the working directory), or `None` when
+/// that isn't a file.
+fn icon_file(
+    icon: &str,
+    home: Option<&Path>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Option<PathBuf> {
+    let path = expand_path(icon, home, var);
+    path.is_file().then_some(path)
+}
+
+/// The first line of a cache file: the theme, icon size and scaling, and
+/// when each theme in the chain last changed its `index.theme`. A cache
+/// with any other key is stale.
+fn cache_key(
+    theme: &str,
+    size: i32,
+    scaling: IconScaling,
+    index_mtimes: &[Option<SystemTime>],
+) -> String {
+    let stamps: Vec<String> = index_mtimes
+        .iter()
+        .map(
+            |mtime| match mtime.and_then(|t| t.duration_since(UNIX_EPOCH).ok()) {
+                Some(since) => format!("{}.{:09}", since.as_secs(), since.subsec_nanos()),
+                None => "-".to_string(),
+            },
+        )
+        .collect();
+    let scaling = match scaling {
+        IconScaling::Down => "down",
+        IconScaling::Nearest => "nearest",
+    };
+    format!(
+        "{CACHE_VERSION}\t{theme}\t{size}\t{scaling}\t{}",
+        stamps.join(",")
+    )
+}
+
+/// Lookups from a cache file, one `icon<TAB>path` per line (empty path:
+/// not in the theme), or nothing if it was written for another `key`.
+fn read_cache(content: &str, key: &str) -> HashMap<String, Option<PathBuf>> {
+    let mut lines = content.lines();
+    if lines.next() != Some(key) {
+        return HashMap::new();
+    }
+    lines
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(icon, path)| {
+            let path = (!path.is_empty()).then(|| PathBuf::from(path));
+            (icon.to_string(), path)
+        })
+        .collect()
+}
+
+fn write_cache(key: &str, entries: &HashMap<String, Option<PathBuf>>) -> String {
+    let mut content = format!("{key}\n");
+    for (icon, path) in entries {
+        let path = path
+            .as_deref()
+            .map(Path::to_string_lossy)
+            .unwrap_or_default();
+        if icon.contains(['\t', '\n']) || path.contains('\n') {
+            continue;
+        }
+        content.push_str(&format!("{icon}\t{path}\n"));
+    }
+    content
+}
+
+impl Theme {
+    fn load(name: &str, base_dirs: &[PathBuf]) -> Option<(Self, Vec<String>)> {
+        let roots: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|root| root.is_dir())
+            .collect();
+        let (index, content) = roots.iter().find_map(|root| {
+            let index = root.join("index.theme");
+            fs::read_to_string(&index)
+                .ok()
+                .map(|content| (index, content))
+        })?;
+        let sections = parse_index(&content);
+        let main = sections.get("Icon Theme")?;
+
+        let dirs = list(main.get("Directories").copied())
+            .into_iter()
+            .filter_map(|path| ThemeDir::parse(path, sections.get(path)?))
+            .collect();
+        let parents = list(main.get("Inherits").copied())
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+
+        Some((Self { roots, index, dirs }, parents))
+    }
+
+    /// The best match for `icon` in this theme: an exact size wins outright,
+    /// otherwise the directory closest to `size`. With `IconScaling::Down`
+    /// any larger directory beats a smaller one, which is only used when
+    /// there's no larger icon.
+    fn find(&self, icon: &str, size: i32, scaling: IconScaling) -> Option<PathBuf> {
+        let mut best: Option<((bool, i32), PathBuf)> = None;
+
+        for dir in &self.dirs {
+            let distance = dir.distance(size);
+            let upscaled = scaling == IconScaling::Down && dir.is_smaller(size);
+            let rank = (upscaled, distance);
+            if best.as_ref().is_some_and(|(r, _)| *r <= rank) {
+                continue;
+            }
+            if let Some(path) = self.file_in(dir, icon) {
+                if distance == 0 {
+                    return Some(path);
+                }
+                best = Some((rank, path));
+            }
+        }
+
+        best.map(|(_, path)| path)
+    }
+
+    fn file_in(&self, dir: &ThemeDir, icon: &str) -> Option<PathBuf> {
+        self.roots.iter().find_map(|root| {
+            EXTENSIONS
+                .iter()
+                .map(|ext| root.join(&dir.path).join(format!("{icon}.{ext}")))
+                .find(|path| path.is_file())
+        })
+    }
+}
+
+impl ThemeDir {
+    fn parse(path: &str, keys: &HashMap<&str, &str>) -> Option<Self> {
+        let int = |key: &str| keys.get(key).and_then(|v| v.trim().parse::<i32>().ok());
+        let size = int("Size")?;
+        let kind = match keys.get("Type").copied() {
+            Some("Fixed") => DirKind::Fixed,
+            Some("Scalable") => DirKind::Scalable,
+            _ => DirKind::Threshold,
+        };
+
+        Some(Self {
+            path: path.to_string(),
+            size,
+            min_size: int("MinSize").unwrap_or(size),
+            max_size: int("MaxSize").unwrap_or(size),
+            threshold: int("Threshold").unwrap_or(2),
+            kind,
+        })
+    }
+
+    fn distance(&self, size: i32) -> i32 {
+        match self.kind {
+            DirKind::Fixed => (self.size - size).abs(),
+            DirKind::Scalable if size < self.min_size => self.min_size - size,
+            DirKind::Scalable if size > self.max_size => size - self.max_size,
+            DirKind::Scalable => 0,
+            DirKind::Threshold if size < self.size - self.threshold => {
+                self.size - self.threshold - size
+            }
+            DirKind::Threshold if size > self.size + self.threshold => {
+                size - self.size - self.threshold
+            }
+            DirKind::Threshold => 0,
+        }
+    }
+
+    /// Whether every icon in the directory is smaller than `size`, so
+    /// showing one at `size` means scaling it up.
+    fn is_smaller(&self, size: i32) -> bool {
+        let largest = match self.kind {
+            DirKind::Fixed => self.size,
+            DirKind::Scalable => self.max_size,
+            DirKind::Threshold => self.size + self.threshold,
+        };
+        largest < size
+    }
+}
+
+/// `name` followed by its ancestors depth first, then hicolor.
+fn load_chain(name: &str, base_dirs: &[PathBuf]) -> Vec<Theme> {
+    fn visit(name: &str, base_dirs: &[PathBuf], seen: &mut HashSet<String>, out: &mut Vec<Theme>) {
+        if name == FALLBACK_THEME || !seen.insert(name.to_string()) {
+            return;
+        }
+        if let Some((theme, parents)) = Theme::load(name, base_dirs) {
+            out.push(theme);
+            for parent in parents {
+                visit(&parent, base_dirs, seen, out);
+            }
+        }
+    }
+
+    let mut themes = Vec::new();
+    visit(name, base_dirs, &mut HashSet::new(), &mut themes);
+    themes.extend(Theme::load(FALLBACK_THEME, base_dirs).map(|(theme, _)| theme));
+    themes
+}
+
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".icons"));
+    }
+    if let Some(data_home) = dirs::data_local_dir() {
+        dirs.push(data_home.join("icons"));
+    }
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    dirs.extend(
+        data_dirs
+            .split(':')
+            .filter(|d| !d.is_empty())
+            .map(|d| Path::new(d).join("icons")),
+    );
+
+    dirs
+}
+
+fn list(value: Option<&str>) -> Vec<&str> {
+    value
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_index(content: &str) -> HashMap<&str, HashMap<&str, &str>> {
+    let mut sections: HashMap<&str, HashMap<&str, &str>> = HashMap::new();
+    let mut current = None;
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(section);
+            sections.entry(section).or_default();
+        } else if let (Some(section), Some((key, value))) = (current, line.split_once('=')) {
+            sections
+                .entry(section)
+                .or_default()
+                .insert(key.trim(), value.trim());
+        }
+    }
+
+    sections
+}
+
+/// Rows still waiting for their icon, handed out visible rows first, then
+/// the nearest ones within `lookahead` of the view. Rows further away stay
+/// queued until scrolling brings them close.
+#[derive(Debug, Default)]
+pub struct IconQueue {
+    pending: BTreeSet<usize>,
+}
+
+impl IconQueue {
+    /// Starts over for a freshly populated list of `rows` rows, dropping
+    /// whatever the previous list still had pending.
+    pub fn reset(&mut self, rows: usize) {
+        self.pending = (0..rows).collect();
+    }
+
+    /// Queues `rows` appended to the list.
+    pub fn add(&mut self, rows: Range<usize>) {
+        self.pending.extend(rows);
+    }
+
+    /// The next row to load, or `None` once every row near `visible` has its
+    /// icon.
+    pub fn next(&mut self, visible: Range<usize>, lookahead: usize) -> Option<usize> {
+        let row = self
+            .pending
+            .range(visible.clone())
+            .next()
+            .copied()
+            .or_else(|| {
+                let below = self
+                    .pending
+                    .range(visible.end..visible.end.saturating_add(lookahead))
+                    .next()
+                    .map(|&row| (row - visible.end, row));
+                let above = self
+                    .pending
+                    .range(visible.start.saturating_sub(lookahead)..visible.start)
+                    .next_back()
+                    .map(|&row| (visible.start - row, row));
+                match (below, above) {
+                    (Some(b), Some(a)) => Some(b.min(a).1),
+                    (b, a) => b.or(a).map(|(_, row)| row),
+                }
+            })?;
+        self.pending.remove(&row);
+        Some(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn theme(base: &Path, name: &str, inherits: &str, dirs: &[(&str, &str)]) {
+        let mut index = format!(
+            "[Icon Theme]\nName={name}\nInherits={inherits}\nDirectories={}\n",
+            dirs.iter().map(|(d, _)| *d).collect::<Vec<_>>().join(",")
+        );
+        for (dir, keys) in dirs {
+            index.push_str(&format!("\n[{dir}]\n{keys}\n"));
+        }
+        write(&base.join(name).join("index.theme"), &index);
+    }
+
+    /// Alpha (fixed 48px, inherits Base) and Beta (scalable) both ship
+    /// `firefox`; Base and hicolor fill in the rest.
+    fn fixture(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&base);
+
+        let fixed = [("48x48/apps", "Size=48\nType=Fixed")];
+        let sized = [
+            ("16x16/apps", "Size=16\nType=Fixed"),
+            ("32x32/apps", "Size=32\nType=Threshold"),
+        ];
+        let scalable = [(
+            "scalable/apps",
+            "Size=48\nMinSize=8\nMaxSize=512\nType=Scalable",
+        )];
+
+        theme(&base, "Alpha", "Base", &fixed);
+        write(&base.join("Alpha/48x48/apps/firefox.png"), "");
+
+        theme(&base, "Beta", "", &scalable);
+        write(&base.join("Beta/scalable/apps/firefox.svg"), "");
+
+        theme(&base, "Base", "hicolor", &sized);
+        write(&base.join("Base/16x16/apps/kitty.png"), "");
+        write(&base.join("Base/32x32/apps/kitty.png"), "");
+
+        theme(&base, "hicolor", "", &fixed);
+        write(&base.join("hicolor/48x48/apps/firefox.png"), "");
+        write(&base.join("hicolor/48x48/apps/gimp.png"), "");
+
+        base
+    }
+
+    fn lookup(base: &Path, theme: Option<&str>) -> IconLookup {
+        IconLookup::with_base_dirs(theme, &[base.to_path_buf()], 36, IconScaling::Down)
+    }
+
+    #[test]
+    fn configured_theme_picks_its_own_icon() {
+        let base = fixture("yeet_test_icons_themes");
+
+        assert_eq!(
+            lookup(&base, Some("Alpha")).resolve("firefox"),
+            Some(base.join("Alpha/48x48/apps/firefox.png"))
+        );
+        assert_eq!(
+            lookup(&base, Some("Beta")).resolve("firefox"),
+            Some(base.join("Beta/scalable/apps/firefox.svg"))
+        );
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn missing_sizes_scale_down_from_larger_icons_or_take_the_nearest() {
+        let base = std::env::temp_dir().join("yeet_test_icons_scaling");
+        let _ = fs::remove_dir_all(&base);
+        let sizes = [24, 40, 64];
+        let dirs: Vec<(String, String)> = sizes
+            .iter()
+            .map(|size| {
+                (
+                    format!("{size}x{size}/apps"),
+                    format!("Size={size}\nType=Fixed"),
+                )
+            })
+            .collect();
+        let dirs: Vec<(&str, &str)> = dirs.iter().map(|(d, k)| (d.as_str(), k.as_str())).collect();
+        theme(&base, "Sizes", "", &dirs);
+        for size in sizes {
+            write(&base.join(format!("Sizes/{size}x{size}/apps/term.png")), "");
+        }
+        let chosen = |size, scaling| {
+            IconLookup::with_base_dirs(Some("Sizes"), &[base.clone()], size, scaling)
+                .resolve("term")
+                .unwrap()
+        };
+        let file = |size: i32| base.join(format!("Sizes/{size}x{size}/apps/term.png"));
+
+        // 48px: 40 is closer, 64 is the next larger.
+        assert_eq!(chosen(48, IconScaling::Nearest), file(40));
+        assert_eq!(chosen(48, IconScaling::Down), file(64));
+        // An exact size wins either way.
+        assert_eq!(chosen(40, IconScaling::Down), file(40));
+        assert_eq!(chosen(40, IconScaling::Nearest), file(40));
+        // 36px: the larger 40 is also the nearest; 30px: 24 is nearer.
+        assert_eq!(chosen(36, IconScaling::Nearest), file(40));
+        assert_eq!(chosen(36, IconScaling::Down), file(40));
+        assert_eq!(chosen(30, IconScaling::Nearest), file(24));
+        assert_eq!(chosen(30, IconScaling::Down), file(40));
+        // Nothing larger than 96px: the nearest smaller it is.
+        assert_eq!(chosen(96, IconScaling::Down), file(64));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn falls_back_through_parents_then_hicolor() {
+        let base = fixture("yeet_test_icons_fallback");
+        let alpha = lookup(&base, Some("Alpha"));
+
+        assert_eq!(
+            alpha.resolve("kitty"),
+            Some(base.join("Base/32x32/apps/kitty.png"))
+        );
+        assert_eq!(
+            alpha.resolve("gimp"),
+            Some(base.join("hicolor/48x48/apps/gimp.png"))
+        );
+        assert_eq!(
+            lookup(&base, Some("Beta")).resolve("gimp"),
+            Some(base.join("hicolor/48x48/apps/gimp.png"))
+        );
+        assert_eq!(alpha.resolve("missing"), None);
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn unset_theme_leaves_lookup_to_gtk() {
+        let base = fixture("yeet_test_icons_unset");
+        assert_eq!(lookup(&base, None).resolve("firefox"), None);
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn icon_paths_are_files_not_theme_names() {
+        let base = fixture("yeet_test_icons_paths");
+        write(&base.join("home/pictures/tool.png"), "");
+        let home = base.join("home");
+        let var = |name: &str| (name == "PICS").then(|| format!("{}/pictures", home.display()));
+
+        assert!(!is_path("firefox"));
+        assert!(is_path("~/pictures/tool.png"));
+        assert!(is_path("icons/tool.png"));
+
+        let tool = Some(home.join("pictures/tool.png"));
+        assert_eq!(icon_file("~/pictures/tool.png", Some(&home), var), tool);
+        assert_eq!(icon_file("$PICS/tool.png", Some(&home), var), tool);
+        let absolute = home.join("pictures/tool.png");
+        assert_eq!(
+            icon_file(&absolute.to_string_lossy(), Some(&home), var),
+            tool
+        );
+        assert_eq!(icon_file("~/pictures/gone.png", Some(&home), var), None);
+
+        // A path is used as is even with a theme configured, while the same
+        // word as a name goes to the theme.
+        let alpha = lookup(&base, Some("Alpha"));
+        let firefox = base.join("hicolor/48x48/apps/firefox.png");
+        assert_eq!(
+            alpha.resolve(&firefox.to_string_lossy()),
+            Some(firefox.clone())
+        );
+        assert_eq!(
+            alpha.resolve("firefox"),
+            Some(base.join("Alpha/48x48/apps/firefox.png"))
+        );
+        assert_eq!(
+            lookup(&base, None).resolve(&firefox.to_string_lossy()),
+            Some(firefox)
+        );
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn cache_key_tracks_theme_size_and_index_changes() {
+        let then = UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let later = then + std::time::Duration::from_millis(1);
+        let key = |theme, size, scaling, mtime| cache_key(theme, size, scaling, &[mtime, None]);
+
+        let down = IconScaling::Down;
+        let papirus = key("Papirus", 32, down, Some(then));
+        assert_eq!(
+            papirus,
+            "yeet-icon-cache 1\tPapirus\t32\tdown\t1700000000.000000000,-"
+        );
+        assert_eq!(papirus, key("Papirus", 32, down, Some(then)));
+        assert_ne!(papirus, key("Papirus-Dark", 32, down, Some(then)));
+        assert_ne!(papirus, key("Papirus", 48, down, Some(then)));
+        assert_ne!(
+            papirus,
+            key("Papirus", 32, IconScaling::Nearest, Some(then))
+        );
+        assert_ne!(papirus, key("Papirus", 32, down, Some(later)));
+    }
+
+    #[test]
+    fn cache_round_trips_and_goes_stale_with_the_key() {
+        let mut entries = HashMap::new();
+        entries.insert("firefox".to_string(), Some(PathBuf::from("/i/firefox.svg")));
+        entries.insert("missing".to_string(), None);
+
+        let content = write_cache("key", &entries);
+        assert_eq!(read_cache(&content, "key"), entries);
+        assert!(read_cache(&content, "other key").is_empty());
+        assert!(read_cache("", "key").is_empty());
+    }
+
+    #[test]
+    fn cache_file_is_reused_until_theme_changes() {
+        let base = fixture("yeet_test_icons_cache");
+        let cache_file = base.join("cache").join("icon-cache.txt");
+        let with_cache = || {
+            let mut lookup = lookup(&base, Some("Alpha"));
+            if let Ok(content) = fs::read_to_string(&cache_file) {
+                *lookup.cache.get_mut() = read_cache(&content, &lookup.key);
+            }
+            lookup.cache_file = Some(cache_file.clone());
+            lookup
+        };
+
+        let first = with_cache();
+        assert_eq!(
+            first.resolve("firefox"),
+            Some(base.join("Alpha/48x48/apps/firefox.png"))
+        );
+        first.save_cache();
+
+        // A cached path is trusted as long as the theme is unchanged...
+        let second = with_cache();
+        assert!(second.cache.borrow().contains_key("firefox"));
+
+        // ...and dropped once an index.theme in the chain changes.
+        let index = base.join("Base/index.theme");
+        let file = fs::File::options().append(true).open(&index).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert!(with_cache().cache.borrow().is_empty());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn directory_distance_follows_spec() {
+        let dir = |kind, size, min_size, max_size| ThemeDir {
+            path: String::new(),
+            size,
+            min_size,
+            max_size,
+            threshold: 2,
+            kind,
+        };
+
+        assert_eq!(dir(DirKind::Fixed, 48, 48, 48).distance(36), 12);
+        assert_eq!(dir(DirKind::Scalable, 48, 8, 512).distance(36), 0);
+        assert_eq!(dir(DirKind::Scalable, 48, 48, 512).distance(36), 12);
+        assert_eq!(dir(DirKind::Threshold, 32, 32, 32).distance(33), 0);
+        assert_eq!(dir(DirKind::Threshold, 32, 32, 32).distance(36), 2);
+    }
+
+    fn drain(queue: &mut IconQueue, visible: Range<usize>, lookahead: usize) -> Vec<usize> {
+        std::iter::from_fn(|| queue.next(visible.clone(), lookahead)).collect()
+    }
+
+    #[test]
+    fn queue_loads_visible_rows_first_then_nearest() {
+        let mut queue = IconQueue::default();
+        queue.reset(20);
+
+        assert_eq!(drain(&mut queue, 5..8, 2), [5, 6, 7, 8, 4, 9, 3]);
+    }
+
+    #[test]
+    fn queue_leaves_far_rows_until_scrolled_to() {
+        let mut queue = IconQueue::default();
+        queue.reset(20);
+        assert_eq!(drain(&mut queue, 0..3, 1), [0, 1, 2, 3]);
+
+        // Scrolling down picks up where the view now is; rows skipped over
+        // in between are never loaded.
+        assert_eq!(drain(&mut queue, 15..17, 0), [15, 16]);
+        assert_eq!(queue.next(0..3, 1), None);
+    }
+
+    #[test]
+    fn appended_rows_join_the_queue() {
+        let mut queue = IconQueue::default();
+        queue.reset(3);
+        assert_eq!(drain(&mut queue, 0..3, 0), [0, 1, 2]);
+
+        queue.add(3..6);
+        assert_eq!(drain(&mut queue, 2..4, 1), [3, 4]);
+        assert_eq!(drain(&mut queue, 5..6, 0), [5]);
+    }
+
+    #[test]
+    fn reset_cancels_pending_rows() {
+        let mut queue = IconQueue::default();
+        queue.reset(10);
+        assert_eq!(queue.next(0..2, 0), Some(0));
+
+        queue.reset(2);
+        assert_eq!(drain(&mut queue, 0..8, 4), [0, 1]);
+    }
+
+    #[test]
+    fn parses_index_sections() {
+        let sections =
+            parse_index("# comment\n[Icon Theme]\nInherits = a, b\n\n[16x16]\nSize=16\n");
+        assert_eq!(
+            list(sections["Icon Theme"].get("Inherits").copied()),
+            ["a", "b"]
+        );
+        assert_eq!(sections["16x16"]["Size"], "16");
+    }
+}
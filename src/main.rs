@@ -1,24 +1,298 @@
+mod clipboard;
+mod collate;
 mod config;
+mod control;
 mod desktop;
+mod exec;
 mod history;
+mod icons;
+mod recent;
+mod search;
+mod theme;
+mod timing;
 mod ui;
 
-use config::Config;
+use config::{Config, MultilinePaste, NoMatchAction};
+use control::{Command, ControlSocket};
 use desktop::{discover_apps, launch_app, App};
 use gtk4::gio::ApplicationFlags;
 use gtk4::prelude::*;
 use gtk4::Application;
-use std::cell::Cell;
+use search::{Matcher, ScoredText, SearchText};
+use std::cell::{Cell, RefCell};
 use std::io::BufRead;
+use std::path::Path;
 use std::rc::Rc;
+use timing::{Phase, Timing};
 
 const APP_ID: &str = "dev.yeet.launcher";
 
+// Exit status contract, shared by every mode (see `--help`).
+const EXIT_OK: i32 = 0;
+const EXIT_CANCELLED: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// What `--print-only` prints for the selected app instead of launching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PrintField {
+    Exec,
+    Id,
+}
+
+impl PrintField {
+    /// Custom entries have no desktop id, so `Id` falls back to their name.
+    fn of(self, app: &App) -> String {
+        match self {
+            PrintField::Exec => app.command_line(),
+            PrintField::Id => app.desktop_id.clone().unwrap_or_else(|| app.name.clone()),
+        }
+    }
+}
+
+/// What accepting an entry in the launcher does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Accept {
+    Launch,
+    /// `--print-only`, `--print-id` or `--format`: print the selection,
+    /// as `--format` gives it when set.
+    Print(PrintField, Option<OutputFormat>),
+    /// `--dry-run`: print the command launching it would run, see
+    /// `desktop::launch_line`.
+    DryRun,
+}
+
+impl Accept {
+    fn new(print: Option<PrintField>, format: Option<OutputFormat>, dry_run: bool) -> Self {
+        if dry_run {
+            return Accept::DryRun;
+        }
+        // --format on its own prints the selection like --print-only.
+        match (print, format) {
+            (None, None) => Accept::Launch,
+            (print, format) => Accept::Print(print.unwrap_or(PrintField::Exec), format),
+        }
+    }
+
+    /// The line accepting `app` prints instead of launching it; `None` for
+    /// `Launch`.
+    fn output(&self, app: &App, config: &Config) -> Option<std::io::Result<String>> {
+        match self {
+            Accept::Launch => None,
+            Accept::Print(_, Some(format)) => Some(Ok(format.render(app))),
+            Accept::Print(field, None) => Some(Ok(field.of(app))),
+            Accept::DryRun => Some(desktop::launch_line(app, config)),
+        }
+    }
+}
+
+/// A selection's fields that `--format` can print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormatField {
+    Name,
+    Exec,
+    Id,
+    Icon,
+    Description,
+    Source,
+}
+
+impl FormatField {
+    fn named(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(Self::Name),
+            "exec" => Some(Self::Exec),
+            "id" => Some(Self::Id),
+            "icon" => Some(Self::Icon),
+            "description" => Some(Self::Description),
+            "source" => Some(Self::Source),
+            _ => None,
+        }
+    }
+
+    /// Fields an app doesn't have print as nothing.
+    fn of(self, app: &App) -> String {
+        match self {
+            Self::Name => app.name.clone(),
+            Self::Exec => PrintField::Exec.of(app),
+            Self::Id => PrintField::Id.of(app),
+            Self::Icon => app.icon.clone().unwrap_or_default(),
+            Self::Description => app.description.clone().unwrap_or_default(),
+            Self::Source => app.source.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FormatPart {
+    Text(String),
+    Field(FormatField),
+}
+
+/// `--format` template for the printed selection, e.g. `{name}\t{id}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OutputFormat {
+    parts: Vec<FormatPart>,
+}
+
+impl OutputFormat {
+    /// Parses `{field}` placeholders, `{{`/`}}` for literal braces, and
+    /// `\t`, `\n`, `\\` escapes (shells pass them through in quotes). An
+    /// unknown placeholder is an error rather than printed as is, so a typo
+    /// can't feed a script the wrong data.
+    fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.peek() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('\\') => text.push('\\'),
+                    _ => {
+                        text.push('\\');
+                        continue;
+                    }
+                },
+                '{' if chars.peek() == Some(&'{') => text.push('{'),
+                '}' if chars.peek() == Some(&'}') => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed {{{name}")),
+                        }
+                    }
+                    let field = FormatField::named(&name).ok_or_else(|| {
+                        format!(
+                            "unknown placeholder {{{name}}} (known: {{name}}, {{exec}}, {{id}}, {{icon}}, {{description}}, {{source}})"
+                        )
+                    })?;
+                    if !text.is_empty() {
+                        parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(FormatPart::Field(field));
+                    continue;
+                }
+                '}' => return Err("unmatched } (write }} for a literal one)".to_string()),
+                c => {
+                    text.push(c);
+                    continue;
+                }
+            }
+            // An escape or doubled brace: skip its second character.
+            chars.next();
+        }
+        if !text.is_empty() {
+            parts.push(FormatPart::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    fn render(&self, app: &App) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                FormatPart::Text(text) => text.clone(),
+                FormatPart::Field(field) => field.of(app),
+            })
+            .collect()
+    }
+}
+
 fn main() {
+    let started = Timing::start();
     let mut dmenu = false;
-    for arg in std::env::args().skip(1) {
+    let mut timing = false;
+    let mut theme_preview = false;
+    let mut recent = false;
+    let mut dry_run = false;
+    let mut print: Option<PrintField> = None;
+    let mut format: Option<OutputFormat> = None;
+    // Read up front so it also covers options acted on as soon as they're
+    // parsed, like --launch.
+    let record_history = !std::env::args().skip(1).any(|arg| arg == "--no-history");
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
         match arg.as_str() {
             "-d" | "--dmenu" => dmenu = true,
+            "--print-only" => print = print.or(Some(PrintField::Exec)),
+            "--print-id" => print = Some(PrintField::Id),
+            "--timing" => timing = true,
+            "--no-history" => {}
+            "--theme-preview" => theme_preview = true,
+            "--recent" => recent = true,
+            "--dry-run" => dry_run = true,
+            "--format" => {
+                let Some(template) = args.next() else {
+                    eprintln!("yeet: --format expects a template");
+                    std::process::exit(EXIT_ERROR);
+                };
+                match OutputFormat::parse(&template) {
+                    Ok(parsed) => format = Some(parsed),
+                    Err(e) => {
+                        eprintln!("yeet: --format: {e}");
+                        std::process::exit(EXIT_ERROR);
+                    }
+                }
+            }
+            "--validate-config" => {
+                let path = args.next_if(|arg| !arg.starts_with('-'));
+                let (status, report) = validate_config(path.as_deref().map(Path::new));
+                if status == EXIT_OK {
+                    println!("{report}");
+                } else {
+                    eprintln!("{report}");
+                }
+                std::process::exit(status);
+            }
+            "--forget-last" => {
+                forget_last();
+                return;
+            }
+            "--install-theme" => {
+                let Some(name) = args.next() else {
+                    eprintln!("yeet: --install-theme expects a theme name");
+                    std::process::exit(EXIT_ERROR);
+                };
+                install_theme(&name);
+                return;
+            }
+            "--score" => {
+                let Some(query) = args.next() else {
+                    eprintln!("yeet: --score expects a query");
+                    std::process::exit(EXIT_ERROR);
+                };
+                print_scores(&query);
+                return;
+            }
+            "--launch" => {
+                let Some(id) = args.next() else {
+                    eprintln!("yeet: --launch expects a desktop id");
+                    std::process::exit(EXIT_ERROR);
+                };
+                launch_by_id(&id, record_history);
+                return;
+            }
+            "--launch-interface" => {
+                let Some(interface) = args.next() else {
+                    eprintln!("yeet: --launch-interface expects an interface name");
+                    std::process::exit(EXIT_ERROR);
+                };
+                launch_interface(&interface, record_history);
+                return;
+            }
+            "--forget" => {
+                let Some(name) = args.next() else {
+                    eprintln!("yeet: --forget expects an app name");
+                    std::process::exit(EXIT_ERROR);
+                };
+                forget_app(&name);
+                return;
+            }
             "-h" | "--help" => {
                 print_help();
                 return;
@@ -29,17 +303,36 @@ fn main() {
             }
             other => {
                 eprintln!("yeet: unknown option '{other}' (see --help)");
-                std::process::exit(2);
+                std::process::exit(EXIT_ERROR);
             }
         }
     }
 
-    let config = Config::load();
+    if dmenu && print.is_some() {
+        eprintln!("yeet: --dmenu already prints the selection; drop --print-only/--print-id");
+        std::process::exit(EXIT_ERROR);
+    }
+
+    if dry_run && (dmenu || print.is_some() || format.is_some()) {
+        eprintln!("yeet: --dry-run prints the launch command; drop --dmenu/--print-only/--print-id/--format");
+        std::process::exit(EXIT_ERROR);
+    }
+
+    let mut config = load_config();
+    if !record_history {
+        config.search.use_history = false;
+    }
+    let timing = timing.then_some(started);
 
     if dmenu {
-        run_dmenu(config);
+        run_dmenu(config, format, timing);
+        return;
+    }
+    let accept = Accept::new(print, format, dry_run);
+    if recent {
+        run_recent(config, accept, timing, record_history);
     } else {
-        run_launcher(config);
+        run_launcher(config, accept, timing, record_history, theme_preview);
     }
 }
 
@@ -50,13 +343,225 @@ fn print_help() {
 Usage: yeet [OPTIONS]
 
 Options:
-  -d, --dmenu    read items from stdin, print the selection to stdout
-  -h, --help     print this help
-  -V, --version  print version",
+  -d, --dmenu      read items from stdin, print the selection to stdout
+  --print-only     print the selected app's command instead of launching it
+  --print-id       print the selected app's desktop id instead of launching it
+  --format TEMPLATE
+                   print the selection as TEMPLATE, with {name}, {exec}, {id},
+                   {icon}, {description}, {source}, and \t, \n escapes; implies
+                   --print-only outside dmenu mode
+  --dry-run        print the command an accepted entry would run (terminal,
+                   launch_prefix, nice/ionice and environment included)
+                   instead of running it
+  --launch ID      launch the app with desktop id ID without showing yeet
+  --launch-interface INTERFACE
+                   launch the app implementing INTERFACE (desktop entry
+                   Implements=), or pick one in yeet if several do
+  --recent         list recently used files (recently-used.xbel) instead of
+                   apps and open the selected one with its default app
+  --forget-last    remove the most recent launch from history
+  --forget NAME    remove every launch of NAME from history
+  --score QUERY    print every app's score for QUERY and whether it's listed
+  --no-history     don't record launches or rank by launch history
+  --timing         print how long discovery, icons and the first paint took
+                   to stderr
+  --validate-config [PATH]
+                   check the config (or only the file at PATH) without
+                   starting yeet; exits 2 if it has errors or problems
+  --install-theme NAME
+                   copy a bundled theme (latte, nord) into the config dir
+  --theme-preview  switch between installed and bundled themes with F5 and
+                   Shift+F5, without changing the config
+  -h, --help       print this help
+  -V, --version    print version
+
+Exit status:
+  0    an app was launched, an item selected, or a command succeeded
+  1    closed without a selection (Escape or another cancel key)
+  2    bad option, unreadable or invalid config, or another error
+  130  interrupted (Ctrl+C)",
         env!("CARGO_PKG_VERSION")
     );
 }
 
+/// `--validate-config`: the exit status, `EXIT_OK` only for a config that
+/// parses and has no problems, and the report to print.
+fn validate_config(path: Option<&Path>) -> (i32, String) {
+    match Config::check(path) {
+        Err(e) => (EXIT_ERROR, format!("yeet: {e}")),
+        Ok(problems) if problems.is_empty() => (EXIT_OK, "config ok".to_string()),
+        Ok(problems) => {
+            let lines: Vec<String> = problems.iter().map(|p| format!("yeet: {p}")).collect();
+            (EXIT_ERROR, lines.join("\n"))
+        }
+    }
+}
+
+/// The user's config, or exit with `EXIT_ERROR` when it's broken.
+fn load_config() -> Config {
+    Config::load().unwrap_or_else(|e| {
+        eprintln!("yeet: {e}");
+        std::process::exit(EXIT_ERROR);
+    })
+}
+
+fn print_scores(query: &str) {
+    let config = load_config();
+    let apps = discover_apps(&config);
+    let texts: Vec<SearchText> = apps
+        .iter()
+        .map(|app| SearchText::new(&app.name, &app.keywords).favorite_at(app.favorite_position))
+        .collect();
+    let matcher = Matcher::new(config.search.matcher);
+    let report = search::score_report(&texts, &matcher, query.trim(), &config.search);
+    print!("{}", format_scores(&apps, &report));
+}
+
+/// One line per matched app: score, `pass`/`fail`, name; then how many
+/// apps didn't match at all.
+fn format_scores(apps: &[App], report: &[ScoredText]) -> String {
+    let mut out = String::new();
+    for scored in report {
+        let verdict = if scored.passed { "pass" } else { "fail" };
+        out.push_str(&format!(
+            "{:>6}  {verdict}  {}\n",
+            scored.score, apps[scored.index].name
+        ));
+    }
+    let unmatched = apps.len() - report.len();
+    if unmatched > 0 {
+        out.push_str(&format!("({unmatched} more did not match)\n"));
+    }
+    out
+}
+
+/// `--launch`: the launcher's launch path (history, `launch_prefix`,
+/// hooks) for one app, without a window.
+fn launch_by_id(id: &str, record_history: bool) {
+    let config = load_config();
+    let apps = discover_apps(&config);
+    let Some(app) = desktop::app_by_id(&apps, id) else {
+        eprintln!("yeet: no app with desktop id '{id}'");
+        std::process::exit(EXIT_ERROR);
+    };
+    if !launch_app(app, &config, None, record_history) {
+        std::process::exit(EXIT_ERROR);
+    }
+}
+
+/// `--launch-interface`: the one app implementing `interface`, launched
+/// like `--launch`, or the launcher listing just those apps when there are
+/// several.
+fn launch_interface(interface: &str, record_history: bool) {
+    let mut config = load_config();
+    if !record_history {
+        config.search.use_history = false;
+    }
+    let apps = discover_apps(&config);
+    match desktop::implementing(&apps, interface).as_slice() {
+        [] => {
+            eprintln!("yeet: no app implements '{interface}'");
+            std::process::exit(EXIT_ERROR);
+        }
+        [app] => {
+            if !launch_app(app, &config, None, record_history) {
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+        several => {
+            let apps = several.iter().map(|&app| app.clone()).collect();
+            show_launcher(config, apps, Accept::Launch, None, record_history, false);
+        }
+    }
+}
+
+/// `--recent`: the launcher over the desktop's recently used files, newest
+/// first, each opened with `xdg-open`.
+fn run_recent(config: Config, accept: Accept, timing: Option<Timing>, record_history: bool) {
+    let Some(path) = recent::xbel_path() else {
+        eprintln!("yeet: no data directory to read recent files from");
+        std::process::exit(EXIT_ERROR);
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            eprintln!("yeet: failed to read {}: {e}", path.display());
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    let apps: Vec<App> = recent::parse_xbel(&content)
+        .iter()
+        .map(App::for_recent_file)
+        .collect();
+    if apps.is_empty() {
+        eprintln!("yeet: no recently used files in {}", path.display());
+        std::process::exit(EXIT_ERROR);
+    }
+    show_launcher(config, apps, accept, timing, record_history, false);
+}
+
+fn forget_last() {
+    let config = load_config();
+    match history::remove_last(config.data_dir().as_deref(), config.general.history_format) {
+        Ok(Some(name)) => println!("Removed last launch ({name}) from history"),
+        Ok(None) => println!("History is empty"),
+        Err(e) => {
+            eprintln!("yeet: failed to update history: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+fn forget_app(name: &str) {
+    let config = load_config();
+    // Launches are recorded by desktop id, or by name for apps without one.
+    let apps = discover_apps(&config);
+    let mut keys: Vec<&str> = apps
+        .iter()
+        .filter(|app| app.name == name)
+        .map(App::identity)
+        .collect();
+    keys.push(name);
+    match history::remove_app(
+        &keys,
+        config.data_dir().as_deref(),
+        config.general.history_format,
+    ) {
+        Ok(0) => println!("No launches of {name} in history"),
+        Ok(count) => println!("Removed {count} launch(es) of {name} from history"),
+        Err(e) => {
+            eprintln!("yeet: failed to update history: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+fn install_theme(name: &str) {
+    let Some(config_dir) = Config::config_dir() else {
+        eprintln!("yeet: no config directory to install into");
+        std::process::exit(EXIT_ERROR);
+    };
+    match theme::install(&config_dir, name) {
+        Ok(dir) => {
+            println!("Installed theme {name} to {}", dir.display());
+            println!("Enable it with `theme = \"{name}\"` under [appearance]");
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let names: Vec<&str> = theme::bundled_names().collect();
+            eprintln!(
+                "yeet: unknown theme '{name}' (available: {})",
+                names.join(", ")
+            );
+            std::process::exit(EXIT_ERROR);
+        }
+        Err(e) => {
+            eprintln!("yeet: failed to install theme {name}: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+}
+
 fn gtk_app() -> Application {
     // NON_UNIQUE: each invocation gets its own window and, in dmenu mode,
     // its own stdin/stdout instead of activating an existing instance.
@@ -66,25 +571,162 @@ fn gtk_app() -> Application {
         .build()
 }
 
-fn run_launcher(config: Config) {
+fn run_launcher(
+    mut config: Config,
+    accept: Accept,
+    timing: Option<Timing>,
+    record_history: bool,
+    theme_preview: bool,
+) {
+    if matches!(accept, Accept::Print(..)) {
+        // One selection, printed once; nothing launched, nothing recorded.
+        // A dry run follows `stay_open`, so several entries can be checked.
+        config.general.stay_open = false;
+    }
+    let discovery = std::time::Instant::now();
     let apps = discover_apps(&config);
+    if timing.is_some() {
+        Phase::new(
+            "discovery",
+            discovery.elapsed(),
+            Some(format!("{} apps", apps.len())),
+        )
+        .report();
+    }
+    show_launcher(config, apps, accept, timing, record_history, theme_preview);
+}
+
+/// The launcher window over `apps`; exits with the session's outcome.
+fn show_launcher(
+    config: Config,
+    apps: Vec<App>,
+    accept: Accept,
+    timing: Option<Timing>,
+    record_history: bool,
+    theme_preview: bool,
+) {
+    let config = Rc::new(config);
+    let outcome = Rc::new(Cell::new(Outcome::Cancelled));
     let app = gtk_app();
+    quit_on_interrupt(&app, outcome.clone());
 
-    app.connect_activate(move |app| {
-        let terminal = config.general.terminal.clone();
-        let on_select: Rc<dyn Fn(&App)> = Rc::new(move |app| launch_app(app, &terminal));
-        ui::build_ui(app, &config, apps.clone(), on_select);
-    });
+    let on_select: Rc<dyn Fn(&App)> = {
+        let launch_config = config.clone();
+        let outcome_flag = outcome.clone();
+        let tokens = RefCell::new(ui::ActivationTokens::default());
+        Rc::new(move |app| {
+            let launched = match accept.output(app, &launch_config) {
+                Some(Ok(line)) => {
+                    println!("{line}");
+                    true
+                }
+                Some(Err(e)) => {
+                    eprintln!("Failed to launch {}: {}", app.name, e);
+                    false
+                }
+                None => {
+                    let token = launch_config
+                        .general
+                        .focus_on_launch
+                        .then(|| tokens.borrow_mut().next())
+                        .flatten();
+                    launch_app(app, &launch_config, token.as_deref(), record_history)
+                }
+            };
+            outcome_flag.set(if launched {
+                Outcome::Selected
+            } else {
+                Outcome::Failed
+            });
+        })
+    };
+
+    // Replaced by a new window on a control socket `reload`.
+    let window: Rc<RefCell<Option<ui::LauncherWindow>>> = Rc::default();
+    let control = if config.general.control_socket {
+        listen_for_commands(&app, &config, &on_select, &window)
+    } else {
+        None
+    };
+
+    {
+        let window = window.clone();
+        app.connect_activate(move |app| {
+            let built = ui::build_ui(app, &config, apps.clone(), on_select.clone(), timing);
+            if theme_preview {
+                built.preview_themes(
+                    theme::previews(Config::config_dir().as_deref()),
+                    config.appearance.theme.as_deref(),
+                );
+            }
+            *window.borrow_mut() = Some(built);
+        });
+    }
 
     // we don't use GTK's arg parsing
     app.run_with_args::<&str>(&[]);
+    drop(control);
+    std::process::exit(outcome.get().exit_code());
 }
 
-fn run_dmenu(mut config: Config) {
-    // dmenu items are arbitrary lines: show all of them up front and keep
-    // launch history out of both ranking and recording.
+/// Serves `general.control_socket` for the launcher window in `window`;
+/// `None` (with a warning) when the socket can't be set up.
+fn listen_for_commands(
+    app: &Application,
+    config: &Rc<Config>,
+    on_select: &Rc<dyn Fn(&App)>,
+    window: &Rc<RefCell<Option<ui::LauncherWindow>>>,
+) -> Option<ControlSocket> {
+    let app = app.clone();
+    let config = config.clone();
+    let on_select = on_select.clone();
+    let window = window.clone();
+    let on_command = move |command| match command {
+        Command::Quit => app.quit(),
+        Command::Toggle => {
+            if let Some(window) = &*window.borrow() {
+                window.toggle();
+            }
+        }
+        Command::Query(query) => {
+            if let Some(window) = &*window.borrow() {
+                window.set_query(&query);
+            }
+        }
+        Command::Reload => {
+            let apps = discover_apps(&config);
+            let rebuilt = ui::build_ui(&app, &config, apps, on_select.clone(), None);
+            // Closing the old window only once the new one exists keeps the
+            // application from quitting in between.
+            if let Some(old) = window.replace(Some(rebuilt)) {
+                old.close();
+            }
+        }
+    };
+
+    let Some(path) = control::socket_path() else {
+        eprintln!("yeet: control socket disabled: no runtime dir ($XDG_RUNTIME_DIR)");
+        return None;
+    };
+    match ControlSocket::bind(path, on_command) {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            eprintln!("yeet: control socket disabled: {e}");
+            None
+        }
+    }
+}
+
+fn run_dmenu(mut config: Config, format: Option<OutputFormat>, timing: Option<Timing>) {
+    // dmenu items are arbitrary lines: show all of them up front, print a
+    // single selection, keep launch history out of both ranking and
+    // recording, and never offer anything that wasn't piped in.
     config.general.initial_results = 0;
+    config.general.stay_open = false;
+    config.general.multiline_paste = MultilinePaste::FirstLine;
     config.search.use_history = false;
+    config.search.no_match_action = NoMatchAction::Empty;
+    config.apps.require_icon = false;
 
     let items: Vec<App> = std::io::stdin()
         .lock()
@@ -96,22 +738,287 @@ fn run_dmenu(mut config: Config) {
 
     if items.is_empty() {
         eprintln!("yeet: --dmenu expects items on stdin");
-        std::process::exit(1);
+        std::process::exit(Outcome::Cancelled.exit_code());
     }
 
-    let selected = Rc::new(Cell::new(false));
+    let outcome = Rc::new(Cell::new(Outcome::Cancelled));
+    let selected: Rc<RefCell<Option<App>>> = Rc::new(RefCell::new(None));
     let app = gtk_app();
+    quit_on_interrupt(&app, outcome.clone());
 
-    let selected_flag = selected.clone();
+    let selection = selected.clone();
     app.connect_activate(move |app| {
-        let selected_flag = selected_flag.clone();
+        let selection = selection.clone();
         let on_select: Rc<dyn Fn(&App)> = Rc::new(move |item| {
-            println!("{}", item.name);
-            selected_flag.set(true);
+            *selection.borrow_mut() = Some(item.clone());
         });
-        ui::build_ui(app, &config, items.clone(), on_select);
+        ui::build_ui(app, &config, items.clone(), on_select, timing);
     });
 
     app.run_with_args::<&str>(&[]);
-    std::process::exit(if selected.get() { 0 } else { 1 });
+    if outcome.get() == Outcome::Interrupted {
+        std::process::exit(Outcome::Interrupted.exit_code());
+    }
+    let (line, outcome) = dmenu_result(selected.borrow().as_ref(), format.as_ref());
+    if let Some(line) = line {
+        println!("{line}");
+    }
+    std::process::exit(outcome.exit_code());
+}
+
+/// What a dmenu session prints and how it ends, given the item picked or
+/// `None` when the window was closed without picking one.
+fn dmenu_result(
+    selected: Option<&App>,
+    format: Option<&OutputFormat>,
+) -> (Option<String>, Outcome) {
+    match selected {
+        Some(item) => {
+            let line = match format {
+                Some(format) => format.render(item),
+                None => item.name.clone(),
+            };
+            (Some(line), Outcome::Selected)
+        }
+        None => (None, Outcome::Cancelled),
+    }
+}
+
+/// Closes the window on Ctrl+C (SIGINT) in the terminal yeet was started
+/// from, so the session ends as `Outcome::Interrupted` instead of being
+/// killed mid-frame.
+fn quit_on_interrupt(app: &Application, outcome: Rc<Cell<Outcome>>) {
+    app.connect_startup(move |app| {
+        let app = app.clone();
+        let outcome = outcome.clone();
+        gtk4::glib::unix_signal_add_local(libc::SIGINT, move || {
+            outcome.set(Outcome::Interrupted);
+            app.quit();
+            gtk4::glib::ControlFlow::Break
+        });
+    });
+}
+
+/// How a window session ended; each maps to one exit status, so scripts
+/// can tell a selection from a cancel from a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Selected,
+    Cancelled,
+    /// The chosen app couldn't be started.
+    Failed,
+    Interrupted,
+}
+
+impl Outcome {
+    fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Selected => EXIT_OK,
+            Outcome::Cancelled => EXIT_CANCELLED,
+            Outcome::Failed => EXIT_ERROR,
+            Outcome::Interrupted => EXIT_INTERRUPTED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_exits_zero() {
+        assert_eq!(Outcome::Selected.exit_code(), 0);
+    }
+
+    #[test]
+    fn cancel_exits_one() {
+        assert_eq!(Outcome::Cancelled.exit_code(), 1);
+    }
+
+    #[test]
+    fn dmenu_prints_the_pick_or_cancels() {
+        let item = App::plain("two words".into());
+        let (line, outcome) = dmenu_result(Some(&item), None);
+        assert_eq!(line.as_deref(), Some("two words"));
+        assert_eq!(outcome.exit_code(), EXIT_OK);
+
+        let format = OutputFormat::parse("[{name}]").unwrap();
+        let (line, _) = dmenu_result(Some(&item), Some(&format));
+        assert_eq!(line.as_deref(), Some("[two words]"));
+
+        let (line, outcome) = dmenu_result(None, Some(&format));
+        assert_eq!(line, None);
+        assert_eq!(outcome.exit_code(), EXIT_CANCELLED);
+    }
+
+    #[test]
+    fn failure_and_interrupt_have_their_own_codes() {
+        assert_eq!(Outcome::Failed.exit_code(), 2);
+        assert_eq!(Outcome::Interrupted.exit_code(), 130);
+    }
+
+    #[test]
+    fn score_output_lists_scores_and_verdicts() {
+        let apps: Vec<App> = ["Firefox", "Files", "Zed"]
+            .iter()
+            .map(|name| App::plain(name.to_string()))
+            .collect();
+        let report = [
+            ScoredText {
+                index: 0,
+                score: 140,
+                passed: true,
+            },
+            ScoredText {
+                index: 1,
+                score: 35,
+                passed: false,
+            },
+        ];
+
+        assert_eq!(
+            format_scores(&apps, &report),
+            "   140  pass  Firefox\n    35  fail  Files\n(1 more did not match)\n"
+        );
+    }
+
+    #[test]
+    fn validate_config_reports_errors_and_problems() {
+        let dir = std::env::temp_dir().join("yeet_test_validate_config");
+        std::fs::create_dir_all(&dir).unwrap();
+        let check = |name: &str, toml: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, toml).unwrap();
+            validate_config(Some(&path))
+        };
+
+        assert_eq!(
+            check("valid.toml", "[general]\nmax_results = 10\n"),
+            (EXIT_OK, "config ok".to_string())
+        );
+
+        let (status, report) = check("typo.toml", "[general]\nmax_results = \"ten\"\n");
+        assert_eq!(status, EXIT_ERROR);
+        assert!(
+            report.starts_with("yeet: failed to load config at "),
+            "{report}"
+        );
+        assert!(report.contains("typo.toml"), "{report}");
+
+        let (status, report) = check("unknown.toml", "[general]\nmax_result = 10\n");
+        assert_eq!(status, EXIT_ERROR);
+        assert!(
+            report.ends_with("unknown.toml: unknown key general.max_result"),
+            "{report}"
+        );
+
+        let (status, report) = check(
+            "clamped.toml",
+            "[general]\nmax_results = 4\ninitial_results = 6\n",
+        );
+        assert_eq!(status, EXIT_ERROR);
+        assert!(
+            report.starts_with("yeet: general.initial_results (6) is more than"),
+            "{report}"
+        );
+
+        let (status, report) = validate_config(Some(&dir.join("missing.toml")));
+        assert_eq!(status, EXIT_ERROR);
+        assert!(report.ends_with("no such file"), "{report}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn accept_action_follows_the_flags() {
+        assert_eq!(Accept::new(None, None, false), Accept::Launch);
+        assert_eq!(Accept::new(None, None, true), Accept::DryRun);
+        assert_eq!(
+            Accept::new(Some(PrintField::Id), None, false),
+            Accept::Print(PrintField::Id, None)
+        );
+        let format = OutputFormat::parse("{name}").unwrap();
+        assert_eq!(
+            Accept::new(None, Some(format.clone()), false),
+            Accept::Print(PrintField::Exec, Some(format))
+        );
+    }
+
+    #[test]
+    fn print_only_prints_a_discovered_apps_command_and_id() {
+        let dir = std::env::temp_dir().join("yeet_test_print_only");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("yeet-print-fixture.desktop"),
+            "[Desktop Entry]\nType=Application\nName=Print Fixture\n\
+             Exec=/opt/fixture/bin/tool --open %U \"two words\"\n",
+        )
+        .unwrap();
+        let config: Config = toml::from_str(&format!(
+            "[apps]\nextra_dirs = [{:?}]",
+            dir.display().to_string()
+        ))
+        .unwrap();
+
+        let apps = discover_apps(&config);
+        let app = desktop::app_by_id(&apps, "yeet-print-fixture").expect("fixture discovered");
+        let printed = |accept: Accept| accept.output(app, &config).unwrap().unwrap();
+        let command = "/opt/fixture/bin/tool --open 'two words'";
+        assert_eq!(
+            printed(Accept::new(Some(PrintField::Exec), None, false)),
+            command
+        );
+        assert_eq!(
+            printed(Accept::new(Some(PrintField::Id), None, false)),
+            "yeet-print-fixture"
+        );
+        let format = OutputFormat::parse("{name}\\t{id}").unwrap();
+        assert_eq!(
+            printed(Accept::new(None, Some(format), false)),
+            "Print Fixture\tyeet-print-fixture"
+        );
+        assert!(printed(Accept::DryRun).ends_with(command));
+        assert!(Accept::Launch.output(app, &config).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn print_id_falls_back_to_name() {
+        let app = App::plain("My Script".into());
+        assert_eq!(PrintField::Id.of(&app), "My Script");
+        assert_eq!(PrintField::Exec.of(&app), "My Script");
+    }
+
+    fn formatted(template: &str, app: &App) -> String {
+        OutputFormat::parse(template).unwrap().render(app)
+    }
+
+    #[test]
+    fn format_substitutes_placeholders() {
+        let mut app = App::plain("Web Browser".into());
+        app.desktop_id = Some("org.mozilla.firefox".into());
+        app.icon = Some("firefox".into());
+
+        assert_eq!(
+            formatted("{name}\\t{id}\\t{icon}", &app),
+            "Web Browser\torg.mozilla.firefox\tfirefox"
+        );
+        assert_eq!(formatted("{id}", &App::plain("x".into())), "x");
+        assert_eq!(formatted("[{description}]", &app), "[]");
+        assert_eq!(formatted("[{source}]", &app), "[]");
+        assert_eq!(
+            formatted("{{{name}}} \\\\ \\q", &app),
+            "{Web Browser} \\ \\q"
+        );
+    }
+
+    #[test]
+    fn format_rejects_unknown_placeholders() {
+        let err = OutputFormat::parse("{name} {exe}").unwrap_err();
+        assert!(err.starts_with("unknown placeholder {exe}"), "{err}");
+        assert!(OutputFormat::parse("{name").is_err());
+        assert!(OutputFormat::parse("name}").is_err());
+    }
 }
@@ -0,0 +1,341 @@
+//! `--recent`: the desktop's recently used files, as GTK and KDE apps record
+//! them in `~/.local/share/recently-used.xbel`.
+//!
+//! Only the bits yeet lists are read: each `<bookmark>`'s `href`, its
+//! timestamps and the `<mime:mime-type>` inside it. Anything else in the
+//! file, including bookmarks without an `href`, is skipped.
+
+use std::path::PathBuf;
+
+const XBEL_FILE: &str = "recently-used.xbel";
+
+/// One `<bookmark>` from the XBEL file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentFile {
+    /// As written in `href`, e.g. `file:///home/me/Notes%20old.txt`.
+    pub uri: String,
+    /// The newest of `modified`, `visited` and `added`, as an ISO 8601
+    /// timestamp (`2024-05-01T09:30:00.123456Z`); empty if none is set.
+    pub used: String,
+    pub mime_type: Option<String>,
+}
+
+impl RecentFile {
+    /// The local path of a `file://` URI, percent-decoded.
+    pub fn path(&self) -> Option<PathBuf> {
+        let rest = self.uri.strip_prefix("file://")?;
+        // `file://host/path`: only the path part names the file.
+        let path = &rest[rest.find('/')?..];
+        Some(PathBuf::from(percent_decode(path)))
+    }
+
+    /// The name to list it under: the file name, or the last part of a
+    /// non-file URI.
+    pub fn name(&self) -> String {
+        match self.path() {
+            Some(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            None => {
+                let trimmed = self.uri.trim_end_matches('/');
+                let last = trimmed.rsplit('/').next().unwrap_or(trimmed);
+                percent_decode(last)
+            }
+        }
+    }
+
+    /// A `file://` URI whose file is gone (moved, deleted, on an unmounted
+    /// drive). Other URIs can't be checked and count as present.
+    pub fn is_missing(&self) -> bool {
+        self.path().is_some_and(|path| !path.exists())
+    }
+
+    /// Icon name for the MIME type, e.g. `application-pdf`.
+    pub fn icon(&self) -> Option<String> {
+        self.mime_type.as_ref().map(|mime| mime.replace('/', "-"))
+    }
+}
+
+/// `recently-used.xbel` in the XDG data dir.
+pub fn xbel_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(XBEL_FILE))
+}
+
+/// The bookmarks in `content`, most recently used first.
+pub fn parse_xbel(content: &str) -> Vec<RecentFile> {
+    let mut files = Vec::new();
+    let mut rest = content;
+    while let Some(start) = find_tag(rest, "bookmark") {
+        let tag_and_body = &rest[start..];
+        let Some(tag_end) = tag_and_body.find('>') else {
+            break;
+        };
+        let tag = &tag_and_body[..tag_end];
+        let body_and_after = &tag_and_body[tag_end + 1..];
+        // `<bookmark .../>` has no body.
+        let (body, after) = if tag.ends_with('/') {
+            ("", body_and_after)
+        } else {
+            match body_and_after.find("</bookmark>") {
+                Some(end) => (&body_and_after[..end], &body_and_after[end..]),
+                None => (body_and_after, ""),
+            }
+        };
+        rest = after;
+
+        let Some(uri) = attribute(tag, "href") else {
+            continue;
+        };
+        let used = ["modified", "visited", "added"]
+            .iter()
+            .filter_map(|name| attribute(tag, name))
+            .max_by(|a, b| compare_timestamps(a, b))
+            .unwrap_or_default();
+        let mime_type = find_tag(body, "mime:mime-type")
+            .and_then(|at| {
+                let tag = &body[at..];
+                attribute(&tag[..tag.find('>').unwrap_or(tag.len())], "type")
+            })
+            .filter(|mime| !mime.is_empty());
+        files.push(RecentFile {
+            uri,
+            used,
+            mime_type,
+        });
+    }
+    files.sort_by(|a, b| compare_timestamps(&b.used, &a.used));
+    files
+}
+
+/// Where the next `<name ...>` (not `<name:other>`) opens in `text`.
+fn find_tag(text: &str, name: &str) -> Option<usize> {
+    let open = format!("<{name}");
+    let mut from = 0;
+    while let Some(at) = text[from..].find(&open) {
+        let start = from + at;
+        let next = text[start + open.len()..].chars().next();
+        if next.is_some_and(|c| c.is_whitespace() || c == '>' || c == '/') {
+            return Some(start);
+        }
+        from = start + open.len();
+    }
+    None
+}
+
+/// The unescaped value of attribute `name` in an opening tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut from = 0;
+    while let Some(at) = tag[from..].find(name) {
+        let start = from + at;
+        from = start + name.len();
+        let preceded = tag[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace);
+        let after = tag[from..].trim_start();
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        if !preceded {
+            continue;
+        }
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let value = &value[1..];
+        let end = value.find(quote)?;
+        return Some(unescape_xml(&value[..end]));
+    }
+    None
+}
+
+/// Orders ISO 8601 UTC timestamps by their whole seconds, then their
+/// fraction, whether or not they have one.
+fn compare_timestamps(a: &str, b: &str) -> std::cmp::Ordering {
+    let split = |t: &str| {
+        let t = t.trim_end_matches('Z');
+        let (seconds, fraction) = t.split_once('.').unwrap_or((t, ""));
+        (seconds.to_string(), fraction.to_string())
+    };
+    let (a_seconds, a_fraction) = split(a);
+    let (b_seconds, b_fraction) = split(b);
+    a_seconds
+        .cmp(&b_seconds)
+        .then_with(|| a_fraction.cmp(&b_fraction))
+}
+
+/// Replaces the predefined XML entities and character references.
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decodes `%XX` escapes; stray `%`s are kept as they are.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0"
+      xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks"
+      xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info"
+>
+  <bookmark href="file:///home/me/Documents/Q3%20report.pdf" added="2024-05-01T09:00:00.000000Z" modified="2024-05-03T17:45:10.250000Z" visited="2024-05-01T09:00:00.000000Z">
+    <info>
+      <metadata owner="http://freedesktop.org">
+        <mime:mime-type type="application/pdf"/>
+        <bookmark:applications>
+          <bookmark:application name="Papers" exec="&apos;papers %u&apos;" modified="2024-05-03T17:45:10.250000Z" count="3"/>
+        </bookmark:applications>
+      </metadata>
+    </info>
+  </bookmark>
+  <bookmark href="file:///home/me/notes/Tom%20&amp;%20Jerry.md" added="2024-05-04T08:00:00Z" modified="2024-05-04T08:00:00Z" visited="2024-05-04T08:00:00Z">
+    <info>
+      <metadata owner="http://freedesktop.org">
+        <mime:mime-type type="text/markdown"/>
+      </metadata>
+    </info>
+  </bookmark>
+  <bookmark href="sftp://server/srv/log.txt" added="2024-04-01T12:00:00Z" modified="2024-04-01T12:00:00Z" visited="2024-04-01T12:00:00Z">
+  </bookmark>
+  <bookmark added="2024-06-01T12:00:00Z"/>
+</xbel>
+"#;
+
+    #[test]
+    fn parses_bookmarks_newest_first() {
+        let files = parse_xbel(SAMPLE);
+        let uris: Vec<&str> = files.iter().map(|file| file.uri.as_str()).collect();
+        assert_eq!(
+            uris,
+            [
+                "file:///home/me/notes/Tom%20&%20Jerry.md",
+                "file:///home/me/Documents/Q3%20report.pdf",
+                "sftp://server/srv/log.txt",
+            ]
+        );
+
+        let report = &files[1];
+        assert_eq!(report.used, "2024-05-03T17:45:10.250000Z");
+        assert_eq!(report.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(report.icon().as_deref(), Some("application-pdf"));
+        assert_eq!(files[2].mime_type, None);
+    }
+
+    #[test]
+    fn file_uris_decode_to_paths_and_names() {
+        let files = parse_xbel(SAMPLE);
+        assert_eq!(
+            files[0].path(),
+            Some(PathBuf::from("/home/me/notes/Tom & Jerry.md"))
+        );
+        assert_eq!(files[0].name(), "Tom & Jerry.md");
+        assert_eq!(files[1].name(), "Q3 report.pdf");
+
+        // Not a local file: named after the URI, never missing.
+        assert_eq!(files[2].path(), None);
+        assert_eq!(files[2].name(), "log.txt");
+        assert!(!files[2].is_missing());
+
+        let on_host = RecentFile {
+            uri: "file://laptop/tmp/a%2Fb%zz".to_string(),
+            used: String::new(),
+            mime_type: None,
+        };
+        assert_eq!(on_host.path(), Some(PathBuf::from("/tmp/a/b%zz")));
+    }
+
+    #[test]
+    fn files_that_are_gone_are_missing() {
+        let dir = std::env::temp_dir().join("yeet_test_recent_missing");
+        std::fs::create_dir_all(&dir).unwrap();
+        let present = dir.join("here now.txt");
+        std::fs::write(&present, "").unwrap();
+
+        let file = |name: &str| RecentFile {
+            uri: format!("file://{}/{name}", dir.display()),
+            used: String::new(),
+            mime_type: None,
+        };
+        assert!(!file("here%20now.txt").is_missing());
+        assert!(file("gone.txt").is_missing());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn timestamps_compare_with_or_without_fractions() {
+        use std::cmp::Ordering;
+        assert_eq!(
+            compare_timestamps("2024-05-04T08:00:00Z", "2024-05-04T08:00:00.5Z"),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_timestamps("2024-05-04T08:00:01Z", "2024-05-04T08:00:00.999Z"),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn unescapes_entities_and_character_references() {
+        assert_eq!(
+            unescape_xml("a &amp; b &lt;&#62; &#x263A;"),
+            "a & b <> \u{263a}"
+        );
+        assert_eq!(unescape_xml("AT&T &bogus; &"), "AT&T &bogus; &");
+    }
+}
@@ -0,0 +1,903 @@
+use crate::config::{MatcherKind, SearchConfig, TriggerMode};
+use fuzzy_matcher::clangd::ClangdMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const SIMPLE_PREFIX_SCORE: i64 = 100;
+const SIMPLE_WORD_START_SCORE: i64 = 80;
+const SIMPLE_SUBSTRING_SCORE: i64 = 60;
+/// `favorite_boost` of the first favorite; the one at position `n` gets
+/// `FAVORITE_BOOST / (n + 1)`.
+const FAVORITE_BOOST: i64 = 60;
+
+/// Scorer selected by `search.matcher`. Every backend's scores are rescaled
+/// onto skim's range (a query matched against itself scores the same
+/// everywhere), so `min_score` and `score_threshold` keep their meaning.
+pub struct Matcher {
+    kind: MatcherKind,
+    skim: SkimMatcherV2,
+    clangd: ClangdMatcher,
+    /// The last query rescaled for; a search scores one query against every
+    /// app.
+    perfect: RefCell<Option<PerfectScores>>,
+}
+
+/// What `query` scores matched against itself, on the selected backend and
+/// on skim.
+struct PerfectScores {
+    query: String,
+    own: Option<i64>,
+    skim: Option<i64>,
+}
+
+impl Matcher {
+    pub fn new(kind: MatcherKind) -> Self {
+        Self {
+            kind,
+            skim: SkimMatcherV2::default(),
+            clangd: ClangdMatcher::default(),
+            perfect: RefCell::new(None),
+        }
+    }
+
+    pub fn fuzzy_match(&self, text: &str, query: &str) -> Option<i64> {
+        match self.kind {
+            MatcherKind::Skim => self.skim.fuzzy_match(text, query),
+            MatcherKind::Clangd => {
+                let score = self.clangd.fuzzy_match(text, query)?;
+                Some(self.rescale(score, query))
+            }
+            MatcherKind::Simple => {
+                let score = simple_score(text, query)?;
+                Some(self.rescale(score, query))
+            }
+        }
+    }
+
+    fn rescale(&self, score: i64, query: &str) -> i64 {
+        let mut cached = self.perfect.borrow_mut();
+        let perfect = match cached.as_ref() {
+            Some(perfect) if perfect.query == query => perfect,
+            _ => cached.insert(self.perfect_scores(query)),
+        };
+        let own = perfect.own.unwrap_or(score);
+        let skim = perfect.skim.unwrap_or(own);
+        score * skim / own.max(1)
+    }
+
+    fn perfect_scores(&self, query: &str) -> PerfectScores {
+        let skim = self.skim.fuzzy_match(query, query);
+        let own = match self.kind {
+            MatcherKind::Skim => skim,
+            MatcherKind::Clangd => self.clangd.fuzzy_match(query, query),
+            MatcherKind::Simple => Some(SIMPLE_PREFIX_SCORE),
+        };
+        PerfectScores {
+            query: query.to_string(),
+            own,
+            skim,
+        }
+    }
+}
+
+/// Which of an app's texts a query matched, for screen readers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchedOn {
+    Name,
+    Keyword(String),
+}
+
+/// An app's searchable text, prepared once per list. Each word counts once:
+/// a keyword that only repeats words of the name or earlier keywords
+/// (ignoring case) is left out, so a verbose entry doesn't match better
+/// for saying "browser" three times.
+pub struct SearchText {
+    name: String,
+    name_lower: String,
+    keywords: Vec<String>,
+    combined: String,
+    combined_lower: String,
+    /// Position in `apps.favorites`, for `search.positional_favorites`.
+    favorite: Option<usize>,
+}
+
+impl SearchText {
+    pub fn new(name: &str, keywords: &[String]) -> Self {
+        let mut seen: HashSet<String> = name.split_whitespace().map(str::to_lowercase).collect();
+        let mut combined = name.to_string();
+        let mut kept = Vec::new();
+        for kw in keywords {
+            let mut new_words = kw
+                .split_whitespace()
+                .filter(|word| seen.insert(word.to_lowercase()))
+                .peekable();
+            if new_words.peek().is_none() {
+                continue;
+            }
+            for word in new_words {
+                combined.push(' ');
+                combined.push_str(word);
+            }
+            kept.push(kw.clone());
+        }
+
+        Self {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            keywords: kept,
+            combined_lower: combined.to_lowercase(),
+            combined,
+            favorite: None,
+        }
+    }
+
+    /// Marks the text as the app at `position` in `apps.favorites`.
+    pub fn favorite_at(mut self, position: Option<usize>) -> Self {
+        self.favorite = position;
+        self
+    }
+
+    /// Substring test over name and keywords; `query_lower` must already be
+    /// lowercase.
+    pub fn contains(&self, query_lower: &str) -> bool {
+        self.combined_lower.contains(query_lower)
+    }
+
+    pub fn name_starts_with(&self, query_lower: &str) -> bool {
+        self.name_lower.starts_with(query_lower)
+    }
+
+    pub fn name_is(&self, query_lower: &str) -> bool {
+        self.name_lower == query_lower
+    }
+
+    /// Best of the name score and the keyword score scaled by
+    /// `keyword_weight`, so a name hit outranks an equally good keyword hit.
+    /// A query that only matches across name and keywords together (e.g.
+    /// "fire web") counts as a keyword hit.
+    pub fn score(&self, matcher: &Matcher, query: &str, keyword_weight: f64) -> Option<i64> {
+        let name_score = matcher.fuzzy_match(&self.name, query);
+        let keyword_score = self
+            .keywords
+            .iter()
+            .filter_map(|kw| matcher.fuzzy_match(kw, query))
+            .max()
+            .or_else(|| {
+                name_score
+                    .is_none()
+                    .then(|| matcher.fuzzy_match(&self.combined, query))
+                    .flatten()
+            })
+            .map(|score| (score as f64 * keyword_weight.clamp(0.0, 1.0)) as i64);
+
+        name_score.max(keyword_score)
+    }
+
+    /// Whether `query` matched the name or, failing that or matching it
+    /// better, which keyword. Substring hits count even when the matcher
+    /// rejects them, as in the launcher. `None` when only the name and
+    /// keywords together match.
+    pub fn matched_on(&self, matcher: &Matcher, query: &str) -> Option<MatchedOn> {
+        let query_lower = query.to_lowercase();
+        let score = |text: &str| {
+            matcher
+                .fuzzy_match(text, query)
+                .or_else(|| text.to_lowercase().contains(&query_lower).then_some(0))
+        };
+
+        let name_score = score(&self.name);
+        let keyword = self
+            .keywords
+            .iter()
+            .filter_map(|kw| score(kw).map(|s| (s, kw)))
+            .rev()
+            .max_by_key(|&(s, _)| s);
+        match (name_score, keyword) {
+            (Some(name), Some((kw, keyword))) if kw > name => {
+                Some(MatchedOn::Keyword(keyword.clone()))
+            }
+            (Some(_), _) => Some(MatchedOn::Name),
+            (None, Some((_, keyword))) => Some(MatchedOn::Keyword(keyword.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The `[triggers]` mode `query` starts with, and the query without its
+/// prefix. The longest matching prefix wins, so `>>` can mean something
+/// other than `>`; empty prefixes are ignored.
+pub fn detect_trigger<'a>(
+    triggers: &BTreeMap<String, TriggerMode>,
+    query: &'a str,
+) -> Option<(TriggerMode, &'a str)> {
+    triggers
+        .iter()
+        .filter(|(prefix, _)| !prefix.is_empty())
+        .filter_map(|(prefix, &mode)| {
+            let rest = query.strip_prefix(prefix.as_str())?;
+            Some((prefix.len(), mode, rest.trim_start()))
+        })
+        .max_by_key(|&(len, _, _)| len)
+        .map(|(_, mode, rest)| (mode, rest))
+}
+
+/// Search results for `query`, best first, as `(index into texts, score)`.
+///
+/// Queries of 2+ characters list only substring matches of the name or
+/// keywords when there are any; otherwise fuzzy matches, cut off by
+/// `fuzzy_cutoff`. Scores include a boost for apps launched recently (per
+/// `history`, launch times by app name, at `now`). With
+/// `search.exact_to_top` an app named exactly like the query (ignoring
+/// case) comes first, then with `search.prefer_prefix` names starting with
+/// the query. Ties go to the shorter name with `search.prefer_shorter`
+/// ("Code" before "Code - OSS"), then to the more recent launch, then to
+/// the earlier text, so the same input always ranks the same way. An
+/// empty query has no results; the launcher shows its initial list then.
+pub fn rank(
+    texts: &[SearchText],
+    matcher: &Matcher,
+    query: &str,
+    search: &SearchConfig,
+    history: &HashMap<String, u64>,
+    now: u64,
+) -> Vec<(usize, i64)> {
+    candidates(texts, matcher, query, search, history, now)
+        .into_iter()
+        .filter(|r| r.listed)
+        .map(|r| (r.index, r.score))
+        .collect()
+}
+
+/// Every text `query` matches, in `rank`'s order, noting which of them
+/// `rank` lists; the rest fall to the substring rule or the fuzzy cutoff.
+fn candidates(
+    texts: &[SearchText],
+    matcher: &Matcher,
+    query: &str,
+    search: &SearchConfig,
+    history: &HashMap<String, u64>,
+    now: u64,
+) -> Vec<Ranked> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let query_lower = query.to_lowercase();
+    let long_query = query.chars().count() >= 2;
+    let substring_only = long_query && texts.iter().any(|t| t.contains(&query_lower));
+
+    let mut scored: Vec<Ranked> = texts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, text)| {
+            let score = text.score(matcher, query, search.keyword_weight);
+            let substring = text.contains(&query_lower);
+            // Substring hits are listed even when the matcher rejects them.
+            let score = if substring_only && substring {
+                score.unwrap_or(0)
+            } else {
+                score?
+            };
+            let boost = recency_boost(history, &text.name, now) + favorite_boost(text, search);
+            Some(Ranked {
+                index,
+                score: score + boost,
+                exact: search.exact_to_top && text.name_is(&query_lower),
+                prefix: search.prefer_prefix && text.name_starts_with(&query_lower),
+                name_len: if search.prefer_shorter {
+                    text.name.chars().count()
+                } else {
+                    0
+                },
+                boost,
+                listed: !substring_only || substring,
+            })
+        })
+        .collect();
+    scored.sort_by(|a, b| {
+        b.exact
+            .cmp(&a.exact)
+            .then_with(|| b.prefix.cmp(&a.prefix))
+            .then_with(|| b.score.cmp(&a.score))
+            .then_with(|| a.name_len.cmp(&b.name_len))
+            .then_with(|| b.boost.cmp(&a.boost))
+            .then_with(|| a.index.cmp(&b.index))
+    });
+
+    if long_query && !substring_only {
+        let best_score = scored.iter().map(|r| r.score).max().unwrap_or(0);
+        let cutoff = fuzzy_cutoff(best_score, search.min_score, search.score_threshold);
+        for r in &mut scored {
+            r.listed = r.exact || r.score >= cutoff;
+        }
+    }
+    scored
+}
+
+/// One candidate while ranking, ordered by `exact`, `prefix`, `score`,
+/// `name_len`, `boost`, then `index`.
+struct Ranked {
+    index: usize,
+    score: i64,
+    exact: bool,
+    prefix: bool,
+    /// Characters in the name; 0 for all without `search.prefer_shorter`.
+    name_len: usize,
+    /// The recency and favorite part of `score`.
+    boost: i64,
+    /// Whether `rank` keeps it.
+    listed: bool,
+}
+
+/// Score added for an app last launched within a day, a week, or earlier.
+fn recency_boost(history: &HashMap<String, u64>, app_name: &str, now: u64) -> i64 {
+    history
+        .get(app_name)
+        .map(|&last| {
+            let age_hours = (now.saturating_sub(last)) / 3600;
+            match age_hours {
+                0..=24 => 100,
+                25..=168 => 50,
+                _ => 20,
+            }
+        })
+        .unwrap_or(0)
+}
+
+/// Score added for the favorite at `position` in `apps.favorites` with
+/// `search.positional_favorites`: the first gets the most, each later one
+/// less, so the list order breaks ties between matching favorites.
+fn favorite_boost(text: &SearchText, search: &SearchConfig) -> i64 {
+    match text.favorite {
+        Some(position) if search.positional_favorites => FAVORITE_BOOST / (position as i64 + 1),
+        _ => 0,
+    }
+}
+
+/// Lowest score a fuzzy match needs to be listed: `min_score`, or
+/// `score_threshold` of the best match when that's higher. Only applies to
+/// queries of 2+ characters with no substring matches.
+pub fn fuzzy_cutoff(best_score: i64, min_score: i64, score_threshold: f64) -> i64 {
+    let relative = (best_score as f64 * score_threshold.clamp(0.0, 1.0)) as i64;
+    min_score.max(relative)
+}
+
+/// One matched text in a `--score` report.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScoredText {
+    pub index: usize,
+    pub score: i64,
+    /// Whether the launcher would list it, before `max_results`.
+    pub passed: bool,
+}
+
+/// Every text matching `query`, in the launcher's order, with the score
+/// `rank` gives it (favorite boosts included, history boosts aside) and
+/// whether `rank` lists it.
+pub fn score_report(
+    texts: &[SearchText],
+    matcher: &Matcher,
+    query: &str,
+    search: &SearchConfig,
+) -> Vec<ScoredText> {
+    candidates(texts, matcher, query, search, &HashMap::new(), 0)
+        .into_iter()
+        .map(|r| ScoredText {
+            index: r.index,
+            score: r.score,
+            passed: r.listed,
+        })
+        .collect()
+}
+
+/// Case-insensitive substring scorer: a match at the start of the text beats
+/// one at a word start, which beats one mid-word. No fuzzy gaps.
+fn simple_score(text: &str, query: &str) -> Option<i64> {
+    let text = text.to_lowercase();
+    let query = query.to_lowercase();
+    let pos = text.find(&query)?;
+
+    if pos == 0 {
+        Some(SIMPLE_PREFIX_SCORE)
+    } else if text[..pos].ends_with(|c: char| !c.is_alphanumeric()) {
+        Some(SIMPLE_WORD_START_SCORE)
+    } else {
+        Some(SIMPLE_SUBSTRING_SCORE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTS: [&str; 4] = ["Campfire", "Files Reader", "Alacritty", "Firefox"];
+
+    fn ranked(kind: MatcherKind, query: &str) -> Vec<&'static str> {
+        let matcher = Matcher::new(kind);
+        let mut scored: Vec<(&str, i64)> = TEXTS
+            .iter()
+            .filter_map(|&t| matcher.fuzzy_match(t, query).map(|s| (t, s)))
+            .collect();
+        scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        scored.into_iter().map(|(t, _)| t).collect()
+    }
+
+    #[test]
+    fn skim_ranks_prefix_then_fuzzy_then_mid_word() {
+        assert_eq!(
+            ranked(MatcherKind::Skim, "fire"),
+            vec!["Firefox", "Files Reader", "Campfire"]
+        );
+    }
+
+    #[test]
+    fn clangd_ranks_prefix_then_fuzzy_then_mid_word() {
+        assert_eq!(
+            ranked(MatcherKind::Clangd, "fire"),
+            vec!["Firefox", "Files Reader", "Campfire"]
+        );
+    }
+
+    #[test]
+    fn simple_only_matches_substrings() {
+        assert_eq!(
+            ranked(MatcherKind::Simple, "fire"),
+            vec!["Firefox", "Campfire"]
+        );
+    }
+
+    #[test]
+    fn simple_prefers_word_starts_over_mid_word() {
+        assert_eq!(simple_score("Files Reader", "read"), Some(80));
+        assert_eq!(simple_score("Spreadsheet", "read"), Some(60));
+        assert_eq!(simple_score("Reader", "READ"), Some(100));
+    }
+
+    fn keywords(kws: &[&str]) -> Vec<String> {
+        kws.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn keyword_match_ranks_below_equal_name_match() {
+        let matcher = Matcher::new(MatcherKind::Skim);
+        let by_name = SearchText::new("Editor", &[]);
+        let by_keyword = SearchText::new("Zed", &keywords(&["Editor"]));
+
+        // same raw score when keywords count fully...
+        assert_eq!(
+            by_name.score(&matcher, "editor", 1.0),
+            by_keyword.score(&matcher, "editor", 1.0)
+        );
+        // ...but the name wins once keywords are weighted down
+        assert!(by_name.score(&matcher, "editor", 0.8) > by_keyword.score(&matcher, "editor", 0.8));
+    }
+
+    #[test]
+    fn zero_keyword_weight_still_matches_keywords() {
+        let matcher = Matcher::new(MatcherKind::Skim);
+        let text = SearchText::new("Zed", &keywords(&["editor"]));
+        assert_eq!(text.score(&matcher, "editor", 0.0), Some(0));
+    }
+
+    #[test]
+    fn query_spanning_name_and_keywords_counts_as_keyword_hit() {
+        let matcher = Matcher::new(MatcherKind::Skim);
+        let text = SearchText::new("Firefox", &keywords(&["web", "browser"]));
+
+        let full = text.score(&matcher, "fire web", 1.0).unwrap();
+        let weighted = text.score(&matcher, "fire web", 0.5).unwrap();
+        assert!(full > 0);
+        assert_eq!(weighted, full / 2);
+    }
+
+    #[test]
+    fn repeated_words_count_once() {
+        let matcher = Matcher::new(MatcherKind::Skim);
+        let text = SearchText::new(
+            "Firefox Browser",
+            &keywords(&["browser", "Web Browser", "firefox BROWSER", "internet"]),
+        );
+        assert_eq!(text.keywords, ["Web Browser", "internet"]);
+        assert_eq!(text.combined, "Firefox Browser Web internet");
+
+        let plain = SearchText::new("Firefox Browser", &keywords(&["Web Browser", "internet"]));
+        assert_eq!(
+            text.score(&matcher, "browser", 1.0),
+            plain.score(&matcher, "browser", 1.0)
+        );
+        assert_eq!(text.matched_on(&matcher, "browser"), Some(MatchedOn::Name));
+    }
+
+    #[test]
+    fn search_text_substring_and_prefix() {
+        let text = SearchText::new("Firefox", &keywords(&["Browser"]));
+        assert!(text.contains("brow"));
+        assert!(text.name_starts_with("fire"));
+        assert!(!text.name_starts_with("brow"));
+    }
+
+    #[test]
+    fn matched_on_names_the_text_that_matched() {
+        let matcher = Matcher::new(MatcherKind::Skim);
+        let text = SearchText::new("Firefox", &keywords(&["web", "browser"]));
+
+        assert_eq!(text.matched_on(&matcher, "fire"), Some(MatchedOn::Name));
+        assert_eq!(
+            text.matched_on(&matcher, "brow"),
+            Some(MatchedOn::Keyword("browser".to_string()))
+        );
+        assert_eq!(text.matched_on(&matcher, "fire web"), None);
+    }
+
+    fn triggers(pairs: &[(&str, TriggerMode)]) -> BTreeMap<String, TriggerMode> {
+        pairs.iter().map(|&(p, m)| (p.to_string(), m)).collect()
+    }
+
+    #[test]
+    fn trigger_prefix_is_detected_and_stripped() {
+        let triggers = triggers(&[(">", TriggerMode::Run), ("?", TriggerMode::WebSearch)]);
+
+        assert_eq!(
+            detect_trigger(&triggers, ">ls -la"),
+            Some((TriggerMode::Run, "ls -la"))
+        );
+        assert_eq!(
+            detect_trigger(&triggers, "? rust docs"),
+            Some((TriggerMode::WebSearch, "rust docs"))
+        );
+        assert_eq!(detect_trigger(&triggers, ">"), Some((TriggerMode::Run, "")));
+        assert_eq!(detect_trigger(&triggers, "firefox"), None);
+        assert_eq!(detect_trigger(&triggers, "a>b"), None);
+    }
+
+    #[test]
+    fn longest_trigger_prefix_wins() {
+        let triggers = triggers(&[
+            (">", TriggerMode::Run),
+            (">>", TriggerMode::WebSearch),
+            ("", TriggerMode::Run),
+        ]);
+
+        assert_eq!(
+            detect_trigger(&triggers, ">>weather"),
+            Some((TriggerMode::WebSearch, "weather"))
+        );
+        assert_eq!(
+            detect_trigger(&triggers, ">top"),
+            Some((TriggerMode::Run, "top"))
+        );
+        assert_eq!(detect_trigger(&triggers, "top"), None);
+    }
+
+    #[test]
+    fn fuzzy_cutoff_is_the_stricter_threshold() {
+        assert_eq!(fuzzy_cutoff(200, 30, 0.6), 120);
+        assert_eq!(fuzzy_cutoff(40, 30, 0.6), 30);
+        assert_eq!(fuzzy_cutoff(200, 30, 2.0), 200);
+    }
+
+    fn search_config(min_score: i64, score_threshold: f64) -> SearchConfig {
+        SearchConfig {
+            min_score,
+            score_threshold,
+            ..SearchConfig::default()
+        }
+    }
+
+    #[test]
+    fn report_flags_fuzzy_matches_below_cutoff() {
+        let texts: Vec<SearchText> = ["Firefox", "Files Reader", "Alacritty"]
+            .iter()
+            .map(|name| SearchText::new(name, &[]))
+            .collect();
+        let matcher = Matcher::new(MatcherKind::Skim);
+
+        // "fr" is no substring of anything, so the fuzzy thresholds apply.
+        let report = score_report(&texts, &matcher, "fr", &search_config(0, 0.0));
+        assert_eq!(report.len(), 2);
+        assert!(report.iter().all(|r| r.passed));
+        assert!(report[0].score >= report[1].score);
+
+        let (best, worst) = (report[0].score, report[1].score);
+        let report = score_report(&texts, &matcher, "fr", &search_config(worst + 1, 0.0));
+        assert!(report[0].passed == (best > worst));
+        assert!(!report[1].passed);
+    }
+
+    #[test]
+    fn report_passes_only_substring_hits_when_there_are_any() {
+        let texts: Vec<SearchText> = ["Firefox", "Files Reader", "Campfire"]
+            .iter()
+            .map(|name| SearchText::new(name, &[]))
+            .collect();
+        let matcher = Matcher::new(MatcherKind::Skim);
+
+        let report = score_report(&texts, &matcher, "fire", &search_config(1000, 1.0));
+        let passed: Vec<usize> = report
+            .iter()
+            .filter(|r| r.passed)
+            .map(|r| r.index)
+            .collect();
+        let failed: Vec<usize> = report
+            .iter()
+            .filter(|r| !r.passed)
+            .map(|r| r.index)
+            .collect();
+        assert_eq!(passed, [0, 2]);
+        assert_eq!(failed, [1]);
+    }
+
+    fn texts(names: &[&str]) -> Vec<SearchText> {
+        names
+            .iter()
+            .map(|name| SearchText::new(name, &[]))
+            .collect()
+    }
+
+    fn ranked_names<'a>(
+        names: &[&'a str],
+        query: &str,
+        search: &SearchConfig,
+        history: &HashMap<String, u64>,
+    ) -> Vec<&'a str> {
+        let matcher = Matcher::new(search.matcher);
+        rank(&texts(names), &matcher, query, search, history, 1_000_000)
+            .into_iter()
+            .map(|(i, _)| names[i])
+            .collect()
+    }
+
+    #[test]
+    fn rank_lists_nothing_for_an_empty_query() {
+        let search = SearchConfig::default();
+        assert!(ranked_names(&["Firefox"], "", &search, &HashMap::new()).is_empty());
+        assert!(ranked_names(&["Firefox"], "  ", &search, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn rank_keeps_only_substring_matches_when_there_are_any() {
+        let names = ["Firefox", "Files Reader", "Campfire"];
+        let search = SearchConfig::default();
+        assert_eq!(
+            ranked_names(&names, "fire", &search, &HashMap::new()),
+            ["Firefox", "Campfire"]
+        );
+    }
+
+    #[test]
+    fn rank_puts_name_prefixes_first_when_preferred() {
+        let names = ["Campfire", "Firefox"];
+        let mut history = HashMap::new();
+        // A fresh launch outweighs the prefix on score alone...
+        history.insert("Campfire".to_string(), 1_000_000);
+
+        let search = SearchConfig::default();
+        assert!(search.prefer_prefix);
+        assert_eq!(
+            ranked_names(&names, "fire", &search, &history),
+            ["Firefox", "Campfire"]
+        );
+
+        // ...so without the prefix preference it comes first.
+        let search = SearchConfig {
+            prefer_prefix: false,
+            ..SearchConfig::default()
+        };
+        assert_eq!(
+            ranked_names(&names, "fire", &search, &history),
+            ["Campfire", "Firefox"]
+        );
+    }
+
+    #[test]
+    fn rank_boosts_recent_launches() {
+        let names = ["Files", "Filezilla"];
+        let search = SearchConfig {
+            prefer_prefix: false,
+            ..SearchConfig::default()
+        };
+        let mut history = HashMap::new();
+        history.insert("Filezilla".to_string(), 1_000_000 - 3600);
+
+        assert_eq!(
+            ranked_names(&names, "file", &search, &history),
+            ["Filezilla", "Files"]
+        );
+        assert_eq!(recency_boost(&history, "Filezilla", 1_000_000), 100);
+        assert_eq!(
+            recency_boost(&history, "Filezilla", 1_000_000 + 48 * 3600),
+            50
+        );
+        assert_eq!(
+            recency_boost(&history, "Filezilla", 1_000_000 + 400 * 3600),
+            20
+        );
+        assert_eq!(recency_boost(&history, "Files", 1_000_000), 0);
+    }
+
+    #[test]
+    fn rank_puts_the_shorter_of_tied_names_first() {
+        let names = ["Code - OSS", "Code"];
+        // "Code" is also an exact match; keep that from deciding the order.
+        let search = SearchConfig {
+            matcher: MatcherKind::Simple,
+            exact_to_top: false,
+            ..SearchConfig::default()
+        };
+        let matcher = Matcher::new(search.matcher);
+        let texts = texts(&names);
+        assert_eq!(
+            texts[0].score(&matcher, "code", 1.0),
+            texts[1].score(&matcher, "code", 1.0)
+        );
+
+        assert!(search.prefer_shorter);
+        assert_eq!(
+            ranked_names(&names, "code", &search, &HashMap::new()),
+            ["Code", "Code - OSS"]
+        );
+
+        let search = SearchConfig {
+            prefer_shorter: false,
+            ..search
+        };
+        assert_eq!(
+            ranked_names(&names, "code", &search, &HashMap::new()),
+            ["Code - OSS", "Code"]
+        );
+    }
+
+    #[test]
+    fn rank_puts_an_exact_name_first() {
+        let names = ["Terminal", "Term"];
+        let search = SearchConfig::default();
+        // Launched just now, so it outscores the exact match.
+        let history = HashMap::from([("Terminal".to_string(), 1_000_000)]);
+        let scores = rank(
+            &texts(&names),
+            &Matcher::new(search.matcher),
+            "term",
+            &SearchConfig {
+                exact_to_top: false,
+                ..SearchConfig::default()
+            },
+            &history,
+            1_000_000,
+        );
+        assert_eq!(scores[0].0, 0);
+        assert!(scores[0].1 > scores[1].1);
+
+        assert!(search.exact_to_top);
+        assert_eq!(
+            ranked_names(&names, "term", &search, &history),
+            ["Term", "Terminal"]
+        );
+
+        let search = SearchConfig {
+            exact_to_top: false,
+            ..search
+        };
+        assert_eq!(
+            ranked_names(&names, "term", &search, &history),
+            ["Terminal", "Term"]
+        );
+    }
+
+    #[test]
+    fn rank_orders_ties_the_same_way_every_time() {
+        // Equal keyword matches: the recent launch first, the rest in text
+        // order.
+        let names = ["Gedit", "Kate", "Mousepad", "Zed"];
+        let search = SearchConfig {
+            prefer_prefix: false,
+            prefer_shorter: false,
+            ..SearchConfig::default()
+        };
+        let mut history = HashMap::new();
+        history.insert("Zed".to_string(), 1_000_000);
+        let texts: Vec<SearchText> = names
+            .iter()
+            .map(|name| SearchText::new(name, &keywords(&["editor"])))
+            .collect();
+        let matcher = Matcher::new(search.matcher);
+
+        let first = rank(&texts, &matcher, "editor", &search, &history, 1_000_000);
+        let order: Vec<usize> = first.iter().map(|&(i, _)| i).collect();
+        assert_eq!(order, [3, 0, 1, 2]);
+        for _ in 0..10 {
+            assert_eq!(
+                rank(&texts, &matcher, "editor", &search, &history, 1_000_000),
+                first
+            );
+        }
+    }
+
+    #[test]
+    fn positional_favorites_rank_earlier_favorites_first() {
+        // Equal keyword matches, listed in text order without the option.
+        let search = SearchConfig {
+            prefer_prefix: false,
+            prefer_shorter: false,
+            ..SearchConfig::default()
+        };
+        let favorites = ["Kate", "Gedit"];
+        let texts: Vec<SearchText> = ["Gedit", "Kate", "Zed"]
+            .iter()
+            .map(|name| {
+                let position = favorites.iter().position(|f| f == name);
+                SearchText::new(name, &keywords(&["editor"])).favorite_at(position)
+            })
+            .collect();
+        let matcher = Matcher::new(search.matcher);
+        let order = |search: &SearchConfig| -> Vec<usize> {
+            rank(&texts, &matcher, "editor", search, &HashMap::new(), 0)
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect()
+        };
+
+        assert!(!search.positional_favorites);
+        assert_eq!(order(&search), [0, 1, 2]);
+
+        let positional = SearchConfig {
+            positional_favorites: true,
+            ..search
+        };
+        assert_eq!(order(&positional), [1, 0, 2]);
+        // `--score` reports the same boosted scores, in the same order.
+        let report: Vec<(usize, i64)> = score_report(&texts, &matcher, "editor", &positional)
+            .into_iter()
+            .filter(|r| r.passed)
+            .map(|r| (r.index, r.score))
+            .collect();
+        assert_eq!(
+            report,
+            rank(&texts, &matcher, "editor", &positional, &HashMap::new(), 0)
+        );
+        assert_eq!(favorite_boost(&texts[1], &positional), FAVORITE_BOOST);
+        assert_eq!(favorite_boost(&texts[0], &positional), FAVORITE_BOOST / 2);
+        assert_eq!(favorite_boost(&texts[2], &positional), 0);
+    }
+
+    #[test]
+    fn rank_applies_fuzzy_thresholds_without_substring_hits() {
+        let names = ["Firefox", "Files Reader", "Alacritty"];
+        let lenient = search_config(0, 0.0);
+        let fuzzy = ranked_names(&names, "fr", &lenient, &HashMap::new());
+        assert_eq!(fuzzy.len(), 2);
+        assert!(!fuzzy.contains(&"Alacritty"));
+
+        let strict = search_config(10_000, 0.0);
+        assert!(ranked_names(&names, "fr", &strict, &HashMap::new()).is_empty());
+
+        // Single characters aren't held to the thresholds.
+        assert!(!ranked_names(&names, "f", &strict, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn perfect_matches_score_the_same_on_every_backend() {
+        let skim = Matcher::new(MatcherKind::Skim).fuzzy_match("firefox", "firefox");
+        let clangd = Matcher::new(MatcherKind::Clangd).fuzzy_match("firefox", "firefox");
+        let simple = Matcher::new(MatcherKind::Simple).fuzzy_match("firefox", "firefox");
+
+        assert!(skim.is_some());
+        assert_eq!(clangd, skim);
+        assert_eq!(simple, skim);
+    }
+
+    #[test]
+    fn rescaling_follows_the_query() {
+        for kind in [MatcherKind::Clangd, MatcherKind::Simple] {
+            let matcher = Matcher::new(kind);
+            for query in ["fire", "firefox", "fire", "fx"] {
+                assert_eq!(
+                    matcher.fuzzy_match("firefox", query),
+                    Matcher::new(kind).fuzzy_match("firefox", query),
+                    "{kind:?} {query}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Named themes for `appearance.theme`: a directory under
+//! `~/.config/yeet/themes/<name>/` holding a `style.css` and, optionally, a
+//! `config.toml` layered between the defaults and the user's own config.
+//! A few themes ship with yeet and are installed with `--install-theme`;
+//! `--theme-preview` switches between all of them in a running window.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const STYLE_FILE: &str = "style.css";
+pub const CONFIG_FILE: &str = "config.toml";
+
+struct BundledTheme {
+    name: &'static str,
+    style: &'static str,
+    config: Option<&'static str>,
+}
+
+const BUNDLED: &[BundledTheme] = &[
+    BundledTheme {
+        name: "latte",
+        style: include_str!("../defaults/themes/latte/style.css"),
+        config: None,
+    },
+    BundledTheme {
+        name: "nord",
+        style: include_str!("../defaults/themes/nord/style.css"),
+        config: Some(include_str!("../defaults/themes/nord/config.toml")),
+    },
+];
+
+/// Names of the themes `--install-theme` knows.
+pub fn bundled_names() -> impl Iterator<Item = &'static str> {
+    BUNDLED.iter().map(|theme| theme.name)
+}
+
+/// Directory of theme `name` under `config_dir`, or `None` when the name
+/// isn't a single path component.
+pub fn dir(config_dir: &Path, name: &str) -> Option<PathBuf> {
+    let plain = !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\']);
+    plain.then(|| config_dir.join("themes").join(name))
+}
+
+/// A theme `--theme-preview` can show.
+pub struct Preview {
+    pub name: String,
+    style: PreviewStyle,
+}
+
+enum PreviewStyle {
+    Bundled(&'static str),
+    /// Read each time it's shown, so edits show up on the next switch.
+    Installed(PathBuf),
+}
+
+impl Preview {
+    pub fn style(&self) -> io::Result<String> {
+        match &self.style {
+            PreviewStyle::Bundled(style) => Ok(style.to_string()),
+            PreviewStyle::Installed(path) => fs::read_to_string(path),
+        }
+    }
+}
+
+/// The themes installed under `config_dir` that have a `style.css`, and
+/// the bundled ones that aren't installed, by name.
+pub fn previews(config_dir: Option<&Path>) -> Vec<Preview> {
+    let mut previews: Vec<Preview> = config_dir
+        .and_then(|dir| fs::read_dir(dir.join("themes")).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let style = entry.path().join(STYLE_FILE);
+            style.is_file().then(|| Preview {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                style: PreviewStyle::Installed(style),
+            })
+        })
+        .collect();
+    for theme in BUNDLED {
+        if !previews.iter().any(|preview| preview.name == theme.name) {
+            previews.push(Preview {
+                name: theme.name.to_string(),
+                style: PreviewStyle::Bundled(theme.style),
+            });
+        }
+    }
+    previews.sort_by(|a, b| a.name.cmp(&b.name));
+    previews
+}
+
+/// The theme shown after `current` (or before it, without `forward`) in a
+/// list of `len`, wrapping around at both ends; from none, the first or the
+/// last one.
+pub fn cycle(current: Option<usize>, len: usize, forward: bool) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    Some(match (current, forward) {
+        (None, true) => 0,
+        (None, false) => len - 1,
+        (Some(i), true) => (i + 1) % len,
+        (Some(i), false) => (i + len - 1) % len,
+    })
+}
+
+/// Writes bundled theme `name` into `config_dir`, returning its directory.
+/// An existing theme of that name is never overwritten.
+pub fn install(config_dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let theme = BUNDLED
+        .iter()
+        .find(|theme| theme.name == name)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no bundled theme named \"{name}\""),
+            )
+        })?;
+    let target = dir(config_dir, name).expect("bundled theme names are plain");
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+
+    fs::create_dir_all(&target)?;
+    fs::write(target.join(STYLE_FILE), theme.style)?;
+    if let Some(config) = theme.config {
+        fs::write(target.join(CONFIG_FILE), config)?;
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn resolves_theme_dir_under_config_dir() {
+        let config_dir = Path::new("/home/me/.config/yeet");
+        assert_eq!(
+            dir(config_dir, "nord"),
+            Some(PathBuf::from("/home/me/.config/yeet/themes/nord"))
+        );
+        assert_eq!(
+            dir(config_dir, "My Theme"),
+            Some(PathBuf::from("/home/me/.config/yeet/themes/My Theme"))
+        );
+    }
+
+    #[test]
+    fn rejects_names_that_leave_themes_dir() {
+        let config_dir = Path::new("/home/me/.config/yeet");
+        for name in ["", ".", "..", "../secrets", "a/b", r"a\b"] {
+            assert_eq!(dir(config_dir, name), None, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn bundled_theme_configs_parse() {
+        for theme in BUNDLED {
+            if let Some(config) = theme.config {
+                assert!(
+                    toml::from_str::<Config>(config).is_ok(),
+                    "{} config.toml",
+                    theme.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn previews_list_installed_themes_over_bundled_ones() {
+        let config_dir = std::env::temp_dir().join("yeet_test_theme_previews");
+        let _ = fs::remove_dir_all(&config_dir);
+        install(&config_dir, "nord").unwrap();
+        let mine = config_dir.join("themes").join("mine");
+        fs::create_dir_all(&mine).unwrap();
+        fs::write(mine.join(STYLE_FILE), "* { color: red; }").unwrap();
+        fs::create_dir_all(config_dir.join("themes").join("no-style")).unwrap();
+
+        let previews = previews(Some(&config_dir));
+        let names: Vec<&str> = previews.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["latte", "mine", "nord"]);
+        assert_eq!(previews[1].style().unwrap(), "* { color: red; }");
+        assert!(matches!(previews[2].style, PreviewStyle::Installed(_)));
+        assert!(matches!(previews[0].style, PreviewStyle::Bundled(_)));
+
+        let _ = fs::remove_dir_all(&config_dir);
+    }
+
+    #[test]
+    fn cycling_themes_wraps_around() {
+        assert_eq!(cycle(None, 3, true), Some(0));
+        assert_eq!(cycle(None, 3, false), Some(2));
+        assert_eq!(cycle(Some(0), 3, true), Some(1));
+        assert_eq!(cycle(Some(2), 3, true), Some(0));
+        assert_eq!(cycle(Some(0), 3, false), Some(2));
+        assert_eq!(cycle(Some(1), 3, false), Some(0));
+        assert_eq!(cycle(Some(0), 1, true), Some(0));
+        assert_eq!(cycle(None, 0, true), None);
+    }
+
+    #[test]
+    fn install_writes_files_once() {
+        let config_dir = std::env::temp_dir().join("yeet_test_install_theme");
+        let _ = fs::remove_dir_all(&config_dir);
+
+        let target = install(&config_dir, "nord").unwrap();
+        assert_eq!(target, config_dir.join("themes").join("nord"));
+        assert!(target.join(STYLE_FILE).is_file());
+        assert!(target.join(CONFIG_FILE).is_file());
+
+        let again = install(&config_dir, "nord").unwrap_err();
+        assert_eq!(again.kind(), io::ErrorKind::AlreadyExists);
+        let unknown = install(&config_dir, "nope").unwrap_err();
+        assert_eq!(unknown.kind(), io::ErrorKind::NotFound);
+
+        let _ = fs::remove_dir_all(&config_dir);
+    }
+}
@@ -0,0 +1,79 @@
+//! `--timing`: how long the startup phases took, printed to stderr as each
+//! one finishes, so a slow start can be reported with numbers instead of
+//! "it's slow".
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Startup clock, started once at the top of `main`.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    start: Instant,
+}
+
+impl Timing {
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+
+    pub fn since_start(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// One finished phase, e.g. `timing: discovery        12.3 ms (312 apps)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phase {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    /// What was done, or what `elapsed` is measured from.
+    pub detail: Option<String>,
+}
+
+impl Phase {
+    pub fn new(name: &'static str, elapsed: Duration, detail: Option<String>) -> Self {
+        Self {
+            name,
+            elapsed,
+            detail,
+        }
+    }
+
+    pub fn report(&self) {
+        eprintln!("yeet: {self}");
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.elapsed.as_secs_f64() * 1000.0;
+        write!(f, "timing: {:<12} {ms:>8.1} ms", self.name)?;
+        match &self.detail {
+            Some(detail) => write!(f, " ({detail})"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_print_milliseconds_and_detail() {
+        let discovery = Phase::new(
+            "discovery",
+            Duration::from_micros(12_345),
+            Some("312 apps".to_string()),
+        );
+        assert_eq!(
+            discovery.to_string(),
+            "timing: discovery        12.3 ms (312 apps)"
+        );
+
+        let paint = Phase::new("first paint", Duration::from_millis(1_250), None);
+        assert_eq!(paint.to_string(), "timing: first paint    1250.0 ms");
+    }
+}
@@ -1,23 +1,40 @@
-use crate::config::Config;
-use crate::desktop::App;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
-use gtk4::gdk::{Display, ModifierType};
+use crate::collate::Collator;
+use crate::config::{
+    Config, ListOrder, MonitorFallback, MonitorSelector, MultilinePaste, WidthMode,
+};
+use crate::desktop::{retain_with_icons, App};
+use crate::history;
+use crate::icons::{IconLookup, IconQueue};
+use crate::search::{detect_trigger, rank, MatchedOn, Matcher, SearchText};
+use crate::theme::{cycle, Preview};
+use crate::timing::{Phase, Timing};
+use gtk4::gdk::{Display, ModifierType, Monitor};
+use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::{
     Application, ApplicationWindow, Box as GtkBox, CssProvider, Entry, Label, ListBox, ListBoxRow,
     Orientation, PolicyType, ScrolledWindow,
 };
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
-use std::cell::RefCell;
-use std::cmp::Reverse;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 const DEFAULT_STYLE: &str = include_str!("../defaults/style.css");
-
-pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: Rc<dyn Fn(&App)>) {
-    load_css();
+const ICON_SIZE: i32 = 36;
+const ICON_CACHE_FILE: &str = "icon-cache.txt";
+
+/// `timing` reports the icon and first-paint phases for `--timing`.
+pub fn build_ui(
+    app: &Application,
+    config: &Config,
+    apps: Vec<App>,
+    on_select: Rc<dyn Fn(&App)>,
+    timing: Option<Timing>,
+) -> LauncherWindow {
+    let css = load_css(config.appearance.theme.as_deref());
 
     let window = ApplicationWindow::builder()
         .application(app)
@@ -34,6 +51,13 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
         window.set_namespace(Some("yeet"));
         window.set_anchor(Edge::Top, true);
         window.set_margin(Edge::Top, config.appearance.anchor_top);
+
+        // Unset leaves placement to the compositor, i.e. the active monitor.
+        if let Some(selector) = &config.general.monitor {
+            if let Some(monitor) = select_monitor(selector, config.general.monitor_fallback) {
+                window.set_monitor(Some(&monitor));
+            }
+        }
     }
 
     window.add_css_class("yeet-window");
@@ -43,12 +67,33 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
 
     let entry = Entry::builder().placeholder_text("Search...").build();
     entry.add_css_class("yeet-entry");
+    flatten_pasted_lines(&entry, config.general.multiline_paste);
 
     let list_box = ListBox::new();
     list_box.set_selection_mode(gtk4::SelectionMode::Single);
     list_box.add_css_class("yeet-list");
 
-    let list_height = config.appearance.row_height * config.general.max_results as i32;
+    let row_layout = RowLayout::new(config.appearance.row_height, config.appearance.row_spacing);
+    let list_height = row_layout.list_height(config.general.max_results);
+    // With `appearance.fixed_list_size`, short result lists are padded with
+    // blank rows so the list never changes shape while typing.
+    let fixed_rows = if config.appearance.fixed_list_size {
+        config.general.max_results
+    } else {
+        0
+    };
+    let descriptions = DescriptionStyle::from_settings(
+        config.appearance.show_descriptions,
+        config.appearance.description_as_tooltip,
+    );
+    let row_style = RowStyle {
+        shortcuts: config.appearance.show_shortcuts,
+        icons: config.appearance.show_icons,
+        descriptions,
+        layout: row_layout,
+        fixed_rows,
+        accessibility: config.general.accessibility,
+    };
     let scrolled = ScrolledWindow::builder()
         .hscrollbar_policy(PolicyType::Never)
         .vscrollbar_policy(PolicyType::Automatic)
@@ -56,43 +101,98 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
         .build();
     scrolled.set_size_request(-1, list_height);
 
+    // Shown while a `confirm = true` app waits for a second Enter.
+    let confirm_label = Label::new(None);
+    confirm_label.add_css_class("yeet-confirm");
+    confirm_label.set_visible(false);
+
     vbox.append(&entry);
+    vbox.append(&confirm_label);
     vbox.append(&scrolled);
     window.set_child(Some(&vbox));
 
+    let icon_cache = history::data_dir(config.data_dir().as_deref()).join(ICON_CACHE_FILE);
+    let theme_load = Instant::now();
+    let icons = IconLookup::new(
+        config.appearance.icon_theme.as_deref(),
+        ICON_SIZE,
+        config.appearance.icon_scaling,
+        Some(icon_cache),
+    );
+    let mut apps = apps;
+    if config.apps.require_icon {
+        // Needs the display's icon theme, so this runs here rather than in
+        // discovery; a theme from `appearance.icon_theme` counts too.
+        let theme = gtk4::IconTheme::for_display(&WidgetExt::display(&window));
+        retain_with_icons(&mut apps, |icon| {
+            icons.resolve(icon).is_some() || theme.has_icon(icon)
+        });
+    }
+    let icons = IconLoader::new(icons, &list_box, &scrolled, config.general.max_results);
+    if timing.is_some() {
+        icons.time(theme_load.elapsed());
+    }
+    let feed = RowFeed::new(icons, &list_box, &scrolled);
     let apps = Rc::new(apps);
-    let app_names_lower: Rc<Vec<String>> =
-        Rc::new(apps.iter().map(|a| a.name.to_lowercase()).collect());
-    let app_name_keyword_texts: Rc<Vec<String>> = Rc::new(
+    let search_texts: Rc<Vec<SearchText>> = Rc::new(
         apps.iter()
-            .map(|a| {
-                let mut text = a.name.clone();
-                for kw in &a.keywords {
-                    text.push(' ');
-                    text.push_str(kw);
-                }
-                text
-            })
-            .collect(),
-    );
-    let app_name_keyword_texts_lower: Rc<Vec<String>> = Rc::new(
-        app_name_keyword_texts
-            .iter()
-            .map(|t| t.to_lowercase())
+            .map(|a| SearchText::new(&a.name, &a.keywords).favorite_at(a.favorite_position))
             .collect(),
     );
     let filtered_apps: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(Vec::new()));
-    let matcher = Rc::new(SkimMatcherV2::default());
+    // Rows built from the query itself, shown instead of `filtered_apps`:
+    // handlers for a typed URL, or the `search.no_match_action` fallback.
+    let query_apps: Rc<RefCell<Vec<App>>> = Rc::new(RefCell::new(Vec::new()));
+    let matcher = Rc::new(Matcher::new(config.search.matcher));
     let max_results = config.general.max_results;
     let initial_results = config.general.initial_results;
-    let min_score = config.search.min_score;
-    let score_threshold = config.search.score_threshold;
-    let prefer_prefix = config.search.prefer_prefix;
-    let show_shortcuts = config.appearance.show_shortcuts;
-    let show_descriptions = config.appearance.show_descriptions;
+    let no_match_action = config.search.no_match_action;
+    let open_urls = config.search.open_urls;
+    let path_detection = config.search.path_detection;
     let use_history = config.search.use_history;
+    let debounce_ms = config.search.debounce_ms;
+    let stay_open = config.general.stay_open;
+    let launch_debounce_ms = config.general.launch_debounce_ms;
+    let wrap_navigation = config.general.wrap_navigation;
+    let enter_launches_top = config.general.enter_launches_top;
+    let copy_target = config.general.copy_target;
+    let list_order: Rc<[ListOrder]> =
+        list_order(&config.general.empty_order, config.apps.favorites_in_list).into();
+    let show_sections = config.appearance.show_sections;
+    let group_by_category = config.appearance.group_by_category;
+    let cancel_keys: Vec<KeyCombo> = config
+        .general
+        .cancel_keys
+        .iter()
+        .filter_map(|spec| {
+            let combo = KeyCombo::parse(spec);
+            if combo.is_none() {
+                eprintln!("Warning: ignoring unknown cancel key \"{spec}\"");
+            }
+            combo
+        })
+        .collect();
     let history: Rc<HashMap<String, u64>> = Rc::new(if use_history {
-        crate::history::load_history()
+        let data_dir = config.data_dir();
+        let format = config.general.history_format;
+        crate::history::forget_older_than(
+            config.search.history_forget_days,
+            data_dir.as_deref(),
+            format,
+        );
+        let keys: Vec<(&str, &str)> = apps
+            .iter()
+            .map(|app| (app.name.as_str(), app.identity()))
+            .collect();
+        crate::history::migrate_names(
+            &crate::history::name_renames(&keys),
+            data_dir.as_deref(),
+            format,
+        );
+        crate::history::by_name(
+            crate::history::load_history(data_dir.as_deref(), format),
+            &keys,
+        )
     } else {
         HashMap::new()
     });
@@ -101,141 +201,395 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
         .map(|d| d.as_secs())
         .unwrap_or(0);
 
+    let fit_width = {
+        let vbox = vbox.clone();
+        let width_mode = config.appearance.width_mode;
+        let min_width = config.appearance.width;
+        let max_width = config.appearance.max_width;
+
+        Rc::new(move |name_labels: &[Label]| {
+            if width_mode == WidthMode::Content {
+                let widths: Vec<i32> = name_labels.iter().map(row_content_width).collect();
+                vbox.set_size_request(content_width(&widths, min_width, max_width), -1);
+            }
+        })
+    };
+
     {
         let mut filtered = filtered_apps.borrow_mut();
-        *filtered = initial_indices(&apps, &history, initial_results);
-        populate_list(
-            &list_box,
+        let (indices, headers) = browse_list(
             &apps,
-            &filtered,
-            show_shortcuts,
-            show_descriptions,
+            &history,
+            initial_results,
+            &list_order,
+            show_sections,
+            group_by_category,
         );
+        *filtered = indices;
+        let name_labels =
+            populate_list(&list_box, &apps, &filtered, row_style, &[], &headers, &feed);
+        fit_width(&name_labels);
     }
-    select_first(&list_box);
+    reset_selection(&list_box, enter_launches_top);
 
-    {
+    let showing_actions = Rc::new(Cell::new(false));
+    let rebuild: Rc<dyn Fn(&str)> = {
         let apps = apps.clone();
-        let app_names_lower = app_names_lower.clone();
-        let app_name_keyword_texts = app_name_keyword_texts.clone();
-        let app_name_keyword_texts_lower = app_name_keyword_texts_lower.clone();
+        let search_texts = search_texts.clone();
         let filtered_apps = filtered_apps.clone();
         let matcher = matcher.clone();
         let history = history.clone();
         let list_box = list_box.clone();
-
-        entry.connect_changed(move |entry| {
-            let query = entry.text();
+        let fit_width = fit_width.clone();
+        let query_apps = query_apps.clone();
+        let feed = feed.clone();
+        let showing_actions = showing_actions.clone();
+        let search = config.search.clone();
+        let triggers = config.triggers.clone();
+        let active_trigger = Cell::new(None);
+        let entry = entry.clone();
+        // Where `~` and `./` in a typed path point.
+        let home = dirs::home_dir();
+        let cwd = std::env::current_dir().unwrap_or_default();
+
+        Rc::new(move |query: &str| {
             let query = query.trim();
             let query_len = query.chars().count();
             let mut filtered = filtered_apps.borrow_mut();
 
+            query_apps.borrow_mut().clear();
+            showing_actions.set(false);
+
+            // A `[triggers]` prefix hands the rest of the query to its mode
+            // instead of searching apps; the entry's class marks the mode.
+            let trigger = detect_trigger(&triggers, query);
+            if let Some(previous) = active_trigger.replace(trigger.map(|(mode, _)| mode)) {
+                entry.remove_css_class(previous.css_class());
+            }
+            if let Some((mode, rest)) = trigger {
+                entry.add_css_class(mode.css_class());
+                filtered.clear();
+                let routed: Vec<App> = (!rest.is_empty())
+                    .then(|| App::for_unmatched_query(mode.action(), rest, &search))
+                    .flatten()
+                    .into_iter()
+                    .collect();
+                let indices: Vec<usize> = (0..routed.len()).collect();
+                let name_labels =
+                    populate_list(&list_box, &routed, &indices, row_style, &[], &[], &feed);
+                *query_apps.borrow_mut() = routed;
+                fit_width(&name_labels);
+                reset_selection(&list_box, enter_launches_top);
+                return;
+            }
+
             if query_len == 0 {
-                *filtered = initial_indices(&apps, &history, initial_results);
-                populate_list(
-                    &list_box,
+                let (indices, headers) = browse_list(
                     &apps,
-                    &filtered,
-                    show_shortcuts,
-                    show_descriptions,
+                    &history,
+                    initial_results,
+                    &list_order,
+                    show_sections,
+                    group_by_category,
                 );
-                select_first(&list_box);
+                *filtered = indices;
+                let name_labels =
+                    populate_list(&list_box, &apps, &filtered, row_style, &[], &headers, &feed);
+                fit_width(&name_labels);
+                reset_selection(&list_box, enter_launches_top);
                 return;
             }
 
             filtered.clear();
 
-            let query_lower = query.to_lowercase();
-            let has_substring_matches = query_len >= 2
-                && app_name_keyword_texts_lower
-                    .iter()
-                    .any(|t| t.contains(&query_lower));
+            // A query naming an existing file or directory opens it.
+            if let Some(path) = path_detection
+                .then(|| crate::desktop::query_path(query, home.as_deref(), &cwd))
+                .flatten()
+                .filter(|path| path.exists())
+            {
+                let opener = vec![App::open_path(&path)];
+                let name_labels =
+                    populate_list(&list_box, &opener, &[0], row_style, &[], &[], &feed);
+                *query_apps.borrow_mut() = opener;
+                fit_width(&name_labels);
+                reset_selection(&list_box, enter_launches_top);
+                return;
+            }
 
-            let mut scored: Vec<(usize, i64, bool)> = if has_substring_matches {
-                app_name_keyword_texts_lower
+            if let Some(scheme) = crate::desktop::url_scheme(query).filter(|_| open_urls) {
+                let handlers: Vec<App> = apps
                     .iter()
-                    .enumerate()
-                    .filter(|(_, text)| text.contains(&query_lower))
-                    .map(|(i, _)| {
-                        let score = matcher
-                            .fuzzy_match(&app_name_keyword_texts[i], query)
-                            .unwrap_or(0);
-                        let is_prefix =
-                            prefer_prefix && app_names_lower[i].starts_with(&query_lower);
-                        let boost = recency_boost(&history, &apps[i].name, now);
-                        (i, score + boost, is_prefix)
-                    })
-                    .collect()
-            } else {
-                app_name_keyword_texts
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, text)| {
-                        matcher.fuzzy_match(text, query).map(|score| {
-                            let is_prefix =
-                                prefer_prefix && app_names_lower[i].starts_with(&query_lower);
-                            let boost = recency_boost(&history, &apps[i].name, now);
-                            (i, score + boost, is_prefix)
-                        })
-                    })
-                    .collect()
+                    .filter_map(|app| app.open_url(&scheme, query))
+                    .take(max_results)
+                    .collect();
+                if !handlers.is_empty() {
+                    let indices: Vec<usize> = (0..handlers.len()).collect();
+                    let name_labels =
+                        populate_list(&list_box, &handlers, &indices, row_style, &[], &[], &feed);
+                    *query_apps.borrow_mut() = handlers;
+                    fit_width(&name_labels);
+                    reset_selection(&list_box, enter_launches_top);
+                    return;
+                }
+            }
+
+            let ranked = rank(&search_texts, &matcher, query, &search, &history, now);
+            *filtered = search_indices(ranked, max_results);
+
+            let fallback = filtered
+                .is_empty()
+                .then(|| App::for_unmatched_query(no_match_action, query, &search))
+                .flatten();
+            let name_labels = match &fallback {
+                Some(app) => populate_list(
+                    &list_box,
+                    std::slice::from_ref(app),
+                    &[0],
+                    row_style,
+                    &[],
+                    &[],
+                    &feed,
+                ),
+                None => {
+                    let matches: Vec<Option<MatchedOn>> = if row_style.accessibility {
+                        filtered
+                            .iter()
+                            .map(|&i| search_texts[i].matched_on(&matcher, query))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    populate_list(&list_box, &apps, &filtered, row_style, &matches, &[], &feed)
+                }
             };
+            query_apps.borrow_mut().extend(fallback);
+            fit_width(&name_labels);
+            reset_selection(&list_box, enter_launches_top);
+        })
+    };
 
-            scored.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| b.1.cmp(&a.1)));
+    // Rebuilding the list for an unchanged query (e.g. coming back from an
+    // app's actions) keeps the selected app selected, wherever its row went.
+    let refresh: Rc<dyn Fn(&str)> = {
+        let apps = apps.clone();
+        let filtered_apps = filtered_apps.clone();
+        let query_apps = query_apps.clone();
+        let list_box = list_box.clone();
+        let last_query = RefCell::new(None::<String>);
+        let listed = move || -> Vec<String> {
+            let filtered = filtered_apps.borrow();
+            let query_apps = query_apps.borrow();
+            filtered
+                .iter()
+                .map(|&i| &apps[i])
+                .chain(query_apps.iter())
+                .map(|app| app.identity().to_string())
+                .collect()
+        };
 
-            if query_len >= 2 && !has_substring_matches {
-                scored.retain(|(_, score, _)| *score >= min_score);
+        Rc::new(move |query: &str| {
+            let same_query = last_query.replace(Some(query.to_string())).as_deref() == Some(query);
+            let kept = list_box
+                .selected_row()
+                .filter(|_| same_query)
+                .and_then(|row| result_position(&list_box, &row))
+                .and_then(|row| listed().get(row).map(|id| (id.clone(), row)));
 
-                let threshold = score_threshold.clamp(0.0, 1.0);
-                let best_score = scored.first().map(|x| x.1).unwrap_or(0);
-                let cutoff = (best_score as f64 * threshold) as i64;
+            rebuild(query);
 
-                scored.retain(|(_, score, _)| *score >= cutoff);
+            let Some((id, previous_row)) = kept else {
+                return;
+            };
+            let row = restored_row(&listed(), &id, previous_row)
+                .and_then(|row| result_row(&list_box, row));
+            if let Some(row) = row {
+                list_box.select_row(Some(&row));
+                scroll_row_into_view(&list_box, &row);
             }
+        })
+    };
+
+    // With debounce enabled, every keystroke schedules a timeout but only the
+    // one that finds the query quiet for the full delay re-filters.
+    let last_change_ms = Rc::new(Cell::new(0_i64));
+    let refresh_pending = Rc::new(Cell::new(false));
+
+    {
+        let refresh = refresh.clone();
+        let last_change_ms = last_change_ms.clone();
+        let refresh_pending = refresh_pending.clone();
 
-            for (i, _score, _) in scored.into_iter().take(max_results) {
-                filtered.push(i);
+        entry.connect_changed(move |entry| {
+            if debounce_ms == 0 {
+                refresh(&entry.text());
+                return;
             }
 
-            populate_list(
-                &list_box,
-                &apps,
-                &filtered,
-                show_shortcuts,
-                show_descriptions,
-            );
-            select_first(&list_box);
+            last_change_ms.set(monotonic_ms());
+            refresh_pending.set(true);
+
+            let refresh = refresh.clone();
+            let last_change_ms = last_change_ms.clone();
+            let refresh_pending = refresh_pending.clone();
+            let entry = entry.clone();
+            glib::timeout_add_local_once(Duration::from_millis(debounce_ms), move || {
+                if refresh_pending.get()
+                    && debounce_elapsed(last_change_ms.get(), monotonic_ms(), debounce_ms)
+                {
+                    refresh_pending.set(false);
+                    refresh(&entry.text());
+                }
+            });
         });
     }
 
-    let activate_selection = {
+    let confirmation = Rc::new(RefCell::new(Confirmation::default()));
+    let last_launch = Rc::new(RefCell::new(LastLaunch::default()));
+
+    // The app behind a row, from the search results or the query rows.
+    let app_at_row = {
         let apps = apps.clone();
         let filtered_apps = filtered_apps.clone();
+        let query_apps = query_apps.clone();
+
+        Rc::new(move |row_idx: usize| -> Option<App> {
+            let app_idx = filtered_apps.borrow().get(row_idx).copied();
+            match app_idx {
+                Some(app_idx) => Some(apps[app_idx].clone()),
+                None => query_apps.borrow().get(row_idx).cloned(),
+            }
+        })
+    };
+
+    let activate_selection = {
+        let app_at_row = app_at_row.clone();
         let window = window.clone();
+        let entry = entry.clone();
         let on_select = on_select.clone();
+        let confirmation = confirmation.clone();
+        let confirm_label = confirm_label.clone();
+        let last_launch = last_launch.clone();
+        let rebuild = rebuild.clone();
+        let refresh_pending = refresh_pending.clone();
 
         Rc::new(move |row_idx: usize| {
-            let app_idx = filtered_apps.borrow().get(row_idx).copied();
-            if let Some(app_idx) = app_idx {
-                on_select(&apps[app_idx]);
-                window.close();
+            if let Some(app) = app_at_row(row_idx) {
+                if !confirmation.borrow_mut().activate(row_idx, app.confirm) {
+                    confirm_label.set_text(&format!("Press Enter again to launch {}", app.name));
+                    confirm_label.set_visible(true);
+                    return;
+                }
+                confirm_label.set_visible(false);
+                if !last_launch
+                    .borrow_mut()
+                    .allows(&app, monotonic_ms(), launch_debounce_ms)
+                {
+                    return;
+                }
+                on_select(&app);
+                match after_launch(stay_open) {
+                    AfterLaunch::Close => window.close(),
+                    AfterLaunch::Reset(reset) => {
+                        reset_list(&reset, &entry, &*rebuild, &refresh_pending)
+                    }
+                }
+            }
+        })
+    };
+
+    let copy_selection = {
+        let apps = apps.clone();
+        let filtered_apps = filtered_apps.clone();
+
+        move |row_idx: usize| {
+            if let Some(&app_idx) = filtered_apps.borrow().get(row_idx) {
+                crate::clipboard::copy_text(&apps[app_idx].name, copy_target);
+            }
+        }
+    };
+
+    // Ctrl+Enter or right-click lists a row's actions in place of the
+    // results; doing it again, or editing the query, goes back.
+    let toggle_actions = {
+        let app_at_row = app_at_row.clone();
+        let filtered_apps = filtered_apps.clone();
+        let query_apps = query_apps.clone();
+        let list_box = list_box.clone();
+        let feed = feed.clone();
+        let fit_width = fit_width.clone();
+        let entry = entry.clone();
+        let refresh = refresh.clone();
+        let showing_actions = showing_actions.clone();
+
+        Rc::new(move |row_idx: usize| {
+            if showing_actions.get() {
+                refresh(&entry.text());
+                return;
             }
+            let actions = app_at_row(row_idx)
+                .map(|app| app.action_apps())
+                .unwrap_or_default();
+            if actions.is_empty() {
+                return;
+            }
+
+            filtered_apps.borrow_mut().clear();
+            let indices: Vec<usize> = (0..actions.len()).collect();
+            let name_labels =
+                populate_list(&list_box, &actions, &indices, row_style, &[], &[], &feed);
+            *query_apps.borrow_mut() = actions;
+            showing_actions.set(true);
+            fit_width(&name_labels);
+            reset_selection(&list_box, true);
         })
     };
 
+    {
+        let toggle_actions = toggle_actions.clone();
+        let list_box_click = list_box.clone();
+        let right_click = gtk4::GestureClick::new();
+        right_click.set_button(gtk4::gdk::BUTTON_SECONDARY);
+        right_click.connect_pressed(move |_, _, _, y| {
+            let Some(row) = list_box_click
+                .row_at_y(y as i32)
+                .filter(|row| row.is_selectable())
+            else {
+                return;
+            };
+            list_box_click.select_row(Some(&row));
+            if let Some(position) = result_position(&list_box_click, &row) {
+                toggle_actions(position);
+            }
+        });
+        list_box.add_controller(right_click);
+    }
+
     {
         let list_box = list_box.clone();
         let activate = activate_selection.clone();
 
-        entry.connect_activate(move |_| {
-            if let Some(row) = list_box.selected_row() {
-                activate(row.index() as usize);
+        let refresh = refresh.clone();
+        let refresh_pending = refresh_pending.clone();
+
+        entry.connect_activate(move |entry| {
+            // Enter can beat the debounce timeout; never act on stale results.
+            if refresh_pending.replace(false) {
+                refresh(&entry.text());
+            }
+            let selected = list_box
+                .selected_row()
+                .and_then(|row| result_position(&list_box, &row));
+            if let Some(row_idx) = enter_target(selected, row_count(&list_box), enter_launches_top)
+            {
+                activate(row_idx);
             }
         });
     }
 
     {
         let list_box_nav = list_box.clone();
+        let feed_nav = feed.clone();
         let window_close = window.clone();
         let activate = activate_selection.clone();
 
@@ -251,12 +605,12 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
             let mut moved = false;
 
             while *acc >= 1.0 {
-                move_selection(&list_box_scroll, 1);
+                move_selection(&list_box_scroll, 1, false);
                 *acc -= 1.0;
                 moved = true;
             }
             while *acc <= -1.0 {
-                move_selection(&list_box_scroll, -1);
+                move_selection(&list_box_scroll, -1, false);
                 *acc += 1.0;
                 moved = true;
             }
@@ -276,6 +630,18 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
 
         let key_controller = gtk4::EventControllerKey::new();
         key_controller.connect_key_pressed(move |_, key, _, modifiers| {
+            if modifiers.contains(ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK)
+                && key.to_lower() == gtk4::gdk::Key::c
+            {
+                let selected = list_box_nav
+                    .selected_row()
+                    .and_then(|row| result_position(&list_box_nav, &row));
+                if let Some(row_idx) = selected {
+                    copy_selection(row_idx);
+                }
+                return gtk4::glib::Propagation::Stop;
+            }
+
             if modifiers.contains(ModifierType::ALT_MASK) {
                 let num = match key {
                     gtk4::gdk::Key::_1 => Some(0),
@@ -296,210 +662,1317 @@ pub fn build_ui(app: &Application, config: &Config, apps: Vec<App>, on_select: R
                 }
             }
 
-            match key {
-                gtk4::gdk::Key::Escape => {
-                    window_close.close();
-                    gtk4::glib::Propagation::Stop
-                }
-                gtk4::gdk::Key::Up => {
-                    move_selection(&list_box_nav, -1);
-                    gtk4::glib::Propagation::Stop
-                }
-                gtk4::gdk::Key::Down => {
-                    move_selection(&list_box_nav, 1);
-                    gtk4::glib::Propagation::Stop
-                }
-                gtk4::gdk::Key::Tab => {
-                    move_selection(&list_box_nav, 1);
-                    gtk4::glib::Propagation::Stop
+            let page = max_results.max(1) as isize;
+            let delta = match key {
+                gtk4::gdk::Key::Up => -1,
+                gtk4::gdk::Key::Down | gtk4::gdk::Key::Tab => 1,
+                gtk4::gdk::Key::Page_Up => -page,
+                gtk4::gdk::Key::Page_Down => page,
+                _ => return gtk4::glib::Propagation::Proceed,
+            };
+            let current = list_box_nav
+                .selected_row()
+                .and_then(|row| result_position(&list_box_nav, &row));
+            feed_nav.reach(position_needed(current, delta, wrap_navigation));
+            move_selection(&list_box_nav, delta, wrap_navigation);
+            gtk4::glib::Propagation::Stop
+        });
+
+        window.add_controller(key_controller);
+
+        // Capture phase: the entry would otherwise swallow Ctrl+C as copy.
+        let cancel_controller = gtk4::EventControllerKey::new();
+        cancel_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let confirmation = confirmation.clone();
+        let confirm_label = confirm_label.clone();
+        let list_box_actions = list_box.clone();
+        cancel_controller.connect_key_pressed(move |_, key, _, modifiers| {
+            if !keeps_confirmation(key, modifiers) && confirmation.borrow_mut().cancel() {
+                confirm_label.set_visible(false);
+            }
+
+            // Before the entry, which would take it as a plain Enter.
+            if modifiers.contains(ModifierType::CONTROL_MASK) && is_enter(key) {
+                let selected = list_box_actions
+                    .selected_row()
+                    .and_then(|row| result_position(&list_box_actions, &row));
+                if let Some(row_idx) = enter_target(selected, row_count(&list_box_actions), true) {
+                    toggle_actions(row_idx);
                 }
-                _ => gtk4::glib::Propagation::Proceed,
+                return gtk4::glib::Propagation::Stop;
+            }
+
+            let key_name = key.name().unwrap_or_default();
+            if cancel_keys.iter().any(|c| c.matches(&key_name, modifiers)) {
+                window_close.close();
+                gtk4::glib::Propagation::Stop
+            } else {
+                gtk4::glib::Propagation::Proceed
             }
         });
 
-        window.add_controller(key_controller);
+        window.add_controller(cancel_controller);
+    }
+
+    if descriptions == DescriptionStyle::Tooltip {
+        // Keyboard navigation selects rows while focus stays in the entry,
+        // so mirror the selected row's description onto the entry for
+        // screen readers and hover.
+        let entry = entry.clone();
+        list_box.connect_row_selected(move |_, row| {
+            let desc = row.and_then(|row| row.tooltip_text());
+            entry.set_tooltip_text(desc.as_deref());
+            match desc {
+                Some(desc) => {
+                    entry.update_property(&[gtk4::accessible::Property::Description(&desc)])
+                }
+                None => entry.reset_property(gtk4::AccessibleProperty::Description),
+            }
+        });
     }
 
     {
         let activate = activate_selection.clone();
-        list_box.connect_row_activated(move |_, row| {
-            activate(row.index() as usize);
+        list_box.connect_row_activated(move |list_box, row| {
+            if let Some(row_idx) = result_position(list_box, row) {
+                activate(row_idx);
+            }
         });
     }
 
     entry.grab_focus();
     window.present();
-}
 
-/// Indices shown before any query: favorites first, then most recently
-/// launched, then the pre-sorted (alphabetical) order. `count == 0` means
-/// show everything (scrollable full list).
-fn initial_indices(apps: &[App], history: &HashMap<String, u64>, count: usize) -> Vec<usize> {
-    let mut indices: Vec<usize> = (0..apps.len()).collect();
-    if !history.is_empty() {
-        indices.sort_by_key(|&i| {
-            (
-                !apps[i].favorite,
-                Reverse(history.get(&apps[i].name).copied().unwrap_or(0)),
-            )
+    if let (Some(timing), Some(clock)) = (timing, window.frame_clock()) {
+        let painted = Cell::new(false);
+        clock.connect_after_paint(move |_| {
+            if !painted.replace(true) {
+                Phase::new(
+                    "first paint",
+                    timing.since_start(),
+                    Some("since start".to_string()),
+                )
+                .report();
+            }
         });
     }
-    if count > 0 {
-        indices.truncate(count);
-    }
-    indices
+
+    LauncherWindow { window, entry, css }
 }
 
-fn populate_list(
-    list_box: &ListBox,
-    apps: &[App],
-    indices: &[usize],
-    show_shortcuts: bool,
-    show_descriptions: bool,
-) {
-    while let Some(row) = list_box.row_at_index(0) {
-        list_box.remove(&row);
-    }
+/// The parts of a built window that `control` commands act on.
+pub struct LauncherWindow {
+    window: ApplicationWindow,
+    entry: Entry,
+    css: CssProvider,
+}
 
-    for (display_idx, &app_idx) in indices.iter().enumerate() {
-        let app = &apps[app_idx];
-        let shortcut = if show_shortcuts && display_idx < 9 {
-            Some(display_idx + 1)
+impl LauncherWindow {
+    /// Hides the window, or shows it again with the search focused.
+    pub fn toggle(&self) {
+        if self.window.is_visible() {
+            self.window.set_visible(false);
         } else {
-            None
-        };
-        let row = create_app_row(app, shortcut, show_descriptions);
-        list_box.append(&row);
+            self.entry.grab_focus();
+            self.window.present();
+        }
     }
-}
-
-fn create_app_row(app: &App, shortcut: Option<usize>, show_descriptions: bool) -> ListBoxRow {
-    let hbox = GtkBox::new(Orientation::Horizontal, 10);
-    hbox.set_margin_top(8);
-    hbox.set_margin_bottom(8);
-    hbox.set_margin_start(12);
-    hbox.set_margin_end(12);
-    hbox.add_css_class("yeet-row-content");
 
-    if let Some(icon_name) = &app.icon {
-        let icon = gtk4::Image::from_icon_name(icon_name);
-        icon.set_pixel_size(36);
-        icon.add_css_class("yeet-icon");
-        hbox.append(&icon);
+    /// Searches for `query` as if it had been typed.
+    pub fn set_query(&self, query: &str) {
+        self.entry.set_text(query);
+        self.entry.set_position(-1);
+        self.entry.grab_focus();
+        self.window.present();
     }
 
-    let text_box = GtkBox::new(Orientation::Vertical, 2);
-    text_box.set_hexpand(true);
-    text_box.set_valign(gtk4::Align::Center);
-
-    let name_label = Label::new(Some(&app.name));
-    name_label.set_halign(gtk4::Align::Start);
-    name_label.add_css_class("yeet-app-name");
-    text_box.append(&name_label);
+    pub fn close(&self) {
+        self.window.close();
+    }
 
-    if let Some(desc) = app.description.as_ref().filter(|_| show_descriptions) {
-        let desc_label = Label::new(Some(desc));
-        desc_label.set_halign(gtk4::Align::Start);
-        desc_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
-        desc_label.add_css_class("yeet-app-desc");
-        text_box.append(&desc_label);
+    /// `--theme-preview`: F5 switches to the next of `themes` and Shift+F5
+    /// to the previous, naming the one shown in the search placeholder.
+    /// Starts from `current` when it's one of them.
+    pub fn preview_themes(&self, themes: Vec<Preview>, current: Option<&str>) {
+        if themes.is_empty() {
+            return;
+        }
+        let shown = Cell::new(current.and_then(|name| themes.iter().position(|t| t.name == name)));
+        let placeholder = |name: &str| format!("Theme: {name} (F5 next, Shift+F5 previous)");
+        self.entry.set_placeholder_text(Some(&placeholder(
+            current.filter(|_| shown.get().is_some()).unwrap_or("yours"),
+        )));
+
+        let entry = self.entry.clone();
+        let css = self.css.clone();
+        let key_controller = gtk4::EventControllerKey::new();
+        key_controller.connect_key_pressed(move |_, key, _, modifiers| {
+            if key != gtk4::gdk::Key::F5 {
+                return gtk4::glib::Propagation::Proceed;
+            }
+            let forward = !modifiers.contains(ModifierType::SHIFT_MASK);
+            if let Some(i) = cycle(shown.get(), themes.len(), forward) {
+                let theme = &themes[i];
+                match theme.style() {
+                    Ok(style) => css.load_from_data(&style),
+                    Err(e) => eprintln!("yeet: theme {}: {e}", theme.name),
+                }
+                entry.set_placeholder_text(Some(&placeholder(&theme.name)));
+                shown.set(Some(i));
+            }
+            gtk4::glib::Propagation::Stop
+        });
+        self.window.add_controller(key_controller);
     }
+}
 
-    hbox.append(&text_box);
+/// Rewrites multi-line text pasted or dropped into `entry` as one line;
+/// typing never inserts a line break, so anything else passes untouched.
+fn flatten_pasted_lines(entry: &Entry, mode: MultilinePaste) {
+    let Some(text) = entry.delegate() else {
+        return;
+    };
+    text.connect_insert_text(move |editable, inserted, position| {
+        if let Some(line) = single_line(inserted, mode) {
+            editable.stop_signal_emission_by_name("insert-text");
+            editable.insert_text(&line, position);
+        }
+    });
+}
 
-    if let Some(num) = shortcut {
-        let shortcut_label = Label::new(Some(&format!("Alt+{}", num)));
-        shortcut_label.set_valign(gtk4::Align::Center);
-        shortcut_label.add_css_class("yeet-shortcut");
-        hbox.append(&shortcut_label);
+/// `text` made a single line per `mode`, ignoring blank lines and the
+/// whitespace around each line; `None` when it has no line breaks.
+fn single_line(text: &str, mode: MultilinePaste) -> Option<String> {
+    if !text.contains('\n') {
+        return None;
     }
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    Some(match mode {
+        MultilinePaste::Join => lines.collect::<Vec<_>>().join(" "),
+        MultilinePaste::FirstLine => lines.next().unwrap_or_default().to_string(),
+    })
+}
 
-    let row = ListBoxRow::new();
-    row.set_child(Some(&hbox));
-    row.add_css_class("yeet-row");
-    row
+/// Activation tokens for launched apps, each handed out once. A token
+/// lets a single window take focus, so a launch that would get one already
+/// used (several launches in quick succession with `stay_open`) gets none
+/// instead.
+#[derive(Default)]
+pub struct ActivationTokens {
+    issued: HashSet<String>,
 }
 
-fn select_first(list_box: &ListBox) {
-    if let Some(row) = list_box.row_at_index(0) {
-        list_box.select_row(Some(&row));
+impl ActivationTokens {
+    /// A fresh token for the next launch.
+    pub fn next(&mut self) -> Option<String> {
+        self.claim(activation_token())
     }
-    if let Some(adj) = list_box.adjustment() {
-        adj.set_value(0.0);
+
+    fn claim(&mut self, token: Option<String>) -> Option<String> {
+        token.filter(|token| self.issued.insert(token.clone()))
     }
 }
 
-fn move_selection(list_box: &ListBox, delta: i32) {
-    let current = list_box.selected_row().map(|r| r.index()).unwrap_or(-1);
-    let new_idx = (current + delta).max(0);
-    if let Some(row) = list_box.row_at_index(new_idx) {
-        list_box.select_row(Some(&row));
-        scroll_row_into_view(list_box, &row);
-    }
+/// A token from the compositor that lets the next app launched take focus
+/// (xdg-activation on Wayland), requested while yeet still has it.
+fn activation_token() -> Option<String> {
+    let context = Display::default()?.app_launch_context();
+    context
+        .startup_notify_id(None::<&gtk4::gio::AppInfo>, &[])
+        .map(|token| token.to_string())
 }
 
-fn scroll_row_into_view(list_box: &ListBox, row: &ListBoxRow) {
-    let Some(adj) = list_box.adjustment() else {
-        return;
+fn select_monitor(selector: &MonitorSelector, fallback: MonitorFallback) -> Option<Monitor> {
+    let model = Display::default()?.monitors();
+    let monitors: Vec<Monitor> = (0..model.n_items())
+        .filter_map(|i| model.item(i).and_downcast::<Monitor>())
+        .collect();
+    let connectors: Vec<Option<String>> = monitors
+        .iter()
+        .map(|m| m.connector().map(|c| c.to_string()))
+        .collect();
+
+    let index = monitor_index(selector, &connectors, fallback)?;
+    monitors.into_iter().nth(index)
+}
+
+/// Position of the configured monitor among the connected ones. When it
+/// isn't connected (e.g. after unplugging a display) this warns and applies
+/// `general.monitor_fallback`; `None` leaves placement to the compositor,
+/// which opens on the active monitor.
+fn monitor_index(
+    selector: &MonitorSelector,
+    connectors: &[Option<String>],
+    fallback: MonitorFallback,
+) -> Option<usize> {
+    let found = match selector {
+        MonitorSelector::Index(index) => {
+            Some(*index as usize).filter(|&index| index < connectors.len())
+        }
+        MonitorSelector::Connector(name) => connectors
+            .iter()
+            .position(|connector| connector.as_deref() == Some(name.as_str())),
     };
-    let alloc = row.allocation();
-    let row_top = alloc.y() as f64;
-    let row_bottom = row_top + alloc.height() as f64;
+    if found.is_some() {
+        return found;
+    }
 
-    if row_top < adj.value() {
-        adj.set_value(row_top);
-    } else if row_bottom > adj.value() + adj.page_size() {
-        adj.set_value(row_bottom - adj.page_size());
+    let clamped = match (selector, fallback) {
+        (MonitorSelector::Index(index), MonitorFallback::Clamp) => connectors
+            .len()
+            .checked_sub(1)
+            .map(|last| (*index as usize).min(last)),
+        _ => None,
+    };
+    match clamped {
+        Some(index) => eprintln!("Warning: monitor {selector} not found, using monitor {index}"),
+        None => eprintln!("Warning: monitor {selector} not found, using the active monitor"),
     }
+    clamped
 }
 
-fn recency_boost(history: &HashMap<String, u64>, app_name: &str, now: u64) -> i64 {
-    history
-        .get(app_name)
-        .map(|&last| {
-            let age_hours = (now.saturating_sub(last)) / 3600;
-            match age_hours {
-                0..=24 => 100,
-                25..=168 => 50,
-                _ => 20,
-            }
-        })
-        .unwrap_or(0)
+const COMBO_MODIFIERS: ModifierType = ModifierType::CONTROL_MASK
+    .union(ModifierType::ALT_MASK)
+    .union(ModifierType::SHIFT_MASK)
+    .union(ModifierType::SUPER_MASK);
+
+/// A `general.cancel_keys` entry such as `"Escape"`, `"ctrl+c"` or `"ctrl+["`.
+#[derive(Debug, PartialEq, Eq)]
+struct KeyCombo {
+    modifiers: ModifierType,
+    key: String,
 }
 
-fn load_css() {
-    let provider = CssProvider::new();
+impl KeyCombo {
+    fn parse(spec: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty())?;
+
+        let mut modifiers = ModifierType::empty();
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => ModifierType::CONTROL_MASK,
+                "alt" => ModifierType::ALT_MASK,
+                "shift" => ModifierType::SHIFT_MASK,
+                "super" => ModifierType::SUPER_MASK,
+                _ => return None,
+            };
+        }
+
+        // GDK key names; accept the characters people actually type too.
+        let key = match key {
+            "[" => "bracketleft",
+            "]" => "bracketright",
+            k if k.eq_ignore_ascii_case("esc") => "Escape",
+            k => k,
+        };
 
-    let user_style = Config::user_style_path().filter(|p| p.exists());
-    match user_style {
-        Some(path) => provider.load_from_path(&path),
-        None => provider.load_from_data(DEFAULT_STYLE),
+        Some(Self {
+            modifiers,
+            key: key.to_string(),
+        })
     }
 
-    gtk4::style_context_add_provider_for_display(
-        &Display::default().expect("Could not get default display"),
-        &provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_USER,
-    );
+    fn matches(&self, key_name: &str, modifiers: ModifierType) -> bool {
+        modifiers & COMBO_MODIFIERS == self.modifiers && key_name.eq_ignore_ascii_case(&self.key)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Debug, PartialEq, Eq)]
+enum AfterLaunch {
+    Close,
+    Reset(ListReset),
+}
 
-    fn plain_apps(names: &[&str]) -> Vec<App> {
-        names.iter().map(|n| App::plain(n.to_string())).collect()
-    }
+/// Where a launch in sticky mode leaves the window.
+#[derive(Debug, PartialEq, Eq)]
+struct ListReset {
+    /// Put in the entry; empty brings back the initial list.
+    query: String,
+    /// Rebuild the list for `query` right away, rather than when the entry
+    /// reports a change (not at all if it was already empty) or after the
+    /// debounce, so an app's actions or a scrolled list don't outlive the
+    /// launch.
+    rebuild_list: bool,
+    focus_entry: bool,
+}
 
-    #[test]
-    fn initial_indices_surfaces_recent_apps_beyond_first_n() {
-        // Alphabetical list; "zoom" was launched recently but sorts last.
-        let apps = plain_apps(&["alpha", "beta", "gamma", "zoom"]);
-        let mut history = HashMap::new();
-        history.insert("zoom".to_string(), 1000);
+/// What the window does once a selection has been handed off. Sticky mode
+/// resets to the empty query so several apps can be launched in a row.
+fn after_launch(stay_open: bool) -> AfterLaunch {
+    if stay_open {
+        AfterLaunch::Reset(ListReset {
+            query: String::new(),
+            rebuild_list: true,
+            focus_entry: true,
+        })
+    } else {
+        AfterLaunch::Close
+    }
+}
 
-        let indices = initial_indices(&apps, &history, 2);
-        assert_eq!(indices, vec![3, 0]);
+/// Applies `reset` to the entry and list; `rebuild` lists results for a
+/// query, and `refresh_pending` marks a debounced refresh still to come.
+fn reset_list(
+    reset: &ListReset,
+    entry: &Entry,
+    rebuild: &dyn Fn(&str),
+    refresh_pending: &Cell<bool>,
+) {
+    let unchanged = entry.text() == reset.query;
+    entry.set_text(&reset.query);
+    // A changed query has just been refreshed, unless that waits for the
+    // debounce.
+    if reset.rebuild_list && (unchanged || refresh_pending.replace(false)) {
+        rebuild(&reset.query);
+    }
+    if reset.focus_entry {
+        entry.grab_focus();
+    }
+}
+
+/// The `general.empty_order` actually in effect: without
+/// `apps.favorites_in_list`, favorites aren't grouped.
+fn list_order(order: &[ListOrder], favorites_in_list: bool) -> Vec<ListOrder> {
+    order
+        .iter()
+        .copied()
+        .filter(|&key| favorites_in_list || key != ListOrder::Favorites)
+        .collect()
+}
+
+/// Indices shown before any query, sorted by each key of `order` in turn
+/// (favorites first, most recently launched first, alphabetical), each
+/// breaking the ties of the keys before it; what's still tied keeps
+/// discovery order. `count == 0` means show everything (scrollable full
+/// list).
+fn initial_indices(
+    apps: &[App],
+    history: &HashMap<String, u64>,
+    count: usize,
+    order: &[ListOrder],
+) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..apps.len()).collect();
+    // apps arrive favorites-first from discovery, so alphabetical order
+    // needs the names collated again.
+    let alpha_keys: Vec<Vec<u8>> = if order.contains(&ListOrder::Alpha) {
+        let collator = Collator::from_env();
+        apps.iter()
+            .map(|app| collator.sort_key(&app.name))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let last_launch = |i: usize| history.get(&apps[i].name).copied().unwrap_or(0);
+    indices.sort_by(|&a, &b| {
+        order.iter().fold(Ordering::Equal, |ordering, key| {
+            ordering.then_with(|| match key {
+                ListOrder::Favorites => apps[b].favorite.cmp(&apps[a].favorite),
+                ListOrder::Recent => last_launch(b).cmp(&last_launch(a)),
+                ListOrder::Alpha => alpha_keys[a].cmp(&alpha_keys[b]),
+            })
+        })
+    });
+    if count > 0 {
+        indices.truncate(count);
+    }
+    indices
+}
+
+/// Indices shown for a query: the best `max_results` of `ranked`. The list
+/// before typing is capped by `initial_results` instead (`initial_indices`).
+fn search_indices(ranked: Vec<(usize, i64)>, max_results: usize) -> Vec<usize> {
+    ranked
+        .into_iter()
+        .take(max_results)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Groups of the list shown before typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Favorites,
+    Recent,
+    All,
+}
+
+impl Section {
+    /// The first group of `order` that `app` is in. Groups after `alpha`
+    /// don't split the list, so they don't count.
+    fn of(app: &App, history: &HashMap<String, u64>, order: &[ListOrder]) -> Self {
+        for key in order {
+            match key {
+                ListOrder::Favorites if app.favorite => return Self::Favorites,
+                ListOrder::Recent if history.contains_key(&app.name) => return Self::Recent,
+                ListOrder::Alpha => break,
+                _ => {}
+            }
+        }
+        Self::All
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Favorites => "Favorites",
+            Self::Recent => "Recent",
+            Self::All => "All",
+        }
+    }
+}
+
+/// The list shown before typing (`initial_indices`) with its headers: by
+/// category group with `appearance.group_by_category`, which takes the
+/// place of `appearance.show_sections`, or by section.
+fn browse_list(
+    apps: &[App],
+    history: &HashMap<String, u64>,
+    count: usize,
+    order: &[ListOrder],
+    show_sections: bool,
+    group_by_category: bool,
+) -> (Vec<usize>, Vec<(usize, &'static str)>) {
+    let mut indices = initial_indices(apps, history, count, order);
+    let headers = if group_by_category {
+        crate::desktop::group_by_category(apps, &mut indices)
+    } else {
+        section_headers(apps, &indices, history, order, show_sections)
+    };
+    (indices, headers)
+}
+
+/// `(position, title)` of each section header in the list shown before
+/// typing, which `initial_indices` groups by `order`. A list that falls in
+/// one section gets no headers.
+fn section_headers(
+    apps: &[App],
+    indices: &[usize],
+    history: &HashMap<String, u64>,
+    order: &[ListOrder],
+    enabled: bool,
+) -> Vec<(usize, &'static str)> {
+    if !enabled {
+        return Vec::new();
+    }
+    let mut headers: Vec<(usize, Section)> = Vec::new();
+    for (position, &i) in indices.iter().enumerate() {
+        let section = Section::of(&apps[i], history, order);
+        if headers.last().map(|&(_, last)| last) != Some(section) {
+            headers.push((position, section));
+        }
+    }
+    if headers.len() < 2 {
+        return Vec::new();
+    }
+    headers
+        .into_iter()
+        .map(|(position, section)| (position, section.title()))
+        .collect()
+}
+
+fn populate_list(
+    list_box: &ListBox,
+    apps: &[App],
+    indices: &[usize],
+    style: RowStyle,
+    matches: &[Option<MatchedOn>],
+    headers: &[(usize, &str)],
+    feed: &Rc<RowFeed>,
+) -> Vec<Label> {
+    while let Some(row) = list_box.row_at_index(0) {
+        list_box.remove(&row);
+    }
+
+    let built = first_batch(indices.len(), style.fixed_rows);
+    let mut name_labels = Vec::with_capacity(built);
+    let mut pending_icons = Vec::with_capacity(built);
+
+    for (display_idx, &app_idx) in indices[..built].iter().enumerate() {
+        for &(_, title) in headers.iter().filter(|(at, _)| *at == display_idx) {
+            list_box.append(&create_section_row(title));
+        }
+        let app = &apps[app_idx];
+        let shortcut = if style.shortcuts && display_idx < 9 {
+            Some(display_idx + 1)
+        } else {
+            None
+        };
+        let (row, name_label, icon) = create_app_row(
+            app,
+            shortcut,
+            style,
+            matches.get(display_idx).and_then(Option::as_ref),
+        );
+        list_box.append(&row);
+        name_labels.push(name_label);
+        pending_icons.push(icon.zip(app.icon.clone()));
+    }
+    let pending = indices[built..]
+        .iter()
+        .enumerate()
+        .map(|(offset, &app_idx)| {
+            let display_idx = built + offset;
+            PendingRow {
+                headers: headers
+                    .iter()
+                    .filter(|(at, _)| *at == display_idx)
+                    .map(|(_, title)| title.to_string())
+                    .collect(),
+                app: apps[app_idx].clone(),
+                matched: matches.get(display_idx).cloned().flatten(),
+            }
+        })
+        .collect();
+    // Only short lists are padded, and those are built in full.
+    for _ in 0..padding_rows(indices.len(), style.fixed_rows) {
+        list_box.append(&create_padding_row(style.layout));
+    }
+    feed.icons.reset(pending_icons);
+    feed.reset(style, built, pending);
+    name_labels
+}
+
+/// Result rows built when a list is populated. Longer lists get the rest a
+/// batch at a time as scrolling or the selection nears the end, so the
+/// full app list or a big dmenu input doesn't build every widget up front.
+const ROW_BATCH: usize = 100;
+
+/// Result rows to build up front out of `total`: a batch, or all of a list
+/// short enough to be padded to `fixed_rows`.
+fn first_batch(total: usize, fixed_rows: usize) -> usize {
+    total.min(ROW_BATCH.max(fixed_rows))
+}
+
+/// Whether the view is within a page of the end of the list, where the
+/// next batch is due. Nothing is due before the list has been laid out.
+fn near_end(value: f64, page_size: f64, upper: f64) -> bool {
+    page_size > 0.0 && upper - (value + page_size) <= page_size
+}
+
+/// The result position a keyboard move of `delta` from `current` needs
+/// built: past the end for a wrap from the first result back to the last.
+fn position_needed(current: Option<usize>, delta: isize, wrap: bool) -> usize {
+    match current {
+        Some(0) if wrap && delta < 0 => usize::MAX,
+        current => current.unwrap_or(0).saturating_add_signed(delta),
+    }
+}
+
+/// A result row not built yet, with the section headers that go above it.
+struct PendingRow {
+    headers: Vec<String>,
+    app: App,
+    matched: Option<MatchedOn>,
+}
+
+/// Builds the rows `populate_list` left for later (see `ROW_BATCH`) when
+/// the view nears the end of the list, and hands their icons to the
+/// `IconLoader`.
+struct RowFeed {
+    list_box: ListBox,
+    adjustment: gtk4::Adjustment,
+    icons: Rc<IconLoader>,
+    style: Cell<Option<RowStyle>>,
+    pending: RefCell<Vec<PendingRow>>,
+    /// Result rows built so far.
+    built: Cell<usize>,
+}
+
+impl RowFeed {
+    fn new(icons: Rc<IconLoader>, list_box: &ListBox, scrolled: &ScrolledWindow) -> Rc<Self> {
+        let feed = Rc::new(Self {
+            list_box: list_box.clone(),
+            adjustment: scrolled.vadjustment(),
+            icons,
+            style: Cell::new(None),
+            pending: RefCell::new(Vec::new()),
+            built: Cell::new(0),
+        });
+
+        // Scrolling, and the list growing or the window resizing.
+        let weak = Rc::downgrade(&feed);
+        let check = move |adjustment: &gtk4::Adjustment| {
+            if let Some(feed) = weak.upgrade() {
+                if near_end(
+                    adjustment.value(),
+                    adjustment.page_size(),
+                    adjustment.upper(),
+                ) {
+                    feed.build_batch();
+                }
+            }
+        };
+        feed.adjustment.connect_value_changed(check.clone());
+        feed.adjustment.connect_changed(check);
+        feed
+    }
+
+    /// Starts over for a freshly populated list with `built` result rows
+    /// and `pending` still to come.
+    fn reset(&self, style: RowStyle, built: usize, pending: Vec<PendingRow>) {
+        self.style.set(Some(style));
+        self.built.set(built);
+        *self.pending.borrow_mut() = pending;
+    }
+
+    /// Builds up to `ROW_BATCH` more rows; `false` once none are left.
+    fn build_batch(&self) -> bool {
+        let Some(style) = self.style.get() else {
+            return false;
+        };
+        let batch: Vec<PendingRow> = {
+            let mut pending = self.pending.borrow_mut();
+            let take = pending.len().min(ROW_BATCH);
+            pending.drain(..take).collect()
+        };
+        if batch.is_empty() {
+            return false;
+        }
+
+        let mut icons = Vec::with_capacity(batch.len());
+        for pending in batch {
+            for title in &pending.headers {
+                self.list_box.append(&create_section_row(title));
+            }
+            // Shortcut badges only go on the first nine rows, always built.
+            let (row, _, icon) =
+                create_app_row(&pending.app, None, style, pending.matched.as_ref());
+            self.list_box.append(&row);
+            icons.push(icon.zip(pending.app.icon));
+        }
+        self.built.set(self.built.get() + icons.len());
+        self.icons.extend(icons);
+        true
+    }
+
+    /// Builds batches until result `position` exists or nothing is left.
+    fn reach(&self, position: usize) {
+        while self.built.get() <= position && self.build_batch() {}
+    }
+}
+
+fn create_app_row(
+    app: &App,
+    shortcut: Option<usize>,
+    style: RowStyle,
+    matched: Option<&MatchedOn>,
+) -> (ListBoxRow, Label, Option<gtk4::Image>) {
+    let hbox = GtkBox::new(Orientation::Horizontal, 10);
+    hbox.set_margin_top(8);
+    hbox.set_margin_bottom(8);
+    hbox.set_margin_start(12);
+    hbox.set_margin_end(12);
+    hbox.add_css_class("yeet-row-content");
+
+    // An empty placeholder keeps the row's layout; `IconLoader` fills it in.
+    let icon = app.icon.as_ref().filter(|_| style.icons).map(|_| {
+        let icon = gtk4::Image::new();
+        icon.set_pixel_size(ICON_SIZE);
+        icon.add_css_class("yeet-icon");
+        hbox.append(&icon);
+        icon
+    });
+
+    let text_box = GtkBox::new(Orientation::Vertical, 2);
+    text_box.set_hexpand(true);
+    text_box.set_valign(gtk4::Align::Center);
+
+    let name_label = Label::new(Some(app.label()));
+    name_label.set_halign(gtk4::Align::Start);
+    name_label.add_css_class("yeet-app-name");
+    text_box.append(&name_label);
+
+    let (inline_desc, tooltip) = style.descriptions.place(app.description.as_deref());
+    if let Some(desc) = inline_desc {
+        let desc_label = Label::new(Some(desc));
+        desc_label.set_halign(gtk4::Align::Start);
+        desc_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        desc_label.add_css_class("yeet-app-desc");
+        text_box.append(&desc_label);
+    }
+
+    hbox.append(&text_box);
+
+    if let Some(num) = shortcut {
+        let shortcut_label = Label::new(Some(&format!("Alt+{}", num)));
+        shortcut_label.set_valign(gtk4::Align::Center);
+        shortcut_label.add_css_class("yeet-shortcut");
+        hbox.append(&shortcut_label);
+    }
+
+    let row = ListBoxRow::new();
+    row.set_child(Some(&hbox));
+    row.add_css_class("yeet-row");
+    if app.missing {
+        row.add_css_class("yeet-row-missing");
+    }
+    style.layout.apply(&row);
+    if let Some(desc) = tooltip {
+        row.set_tooltip_text(Some(desc));
+        row.update_property(&[gtk4::accessible::Property::Description(desc)]);
+    }
+    if style.accessibility {
+        let shown_desc = inline_desc.or(tooltip);
+        row.update_property(&[
+            gtk4::accessible::Property::Label(app.label()),
+            gtk4::accessible::Property::Description(&accessible_description(shown_desc, matched)),
+        ]);
+    }
+    (row, name_label, icon)
+}
+
+/// A non-selectable section title (`appearance.show_sections`).
+fn create_section_row(title: &str) -> ListBoxRow {
+    let label = Label::new(Some(title));
+    label.set_halign(gtk4::Align::Start);
+    label.add_css_class("yeet-section-title");
+
+    let row = ListBoxRow::new();
+    row.set_child(Some(&label));
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.set_can_focus(false);
+    row.add_css_class("yeet-section");
+    row
+}
+
+/// A blank, inert row filling out a short list (`appearance.fixed_list_size`).
+fn create_padding_row(row_layout: RowLayout) -> ListBoxRow {
+    let row = ListBoxRow::new();
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.set_can_focus(false);
+    row.set_can_target(false);
+    row.add_css_class("yeet-row-padding");
+    row_layout.apply(&row);
+    row
+}
+
+/// Blank rows to append after `results` rows so the list always has
+/// `fixed_rows` (0 = no padding).
+fn padding_rows(results: usize, fixed_rows: usize) -> usize {
+    fixed_rows.saturating_sub(results)
+}
+
+/// Fills in row icons from an idle callback after the rows are on screen,
+/// visible rows first, so resolving and decoding icons never holds up
+/// typing or scrolling. Rows scrolled far out of view wait until they come
+/// back; repopulating the list drops everything still pending.
+struct IconLoader {
+    lookup: IconLookup,
+    list_box: ListBox,
+    adjustment: gtk4::Adjustment,
+    /// Rows assumed visible before the list has been allocated.
+    page_rows: usize,
+    rows: RefCell<Vec<Option<(gtk4::Image, String)>>>,
+    queue: RefCell<IconQueue>,
+    scheduled: Cell<bool>,
+    /// For `--timing`: time spent on icons and how many were loaded, until
+    /// the first page is done and they're reported.
+    timed: Cell<Option<(Duration, usize)>>,
+}
+
+impl IconLoader {
+    fn new(
+        lookup: IconLookup,
+        list_box: &ListBox,
+        scrolled: &ScrolledWindow,
+        page_rows: usize,
+    ) -> Rc<Self> {
+        let loader = Rc::new(Self {
+            lookup,
+            list_box: list_box.clone(),
+            adjustment: scrolled.vadjustment(),
+            page_rows: page_rows.max(1),
+            rows: RefCell::new(Vec::new()),
+            queue: RefCell::new(IconQueue::default()),
+            scheduled: Cell::new(false),
+            timed: Cell::new(None),
+        });
+
+        let weak = Rc::downgrade(&loader);
+        loader.adjustment.connect_value_changed(move |_| {
+            if let Some(loader) = weak.upgrade() {
+                loader.schedule();
+            }
+        });
+        loader
+    }
+
+    /// Replaces the pending icons with those of a freshly populated list,
+    /// one entry per row: its placeholder image and icon name.
+    fn reset(self: &Rc<Self>, rows: Vec<Option<(gtk4::Image, String)>>) {
+        self.queue.borrow_mut().reset(rows.len());
+        *self.rows.borrow_mut() = rows;
+        self.schedule();
+    }
+
+    /// Queues the icons of rows appended to the current list.
+    fn extend(self: &Rc<Self>, rows: Vec<Option<(gtk4::Image, String)>>) {
+        {
+            let mut current = self.rows.borrow_mut();
+            let start = current.len();
+            current.extend(rows);
+            self.queue.borrow_mut().add(start..current.len());
+        }
+        self.schedule();
+    }
+
+    /// Starts timing icon work for `--timing`, counting `theme_load`, the
+    /// time taken to read the icon theme.
+    fn time(&self, theme_load: Duration) {
+        self.timed.set(Some((theme_load, 0)));
+    }
+
+    fn schedule(self: &Rc<Self>) {
+        if self.scheduled.replace(true) {
+            return;
+        }
+        let loader = self.clone();
+        glib::idle_add_local(move || {
+            if loader.load_next() {
+                glib::ControlFlow::Continue
+            } else {
+                loader.scheduled.set(false);
+                glib::ControlFlow::Break
+            }
+        });
+    }
+
+    /// Loads one icon; `false` once nothing near the view is left.
+    fn load_next(&self) -> bool {
+        let visible = self.visible_rows();
+        let lookahead = visible.len();
+        let Some(row) = self.queue.borrow_mut().next(visible, lookahead) else {
+            self.lookup.save_cache();
+            if let Some((spent, loaded)) = self.timed.take() {
+                Phase::new("icons", spent, Some(format!("{loaded} icons"))).report();
+            }
+            return false;
+        };
+        if let Some((image, name)) = self.rows.borrow_mut().get_mut(row).and_then(Option::take) {
+            let started = Instant::now();
+            match self.lookup.resolve(&name) {
+                Some(path) => image.set_from_file(Some(path)),
+                None => image.set_icon_name(Some(&name)),
+            }
+            if let Some((spent, loaded)) = self.timed.get() {
+                self.timed
+                    .set(Some((spent + started.elapsed(), loaded + 1)));
+            }
+        }
+        true
+    }
+
+    /// Rows currently on screen, or the first page until the list has been
+    /// laid out.
+    fn visible_rows(&self) -> std::ops::Range<usize> {
+        let top = self.adjustment.value();
+        let row_at = |y: f64| {
+            self.list_box
+                .row_at_y(y as i32)
+                .map(|row| results_before(&self.list_box, &row))
+        };
+        let start = row_at(top).unwrap_or(0);
+        let end = row_at(top + self.adjustment.page_size())
+            .map(|row| row + 1)
+            .unwrap_or(start + self.page_rows);
+        start..end.max(start + 1)
+    }
+}
+
+/// Row sizing from `appearance.row_height` and `appearance.row_spacing`.
+/// Spacing is split above and below each row, on top of any CSS margin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RowLayout {
+    height: i32,
+    spacing: i32,
+}
+
+impl RowLayout {
+    fn new(height: i32, spacing: i32) -> Self {
+        Self {
+            height: height.max(0),
+            spacing: spacing.max(0),
+        }
+    }
+
+    /// `(top, bottom)` margins for a row.
+    fn margins(self) -> (i32, i32) {
+        let top = self.spacing / 2;
+        (top, self.spacing - top)
+    }
+
+    /// Height of the scroll area showing `rows` rows without scrolling.
+    fn list_height(self, rows: usize) -> i32 {
+        (self.height + self.spacing).saturating_mul(rows as i32)
+    }
+
+    fn apply(self, row: &ListBoxRow) {
+        let (top, bottom) = self.margins();
+        row.set_size_request(-1, self.height);
+        row.set_margin_top(top);
+        row.set_margin_bottom(bottom);
+    }
+}
+
+/// What every result row is built with.
+#[derive(Debug, Clone, Copy)]
+struct RowStyle {
+    shortcuts: bool,
+    icons: bool,
+    descriptions: DescriptionStyle,
+    layout: RowLayout,
+    /// Rows to pad short lists to; see `padding_rows`.
+    fixed_rows: usize,
+    /// Give rows an accessible name and description (`general.accessibility`).
+    accessibility: bool,
+}
+
+/// Screen reader description of a result row: the description it shows,
+/// then why it matched the query. Plain text; rows are labelled with the
+/// app's name separately.
+fn accessible_description(description: Option<&str>, matched: Option<&MatchedOn>) -> String {
+    let reason = matched.map(|matched| match matched {
+        MatchedOn::Name => "matches name".to_string(),
+        MatchedOn::Keyword(keyword) => format!("matches keyword {keyword}"),
+    });
+    description
+        .into_iter()
+        .map(str::to_string)
+        .chain(reason)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// How rows show app descriptions (`appearance.show_descriptions` and
+/// `appearance.description_as_tooltip`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DescriptionStyle {
+    Hidden,
+    Inline,
+    Tooltip,
+}
+
+impl DescriptionStyle {
+    fn from_settings(show: bool, as_tooltip: bool) -> Self {
+        match (show, as_tooltip) {
+            (false, _) => Self::Hidden,
+            (true, false) => Self::Inline,
+            (true, true) => Self::Tooltip,
+        }
+    }
+
+    /// Splits `description` into `(inline, tooltip)` text for a row.
+    fn place(self, description: Option<&str>) -> (Option<&str>, Option<&str>) {
+        match self {
+            Self::Hidden => (None, None),
+            Self::Inline => (description, None),
+            Self::Tooltip => (None, description),
+        }
+    }
+}
+
+/// Natural width of a name label's row, as if the (ellipsizing)
+/// description never asked for more room than the name.
+fn row_content_width(name_label: &Label) -> i32 {
+    let natural = |widget: &gtk4::Widget| widget.measure(Orientation::Horizontal, -1).1;
+    let name_width = natural(name_label.upcast_ref());
+    let (Some(text_box), Some(row)) = (
+        name_label.parent(),
+        name_label.ancestor(ListBoxRow::static_type()),
+    ) else {
+        return name_width;
+    };
+    natural(&row) - natural(&text_box) + name_width
+}
+
+/// Window width for `width_mode = "content"`: wide enough for the widest
+/// row, but never narrower than `min_width` or wider than `max_width`.
+fn content_width(row_widths: &[i32], min_width: i32, max_width: i32) -> i32 {
+    let widest = row_widths.iter().copied().max().unwrap_or(0);
+    widest.clamp(min_width, max_width.max(min_width))
+}
+
+/// Scrolls back to the top after the list is repopulated, selecting the
+/// first row when Enter launches it anyway (`general.enter_launches_top`).
+fn reset_selection(list_box: &ListBox, select_top: bool) {
+    if let Some(row) = result_row(list_box, 0).filter(|_| select_top) {
+        list_box.select_row(Some(&row));
+    }
+    if let Some(adj) = list_box.adjustment() {
+        adj.set_value(0.0);
+    }
+}
+
+/// Row to select once the list is rebuilt: wherever the app `selected`
+/// (its `App::identity`) is now listed, or the row it was on, clamped to the
+/// list, if it's gone.
+fn restored_row(identities: &[String], selected: &str, previous_row: usize) -> Option<usize> {
+    identities.iter().position(|id| id == selected).or_else(|| {
+        identities
+            .len()
+            .checked_sub(1)
+            .map(|last| previous_row.min(last))
+    })
+}
+
+/// The last app launched from this window, so a held Enter or a double
+/// click doesn't start it twice (`general.launch_debounce_ms`).
+#[derive(Debug, Default)]
+struct LastLaunch {
+    launched: Option<(String, i64)>,
+}
+
+impl LastLaunch {
+    /// Whether `app` may launch at `now_ms`: not if it was itself launched
+    /// less than `debounce_ms` ago. Allowed launches are remembered.
+    fn allows(&mut self, app: &App, now_ms: i64, debounce_ms: u64) -> bool {
+        let identity = app.identity();
+        let repeated = self.launched.as_ref().is_some_and(|(last, at)| {
+            last == identity && now_ms.saturating_sub(*at) < debounce_ms as i64
+        });
+        if !repeated {
+            self.launched = Some((identity.to_string(), now_ms));
+        }
+        !repeated
+    }
+}
+
+/// Two-step launch for `confirm = true` apps: the first activation arms
+/// the row, activating the same row again launches it, and any other key in
+/// between disarms.
+#[derive(Debug, Default)]
+struct Confirmation {
+    armed: Option<usize>,
+}
+
+impl Confirmation {
+    /// Whether activating `row` should launch now; if not, it's now armed.
+    fn activate(&mut self, row: usize, needs_confirm: bool) -> bool {
+        let confirmed = self.armed.take() == Some(row);
+        if confirmed || !needs_confirm {
+            return true;
+        }
+        self.armed = Some(row);
+        false
+    }
+
+    /// Disarms; returns whether a launch was waiting.
+    fn cancel(&mut self) -> bool {
+        self.armed.take().is_some()
+    }
+}
+
+/// Keys that don't disarm a pending confirmation: the ones that activate a
+/// row (Enter, Alt+1-9) and bare modifiers on their way to Alt+N.
+fn keeps_confirmation(key: gtk4::gdk::Key, modifiers: ModifierType) -> bool {
+    use gtk4::gdk::Key;
+    let quick_launch = modifiers.contains(ModifierType::ALT_MASK)
+        && key.to_unicode().is_some_and(|c| ('1'..='9').contains(&c));
+    quick_launch
+        || is_enter(key)
+        || matches!(
+            key,
+            Key::Shift_L
+                | Key::Shift_R
+                | Key::Control_L
+                | Key::Control_R
+                | Key::Alt_L
+                | Key::Alt_R
+                | Key::Super_L
+                | Key::Super_R
+                | Key::Meta_L
+                | Key::Meta_R
+        )
+}
+
+fn is_enter(key: gtk4::gdk::Key) -> bool {
+    use gtk4::gdk::Key;
+    matches!(key, Key::Return | Key::KP_Enter | Key::ISO_Enter)
+}
+
+/// Result rows in the list, not counting `appearance.fixed_list_size`
+/// padding (which always comes last).
+fn row_count(list_box: &ListBox) -> usize {
+    result_rows(list_box).len()
+}
+
+/// The rows showing results, in order; section headers and padding rows
+/// can't be selected and aren't counted. Keyboard navigation, Alt+N and
+/// the lists behind the rows all go by position among these.
+fn result_rows(list_box: &ListBox) -> Vec<ListBoxRow> {
+    (0..)
+        .map_while(|i| list_box.row_at_index(i))
+        .filter(|row| row.is_selectable())
+        .collect()
+}
+
+/// Indices of the selectable entries in `selectable`.
+fn selectable_positions(selectable: &[bool]) -> Vec<usize> {
+    (0..selectable.len()).filter(|&i| selectable[i]).collect()
+}
+
+/// The row showing result `position`.
+fn result_row(list_box: &ListBox, position: usize) -> Option<ListBoxRow> {
+    result_rows(list_box).into_iter().nth(position)
+}
+
+/// Result position of `row`, or `None` for a header or padding row.
+fn result_position(list_box: &ListBox, row: &ListBoxRow) -> Option<usize> {
+    row.is_selectable().then(|| results_before(list_box, row))
+}
+
+/// Result rows above `row`.
+fn results_before(list_box: &ListBox, row: &ListBoxRow) -> usize {
+    (0..row.index())
+        .filter_map(|i| list_box.row_at_index(i))
+        .filter(|row| row.is_selectable())
+        .count()
+}
+
+/// Row Enter activates: the selected one, else the top result when
+/// `launches_top`. An empty list has nothing to launch; the
+/// `search.no_match_action` fallback, when set, is a row like any other.
+fn enter_target(selected: Option<usize>, rows: usize, launches_top: bool) -> Option<usize> {
+    match selected {
+        Some(row) if row < rows => Some(row),
+        _ => (launches_top && rows > 0).then_some(0),
+    }
+}
+
+fn move_selection(list_box: &ListBox, delta: isize, wrap: bool) {
+    let rows: Vec<ListBoxRow> = (0..).map_while(|i| list_box.row_at_index(i)).collect();
+    let selectable: Vec<bool> = rows.iter().map(|row| row.is_selectable()).collect();
+    let current = list_box.selected_row().map(|row| row.index() as usize);
+    let Some(new_idx) = step_row(&selectable, current, delta, wrap) else {
+        return;
+    };
+    if let Some(row) = rows.get(new_idx) {
+        list_box.select_row(Some(row));
+        scroll_row_into_view(list_box, row);
+    }
+}
+
+/// Row `delta` results away from row `current`, skipping the rows that
+/// aren't `selectable` (section headers, padding); see `step_selection`.
+fn step_row(
+    selectable: &[bool],
+    current: Option<usize>,
+    delta: isize,
+    wrap: bool,
+) -> Option<usize> {
+    let results = selectable_positions(selectable);
+    let position = current.and_then(|row| results.iter().position(|&r| r == row));
+    step_selection(position, delta, results.len(), wrap).map(|position| results[position])
+}
+
+/// Row `delta` rows away from `current` in a list of `len`, stopping at the
+/// ends. With `wrap`, a move that starts at an end goes round to the other
+/// one, so paging stops at the last row before wrapping to the first.
+fn step_selection(current: Option<usize>, delta: isize, len: usize, wrap: bool) -> Option<usize> {
+    let last = len.checked_sub(1)?;
+    match current {
+        Some(0) if wrap && delta < 0 => Some(last),
+        Some(current) if wrap && delta > 0 && current == last => Some(0),
+        Some(current) => Some(current.saturating_add_signed(delta).min(last)),
+        None => Some(delta.saturating_sub(1).clamp(0, last as isize) as usize),
+    }
+}
+
+fn scroll_row_into_view(list_box: &ListBox, row: &ListBoxRow) {
+    let Some(adj) = list_box.adjustment() else {
+        return;
+    };
+    let alloc = row.allocation();
+    let row_top = alloc.y() as f64;
+    let row_bottom = row_top + alloc.height() as f64;
+
+    if row_top < adj.value() {
+        adj.set_value(row_top);
+    } else if row_bottom > adj.value() + adj.page_size() {
+        adj.set_value(row_bottom - adj.page_size());
+    }
+}
+
+fn monotonic_ms() -> i64 {
+    glib::monotonic_time() / 1000
+}
+
+/// Whether the query has been quiet for the full debounce delay, i.e. a
+/// pending refresh should run now rather than wait for a later keystroke's
+/// timeout.
+fn debounce_elapsed(last_change_ms: i64, now_ms: i64, delay_ms: u64) -> bool {
+    now_ms.saturating_sub(last_change_ms) >= delay_ms as i64
+}
+
+/// The stylesheet is the theme's when one is set, else the user's
+/// `style.css`, else the bundled default.
+fn load_css(theme: Option<&str>) -> CssProvider {
+    let provider = CssProvider::new();
+
+    let theme_style = theme
+        .and_then(Config::theme_dir)
+        .map(|dir| dir.join(crate::theme::STYLE_FILE))
+        .filter(|p| p.exists());
+    let style = theme_style.or_else(|| Config::user_style_path().filter(|p| p.exists()));
+    match style {
+        Some(path) => provider.load_from_path(&path),
+        None => provider.load_from_data(DEFAULT_STYLE),
+    }
+
+    gtk4::style_context_add_provider_for_display(
+        &Display::default().expect("Could not get default display"),
+        &provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER,
+    );
+    provider
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SearchConfig;
+
+    fn plain_apps(names: &[&str]) -> Vec<App> {
+        names.iter().map(|n| App::plain(n.to_string())).collect()
+    }
+
+    #[test]
+    fn pasted_lines_are_joined_or_cut_to_the_first() {
+        let pasted = "cd ~/src/yeet &&\r\n  cargo build\n\ncargo test\n";
+        assert_eq!(
+            single_line(pasted, MultilinePaste::Join).as_deref(),
+            Some("cd ~/src/yeet && cargo build cargo test")
+        );
+        assert_eq!(
+            single_line(pasted, MultilinePaste::FirstLine).as_deref(),
+            Some("cd ~/src/yeet &&")
+        );
+        assert_eq!(
+            single_line("firefox\n", MultilinePaste::Join).as_deref(),
+            Some("firefox")
+        );
+        assert_eq!(
+            single_line("\n\n", MultilinePaste::FirstLine).as_deref(),
+            Some("")
+        );
+        assert_eq!(single_line("fire fox", MultilinePaste::Join), None);
+    }
+
+    const DEFAULT_ORDER: &[ListOrder] =
+        &[ListOrder::Favorites, ListOrder::Recent, ListOrder::Alpha];
+    const NO_FAVORITES: &[ListOrder] = &[ListOrder::Recent, ListOrder::Alpha];
+
+    #[test]
+    fn initial_indices_surfaces_recent_apps_beyond_first_n() {
+        // Alphabetical list; "zoom" was launched recently but sorts last.
+        let apps = plain_apps(&["alpha", "beta", "gamma", "zoom"]);
+        let mut history = HashMap::new();
+        history.insert("zoom".to_string(), 1000);
+
+        let indices = initial_indices(&apps, &history, 2, DEFAULT_ORDER);
+        assert_eq!(indices, vec![3, 0]);
     }
 
     #[test]
@@ -509,21 +1982,603 @@ mod tests {
         let mut history = HashMap::new();
         history.insert("recent".to_string(), 1000);
 
-        let indices = initial_indices(&apps, &history, 2);
+        let indices = initial_indices(&apps, &history, 2, DEFAULT_ORDER);
         assert_eq!(indices, vec![0, 2]);
     }
 
+    #[test]
+    fn initial_indices_without_favorites_first_is_alphabetical() {
+        // discovery order: favorites hoisted above the alphabetical rest
+        let mut apps = plain_apps(&["zeta", "alpha", "beta"]);
+        apps[0].favorite = true;
+
+        let indices = initial_indices(&apps, &HashMap::new(), 0, NO_FAVORITES);
+        assert_eq!(indices, vec![1, 2, 0]);
+
+        let indices = initial_indices(&apps, &HashMap::new(), 0, DEFAULT_ORDER);
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn initial_indices_without_favorites_first_ranks_by_history_only() {
+        let mut apps = plain_apps(&["fav", "other", "recent"]);
+        apps[0].favorite = true;
+        let mut history = HashMap::new();
+        history.insert("recent".to_string(), 1000);
+
+        let indices = initial_indices(&apps, &history, 2, NO_FAVORITES);
+        assert_eq!(indices, vec![2, 0]);
+    }
+
+    #[test]
+    fn initial_indices_applies_order_keys_in_turn() {
+        // Discovery order: the favorite hoisted, then alphabetical.
+        let mut apps = plain_apps(&["yak", "alpha", "beta", "gamma"]);
+        apps[0].favorite = true;
+        let history = HashMap::from([("gamma".to_string(), 2000), ("yak".to_string(), 1000)]);
+
+        let order = [ListOrder::Recent, ListOrder::Favorites, ListOrder::Alpha];
+        assert_eq!(initial_indices(&apps, &history, 0, &order), [3, 0, 1, 2]);
+
+        let order = [ListOrder::Alpha];
+        assert_eq!(initial_indices(&apps, &history, 0, &order), [1, 2, 3, 0]);
+
+        // Ties left by the keys keep discovery order.
+        let order = [ListOrder::Favorites];
+        assert_eq!(initial_indices(&apps, &history, 0, &order), [0, 1, 2, 3]);
+        assert_eq!(initial_indices(&apps, &history, 0, &[]), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn sections_follow_the_order_keys() {
+        let mut apps = plain_apps(&["fav", "both", "recent", "zed"]);
+        apps[0].favorite = true;
+        apps[1].favorite = true;
+        let history = HashMap::from([("both".to_string(), 2000), ("recent".to_string(), 1000)]);
+
+        let order = [ListOrder::Recent, ListOrder::Favorites, ListOrder::Alpha];
+        let indices = initial_indices(&apps, &history, 0, &order);
+        assert_eq!(indices, [1, 2, 0, 3]);
+        assert_eq!(
+            section_headers(&apps, &indices, &history, &order, true),
+            [(0, "Recent"), (2, "Favorites"), (3, "All")]
+        );
+
+        // Keys after alpha can't group anything.
+        let order = [ListOrder::Alpha, ListOrder::Recent];
+        let indices = initial_indices(&apps, &history, 0, &order);
+        assert!(section_headers(&apps, &indices, &history, &order, true).is_empty());
+    }
+
+    #[test]
+    fn favorites_in_list_off_drops_the_favorites_key() {
+        assert_eq!(list_order(DEFAULT_ORDER, true), DEFAULT_ORDER);
+        assert_eq!(list_order(DEFAULT_ORDER, false), NO_FAVORITES);
+    }
+
+    #[test]
+    fn empty_and_search_lists_have_their_own_limits() {
+        let search = SearchConfig::default();
+        let (initial_results, max_results) = (2, 3);
+        let apps = plain_apps(&["term", "terminal", "termius", "terraform", "zed"]);
+        let texts: Vec<SearchText> = apps
+            .iter()
+            .map(|app| SearchText::new(&app.name, &app.keywords))
+            .collect();
+        let history = HashMap::new();
+
+        let empty = initial_indices(&apps, &history, initial_results, DEFAULT_ORDER);
+        assert_eq!(empty, [0, 1]);
+
+        let matcher = Matcher::new(search.matcher);
+        let ranked = rank(&texts, &matcher, "ter", &search, &history, 0);
+        assert_eq!(ranked.len(), 4);
+        assert_eq!(search_indices(ranked, max_results).len(), 3);
+    }
+
+    #[test]
+    fn activation_tokens_are_handed_out_once() {
+        let mut tokens = ActivationTokens::default();
+        let launches = ["a-1", "a-2", "a-2", "a-3", "a-1"].map(|t| Some(t.to_string()));
+        let handed: Vec<Option<String>> = launches.into_iter().map(|t| tokens.claim(t)).collect();
+        assert_eq!(
+            handed,
+            [
+                Some("a-1".to_string()),
+                Some("a-2".to_string()),
+                None,
+                Some("a-3".to_string()),
+                None
+            ]
+        );
+        assert_eq!(tokens.claim(None), None);
+    }
+
     #[test]
     fn initial_indices_zero_count_shows_all() {
         let apps = plain_apps(&["a", "b", "c"]);
-        let indices = initial_indices(&apps, &HashMap::new(), 0);
+        let indices = initial_indices(&apps, &HashMap::new(), 0, DEFAULT_ORDER);
         assert_eq!(indices, vec![0, 1, 2]);
     }
 
+    fn connectors(names: &[&str]) -> Vec<Option<String>> {
+        names.iter().map(|n| Some(n.to_string())).collect()
+    }
+
+    #[test]
+    fn monitor_index_resolves_connector_name() {
+        let connectors = connectors(&["eDP-1", "DP-1", "HDMI-A-1"]);
+        let selector = MonitorSelector::Connector("HDMI-A-1".into());
+        assert_eq!(
+            monitor_index(&selector, &connectors, MonitorFallback::Active),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn missing_monitor_falls_back_to_active_monitor() {
+        let connectors = connectors(&["eDP-1", "DP-1"]);
+        let missing = MonitorSelector::Connector("DP-2".into());
+        assert_eq!(
+            monitor_index(&missing, &connectors, MonitorFallback::Active),
+            None
+        );
+        assert_eq!(
+            monitor_index(
+                &MonitorSelector::Index(5),
+                &connectors,
+                MonitorFallback::Active
+            ),
+            None
+        );
+        assert_eq!(
+            monitor_index(
+                &MonitorSelector::Index(1),
+                &connectors,
+                MonitorFallback::Active
+            ),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn clamp_fallback_picks_nearest_monitor_index() {
+        let two = connectors(&["eDP-1", "DP-1"]);
+        let clamp = |index: u32, connectors: &[Option<String>]| {
+            monitor_index(
+                &MonitorSelector::Index(index),
+                connectors,
+                MonitorFallback::Clamp,
+            )
+        };
+        assert_eq!(clamp(0, &two), Some(0));
+        assert_eq!(clamp(1, &two), Some(1));
+        assert_eq!(clamp(2, &two), Some(1));
+        assert_eq!(clamp(7, &two), Some(1));
+        assert_eq!(clamp(0, &[]), None);
+
+        // A connector name has no nearest monitor.
+        let missing = MonitorSelector::Connector("DP-2".into());
+        assert_eq!(monitor_index(&missing, &two, MonitorFallback::Clamp), None);
+    }
+
+    #[test]
+    fn accessible_description_says_why_a_row_matched() {
+        let keyword = MatchedOn::Keyword("browser".to_string());
+        assert_eq!(
+            accessible_description(Some("Web Browser"), Some(&keyword)),
+            "Web Browser, matches keyword browser"
+        );
+        assert_eq!(
+            accessible_description(None, Some(&MatchedOn::Name)),
+            "matches name"
+        );
+        assert_eq!(
+            accessible_description(Some("Web Browser"), None),
+            "Web Browser"
+        );
+        // Text is passed through as is, never as escaped markup.
+        assert_eq!(
+            accessible_description(Some("Tom & Jerry <3"), None),
+            "Tom & Jerry <3"
+        );
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn selection_follows_app_when_rows_are_inserted_above() {
+        let before = ids(&["firefox", "kitty", "gimp"]);
+        let selected = restored_row(&before, "kitty", 1).unwrap();
+        assert_eq!(before[selected], "kitty");
+
+        let after = ids(&["alacritty", "blender", "firefox", "kitty", "gimp"]);
+        let selected = restored_row(&after, "kitty", 1).unwrap();
+        assert_eq!(selected, 3);
+        assert_eq!(after[selected], "kitty");
+    }
+
+    #[test]
+    fn selection_clamps_when_app_is_gone() {
+        let after = ids(&["firefox", "gimp"]);
+        assert_eq!(restored_row(&after, "kitty", 1), Some(1));
+        assert_eq!(restored_row(&after, "kitty", 5), Some(1));
+        assert_eq!(restored_row(&[], "kitty", 0), None);
+    }
+
+    #[test]
+    fn row_layout_splits_spacing_around_rows() {
+        assert_eq!(RowLayout::new(56, 0).margins(), (0, 0));
+        assert_eq!(RowLayout::new(56, 6).margins(), (3, 3));
+        assert_eq!(RowLayout::new(56, 5).margins(), (2, 3));
+        assert_eq!(RowLayout::new(56, -4).margins(), (0, 0));
+    }
+
+    #[test]
+    fn padding_fills_short_lists_up_to_fixed_size() {
+        assert_eq!(padding_rows(3, 8), 5);
+        assert_eq!(padding_rows(0, 8), 8);
+        assert_eq!(padding_rows(8, 8), 0);
+        assert_eq!(padding_rows(20, 8), 0);
+        assert_eq!(padding_rows(3, 0), 0);
+    }
+
+    #[test]
+    fn list_height_counts_spacing_per_row() {
+        assert_eq!(RowLayout::new(56, 0).list_height(8), 448);
+        assert_eq!(RowLayout::new(40, 6).list_height(8), 368);
+        assert_eq!(RowLayout::new(40, 6).list_height(0), 0);
+    }
+
+    #[test]
+    fn description_style_follows_settings() {
+        assert_eq!(
+            DescriptionStyle::from_settings(true, false),
+            DescriptionStyle::Inline
+        );
+        assert_eq!(
+            DescriptionStyle::from_settings(true, true),
+            DescriptionStyle::Tooltip
+        );
+        assert_eq!(
+            DescriptionStyle::from_settings(false, true),
+            DescriptionStyle::Hidden
+        );
+    }
+
+    #[test]
+    fn description_goes_inline_or_to_tooltip() {
+        let desc = Some("Web Browser");
+        assert_eq!(DescriptionStyle::Inline.place(desc), (desc, None));
+        assert_eq!(DescriptionStyle::Tooltip.place(desc), (None, desc));
+        assert_eq!(DescriptionStyle::Hidden.place(desc), (None, None));
+        assert_eq!(DescriptionStyle::Tooltip.place(None), (None, None));
+    }
+
+    #[test]
+    fn content_width_fits_widest_row() {
+        assert_eq!(content_width(&[320, 610, 480], 500, 900), 610);
+    }
+
+    #[test]
+    fn content_width_never_shrinks_below_min() {
+        assert_eq!(content_width(&[120, 200], 500, 900), 500);
+        assert_eq!(content_width(&[], 500, 900), 500);
+    }
+
+    #[test]
+    fn content_width_caps_at_max() {
+        assert_eq!(content_width(&[1400], 500, 900), 900);
+        // a max below the configured width never shrinks the window
+        assert_eq!(content_width(&[1400], 500, 300), 500);
+    }
+
+    #[test]
+    fn parses_cancel_key_combos() {
+        assert_eq!(
+            KeyCombo::parse("Escape"),
+            Some(KeyCombo {
+                modifiers: ModifierType::empty(),
+                key: "Escape".into()
+            })
+        );
+        assert_eq!(
+            KeyCombo::parse("ctrl+c"),
+            Some(KeyCombo {
+                modifiers: ModifierType::CONTROL_MASK,
+                key: "c".into()
+            })
+        );
+        assert_eq!(
+            KeyCombo::parse("Ctrl+["),
+            Some(KeyCombo {
+                modifiers: ModifierType::CONTROL_MASK,
+                key: "bracketleft".into()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers_and_empty_keys() {
+        assert_eq!(KeyCombo::parse("hyper+c"), None);
+        assert_eq!(KeyCombo::parse("ctrl+"), None);
+        assert_eq!(KeyCombo::parse(""), None);
+    }
+
+    #[test]
+    fn key_combo_requires_exact_modifiers() {
+        let ctrl_c = KeyCombo::parse("ctrl+c").unwrap();
+        assert!(ctrl_c.matches("c", ModifierType::CONTROL_MASK));
+        assert!(!ctrl_c.matches("c", ModifierType::empty()));
+        assert!(!ctrl_c.matches("c", ModifierType::CONTROL_MASK | ModifierType::ALT_MASK));
+
+        // lock/button state doesn't count as a modifier
+        let escape = KeyCombo::parse("esc").unwrap();
+        assert!(escape.matches("Escape", ModifierType::LOCK_MASK));
+        assert!(!escape.matches("Escape", ModifierType::SHIFT_MASK));
+    }
+
+    #[test]
+    fn launch_closes_window_by_default() {
+        assert_eq!(after_launch(false), AfterLaunch::Close);
+    }
+
+    #[test]
+    fn stay_open_resets_the_list_instead_of_closing() {
+        let AfterLaunch::Reset(reset) = after_launch(true) else {
+            panic!("the window closed");
+        };
+        assert_eq!(reset.query, "");
+        assert!(reset.rebuild_list);
+        assert!(reset.focus_entry);
+    }
+
+    /// Replays keystrokes at the given times, firing each one's timeout
+    /// `delay_ms` later, and returns which keystrokes triggered a refresh.
+    fn debounced_refreshes(keystrokes_ms: &[i64], delay_ms: u64) -> Vec<usize> {
+        let mut refreshed = Vec::new();
+        for (i, fire_at) in keystrokes_ms
+            .iter()
+            .map(|t| t + delay_ms as i64)
+            .enumerate()
+        {
+            let last_change = keystrokes_ms
+                .iter()
+                .copied()
+                .filter(|&t| t <= fire_at)
+                .max()
+                .unwrap();
+            if debounce_elapsed(last_change, fire_at, delay_ms) {
+                refreshed.push(i);
+            }
+        }
+        refreshed
+    }
+
+    #[test]
+    fn debounce_coalesces_rapid_keystrokes() {
+        let refreshed = debounced_refreshes(&[0, 30, 60, 90], 100);
+        assert_eq!(refreshed, vec![3]);
+    }
+
+    #[test]
+    fn debounce_refreshes_after_each_pause() {
+        let refreshed = debounced_refreshes(&[0, 30, 200, 250, 500], 100);
+        assert_eq!(refreshed, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn debounce_final_keystroke_always_refreshes() {
+        let keystrokes: Vec<i64> = (0..20).map(|i| i * 10).collect();
+        let refreshed = debounced_refreshes(&keystrokes, 50);
+        assert_eq!(refreshed.last(), Some(&(keystrokes.len() - 1)));
+    }
+
+    #[test]
+    fn second_launch_of_same_app_is_debounced() {
+        let firefox = App::plain("Firefox".to_string());
+        let kitty = App::plain("Kitty".to_string());
+        let mut last = LastLaunch::default();
+
+        assert!(last.allows(&firefox, 1_000, 500));
+        assert!(!last.allows(&firefox, 1_200, 500));
+        // Blocked attempts don't extend the window.
+        assert!(!last.allows(&firefox, 1_499, 500));
+        assert!(last.allows(&firefox, 1_500, 500));
+        // Another app isn't held back.
+        assert!(last.allows(&kitty, 1_600, 500));
+        assert!(last.allows(&firefox, 1_700, 500));
+    }
+
+    #[test]
+    fn zero_launch_debounce_allows_every_launch() {
+        let firefox = App::plain("Firefox".to_string());
+        let mut last = LastLaunch::default();
+        assert!(last.allows(&firefox, 1_000, 0));
+        assert!(last.allows(&firefox, 1_000, 0));
+    }
+
+    #[test]
+    fn sections_follow_favorites_recent_rest() {
+        let mut apps = plain_apps(&["fav", "other", "recent", "zed"]);
+        apps[0].favorite = true;
+        let mut history = HashMap::new();
+        history.insert("recent".to_string(), 1000);
+
+        let indices = initial_indices(&apps, &history, 0, DEFAULT_ORDER);
+        assert_eq!(indices, vec![0, 2, 1, 3]);
+        assert_eq!(
+            section_headers(&apps, &indices, &history, DEFAULT_ORDER, true),
+            [(0, "Favorites"), (1, "Recent"), (2, "All")]
+        );
+        assert!(section_headers(&apps, &indices, &history, DEFAULT_ORDER, false).is_empty());
+    }
+
+    #[test]
+    fn single_section_gets_no_header() {
+        let apps = plain_apps(&["a", "b"]);
+        let indices = initial_indices(&apps, &HashMap::new(), 0, DEFAULT_ORDER);
+        assert!(section_headers(&apps, &indices, &HashMap::new(), DEFAULT_ORDER, true).is_empty());
+    }
+
+    #[test]
+    fn navigation_skips_section_headers() {
+        // Favorites header, fav, Recent header, recent, All header, a, b
+        let selectable = [false, true, false, true, false, true, true];
+
+        assert_eq!(step_row(&selectable, Some(1), 1, false), Some(3));
+        assert_eq!(step_row(&selectable, Some(3), 1, false), Some(5));
+        assert_eq!(step_row(&selectable, Some(5), -1, false), Some(3));
+        assert_eq!(step_row(&selectable, Some(1), -1, false), Some(1));
+        assert_eq!(step_row(&selectable, Some(1), -1, true), Some(6));
+        assert_eq!(step_row(&selectable, Some(6), 1, true), Some(1));
+        // Nothing selected: Down lands on the first result, not a header.
+        assert_eq!(step_row(&selectable, None, 1, false), Some(1));
+        assert_eq!(step_row(&selectable, Some(1), 8, false), Some(6));
+        assert_eq!(step_row(&[false, false], None, 1, false), None);
+    }
+
     #[test]
     fn initial_indices_preserves_order_without_history() {
         let apps = plain_apps(&["a", "b", "c"]);
-        let indices = initial_indices(&apps, &HashMap::new(), 2);
+        let indices = initial_indices(&apps, &HashMap::new(), 2, DEFAULT_ORDER);
         assert_eq!(indices, vec![0, 1]);
     }
+
+    #[test]
+    fn step_selection_stops_at_ends_without_wrap() {
+        assert_eq!(step_selection(Some(0), -1, 5, false), Some(0));
+        assert_eq!(step_selection(Some(4), 1, 5, false), Some(4));
+        assert_eq!(step_selection(Some(2), 1, 5, false), Some(3));
+        assert_eq!(step_selection(Some(2), -8, 5, false), Some(0));
+        assert_eq!(step_selection(Some(2), 8, 5, false), Some(4));
+    }
+
+    #[test]
+    fn step_selection_wraps_at_ends() {
+        assert_eq!(step_selection(Some(0), -1, 5, true), Some(4));
+        assert_eq!(step_selection(Some(4), 1, 5, true), Some(0));
+        assert_eq!(step_selection(Some(2), 1, 5, true), Some(3));
+    }
+
+    #[test]
+    fn paging_stops_at_the_end_before_wrapping() {
+        assert_eq!(step_selection(Some(2), 8, 5, true), Some(4));
+        assert_eq!(step_selection(Some(4), 8, 5, true), Some(0));
+        assert_eq!(step_selection(Some(2), -8, 5, true), Some(0));
+        assert_eq!(step_selection(Some(0), -8, 5, true), Some(4));
+    }
+
+    #[test]
+    fn step_selection_without_selection_or_rows() {
+        assert_eq!(step_selection(None, 1, 5, true), Some(0));
+        assert_eq!(step_selection(None, -1, 5, true), Some(0));
+        assert_eq!(step_selection(Some(0), 1, 0, true), None);
+    }
+
+    #[test]
+    fn long_lists_build_one_batch_up_front() {
+        assert_eq!(first_batch(8, 0), 8);
+        assert_eq!(first_batch(5_000, 0), ROW_BATCH);
+        assert_eq!(first_batch(ROW_BATCH, 0), ROW_BATCH);
+        // A list padded to fixed_rows is always built whole.
+        assert_eq!(first_batch(150, 200), 150);
+    }
+
+    #[test]
+    fn next_batch_is_due_within_a_page_of_the_end() {
+        // 400px of rows in a 100px view.
+        assert!(!near_end(0.0, 100.0, 400.0));
+        assert!(!near_end(199.0, 100.0, 400.0));
+        assert!(near_end(200.0, 100.0, 400.0));
+        assert!(near_end(300.0, 100.0, 400.0));
+        // Not laid out yet.
+        assert!(!near_end(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn keyboard_moves_need_rows_up_to_their_target() {
+        assert_eq!(position_needed(Some(98), 1, false), 99);
+        assert_eq!(position_needed(Some(95), 8, true), 103);
+        assert_eq!(position_needed(None, 1, false), 1);
+        assert_eq!(position_needed(Some(5), -8, false), 0);
+        // Wrapping up from the top goes to the very last result.
+        assert_eq!(position_needed(Some(0), -1, true), usize::MAX);
+        assert_eq!(position_needed(Some(0), -1, false), 0);
+    }
+
+    #[test]
+    fn enter_launches_selected_row() {
+        assert_eq!(enter_target(Some(3), 8, true), Some(3));
+        assert_eq!(enter_target(Some(3), 8, false), Some(3));
+    }
+
+    #[test]
+    fn enter_launches_top_without_selection() {
+        assert_eq!(enter_target(None, 8, true), Some(0));
+        assert_eq!(enter_target(None, 8, false), None);
+    }
+
+    #[test]
+    fn enter_with_single_result() {
+        assert_eq!(enter_target(None, 1, true), Some(0));
+        assert_eq!(enter_target(Some(0), 1, false), Some(0));
+    }
+
+    #[test]
+    fn enter_on_empty_list_does_nothing() {
+        assert_eq!(enter_target(None, 0, true), None);
+        assert_eq!(enter_target(Some(2), 0, true), None);
+    }
+
+    #[test]
+    fn confirm_needs_second_activation() {
+        let mut confirmation = Confirmation::default();
+        assert!(!confirmation.activate(2, true));
+        assert!(confirmation.activate(2, true));
+        // Launching disarms again.
+        assert!(!confirmation.activate(2, true));
+    }
+
+    #[test]
+    fn confirm_is_cancelled_by_other_keys() {
+        let mut confirmation = Confirmation::default();
+        assert!(!confirmation.activate(2, true));
+        assert!(confirmation.cancel());
+        assert!(!confirmation.activate(2, true));
+        assert!(confirmation.cancel());
+        assert!(!confirmation.cancel());
+    }
+
+    #[test]
+    fn confirm_rearms_for_a_different_row() {
+        let mut confirmation = Confirmation::default();
+        assert!(!confirmation.activate(2, true));
+        assert!(!confirmation.activate(3, true));
+        assert!(confirmation.activate(3, true));
+    }
+
+    #[test]
+    fn apps_without_confirm_launch_at_once() {
+        let mut confirmation = Confirmation::default();
+        assert!(confirmation.activate(0, false));
+        assert!(!confirmation.activate(1, true));
+        assert!(confirmation.activate(0, false));
+        assert!(!confirmation.cancel());
+    }
+
+    #[test]
+    fn enter_and_quick_launch_keep_confirmation() {
+        use gtk4::gdk::Key;
+        let none = ModifierType::empty();
+        assert!(keeps_confirmation(Key::Return, none));
+        assert!(keeps_confirmation(Key::KP_Enter, none));
+        assert!(keeps_confirmation(Key::Alt_L, none));
+        assert!(keeps_confirmation(Key::_3, ModifierType::ALT_MASK));
+        assert!(!keeps_confirmation(Key::_3, none));
+        assert!(!keeps_confirmation(Key::a, none));
+        assert!(!keeps_confirmation(Key::Down, none));
+    }
 }
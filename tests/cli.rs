@@ -0,0 +1,96 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn yeet() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_yeet"))
+}
+
+#[test]
+fn help_exits_zero() {
+    let status = yeet().arg("--help").stdout(Stdio::null()).status().unwrap();
+    assert_eq!(status.code(), Some(0));
+}
+
+#[test]
+fn unknown_option_exits_two() {
+    let status = yeet()
+        .arg("--bogus")
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn print_only_conflicts_with_dmenu() {
+    for flag in ["--print-only", "--print-id"] {
+        let status = yeet()
+            .args(["--dmenu", flag])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert_eq!(status.code(), Some(2), "{flag}");
+    }
+}
+
+#[test]
+fn bad_format_template_exits_two() {
+    for template in ["{nmae}", "{name"] {
+        let status = yeet()
+            .args(["--dmenu", "--format", template])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert_eq!(status.code(), Some(2), "{template}");
+    }
+}
+
+#[test]
+fn dmenu_without_items_exits_one() {
+    let mut child = yeet()
+        .arg("--dmenu")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"\n\n").unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(1));
+}
+
+/// A config home holding `config.toml` with `contents`.
+fn config_home(name: &str, contents: &str) -> std::path::PathBuf {
+    let home = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(home.join("yeet")).unwrap();
+    std::fs::write(home.join("yeet").join("config.toml"), contents).unwrap();
+    home
+}
+
+#[test]
+fn invalid_config_exits_two() {
+    let home = config_home("yeet_cli_bad_config", "[general\nmax_results = 8\n");
+    let status = yeet()
+        .args(["--score", "fire"])
+        .env("XDG_CONFIG_HOME", &home)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+    let _ = std::fs::remove_dir_all(&home);
+}
+
+#[test]
+fn launch_of_unknown_id_exits_two() {
+    let home = config_home("yeet_cli_unknown_id", "");
+    let status = yeet()
+        .args(["--launch", "no.such.app.yeet-test"])
+        .env("XDG_CONFIG_HOME", &home)
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+    let _ = std::fs::remove_dir_all(&home);
+}